@@ -0,0 +1,62 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use std::path::PathBuf;
+use syn::{Error, LitStr};
+use toml::{Table, Value};
+
+/// Offset database of a firmware, which is a TOML file relative to the crate root.
+pub struct Database {
+    path: LitStr,
+    table: Table,
+}
+
+impl Database {
+    pub fn load(path: LitStr) -> syn::Result<Self> {
+        // Read the file.
+        let root = std::env::var_os("CARGO_MANIFEST_DIR")
+            .ok_or_else(|| Error::new_spanned(&path, "CARGO_MANIFEST_DIR is not set"))?;
+        let file = PathBuf::from(root).join(path.value());
+        let data = std::fs::read_to_string(&file).map_err(|e| {
+            Error::new_spanned(&path, format!("couldn't read {}: {}", file.display(), e))
+        })?;
+
+        // Parse.
+        let table: Table = data.parse().map_err(|e| {
+            Error::new_spanned(&path, format!("couldn't parse {}: {}", file.display(), e))
+        })?;
+
+        for k in table.keys() {
+            if !matches!(k.as_str(), "consts" | "offsets" | "structs") {
+                return Err(Error::new_spanned(&path, format!("unknown table `{k}`")));
+            }
+        }
+
+        Ok(Self { path, table })
+    }
+
+    pub fn path(&self) -> &LitStr {
+        &self.path
+    }
+
+    /// Returns a table in the root of the database.
+    pub fn table(&self, name: &str) -> syn::Result<Option<&Table>> {
+        match self.table.get(name) {
+            Some(Value::Table(v)) => Ok(Some(v)),
+            Some(_) => Err(self.error(format!("`{name}` must be a table"))),
+            None => Ok(None),
+        }
+    }
+
+    pub fn error(&self, msg: impl std::fmt::Display) -> Error {
+        Error::new_spanned(&self.path, msg)
+    }
+
+    /// Returns an item to make the crate rebuild when the database is changed.
+    pub fn track(&self) -> TokenStream {
+        let path = &self.path;
+
+        quote! {
+            const _: &[u8] = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/", #path));
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use proc_macro2::TokenStream;
 use quote::quote;
-use syn::{Error, Fields, ItemStruct, Type};
+use syn::{Attribute, Error, Fields, Ident, ItemStruct, LitInt, Type};
 
 pub fn mapped_kernel(item: ItemStruct) -> syn::Result<TokenStream> {
     // Check if single unnamed field.
@@ -32,27 +32,54 @@ pub fn mapped_kernel(item: ItemStruct) -> syn::Result<TokenStream> {
 
     // Render implementations.
     let ident = item.ident;
+    let (source, offset) = parse_options(&ident, &item.attrs)?;
+    let handler = match source {
+        Source::Lstar => quote! {
+            // Read LSTAR register.
+            let mut rdx: usize;
+            let mut rax: usize;
+
+            unsafe {
+                core::arch::asm!(
+                    "rdmsr",
+                    in("ecx") 0xc0000082u32,
+                    out("rdx") rdx, // Use 64-bits version to suppress "mov edx, edx".
+                    out("rax") rax, // Same here.
+                    options(pure, nomem, preserves_flags, nostack)
+                );
+            }
+
+            let handler = (rdx << 32) | rax;
+        },
+        Source::Idt(vector) => quote! {
+            // Read IDTR.
+            let mut idtr = [0u8; 10];
+
+            unsafe {
+                core::arch::asm!(
+                    "sidt [{}]",
+                    in(reg) idtr.as_mut_ptr(),
+                    options(preserves_flags, nostack)
+                );
+            }
+
+            // Read the gate descriptor.
+            let idt = usize::from_le_bytes(idtr[2..].try_into().unwrap());
+            let gate: [u8; 16] = unsafe { core::ptr::read((idt + #vector * 16) as *const [u8; 16]) };
+            let lo = u16::from_le_bytes([gate[0], gate[1]]) as usize;
+            let mid = u16::from_le_bytes([gate[6], gate[7]]) as usize;
+            let hi = u32::from_le_bytes([gate[8], gate[9], gate[10], gate[11]]) as usize;
+            let handler = (hi << 32) | (mid << 16) | lo;
+        },
+    };
 
     Ok(quote! {
         impl Default for #ident {
             fn default() -> Self {
-                // Read LSTAR register.
-                let mut rdx: usize;
-                let mut rax: usize;
-
-                unsafe {
-                    core::arch::asm!(
-                        "rdmsr",
-                        in("ecx") 0xc0000082u32,
-                        out("rdx") rdx, // Use 64-bits version to suppress "mov edx, edx".
-                        out("rax") rax, // Same here.
-                        options(pure, nomem, preserves_flags, nostack)
-                    );
-                }
+                #handler
 
                 // Get base address of the kernel.
-                let aslr = ((rdx << 32) | rax) - 0xffffffff822001c0;
-                let base = aslr + 0xffffffff82200000;
+                let base = handler - #offset;
 
                 Self(base as *const u8)
             }
@@ -65,3 +92,72 @@ pub fn mapped_kernel(item: ItemStruct) -> syn::Result<TokenStream> {
         }
     })
 }
+
+/// Where to get an address of the kernel handler to calculate the kernel base.
+enum Source {
+    Lstar,
+    Idt(usize),
+}
+
+/// Returns where to get the handler and offset of the handler from the kernel base.
+fn parse_options(ident: &Ident, attrs: &[Attribute]) -> syn::Result<(Source, usize)> {
+    let mut lstar = None;
+    let mut idt = None;
+    let mut handler = None;
+    let mut base = None;
+
+    for attr in attrs.iter().filter(|a| a.path().is_ident("mapped_kernel")) {
+        attr.parse_nested_meta(|m| {
+            let name = m
+                .path
+                .get_ident()
+                .map(|v| v.to_string())
+                .unwrap_or_default();
+            let slot = match name.as_str() {
+                "lstar" => &mut lstar,
+                "idt" => &mut idt,
+                "handler" => &mut handler,
+                "base" => &mut base,
+                _ => return Err(m.error("unknown option")),
+            };
+
+            let v: LitInt = m.value()?.parse()?;
+
+            if slot.replace(v.base10_parse::<usize>()?).is_some() {
+                return Err(m.error(format!("duplicated `{name}`")));
+            }
+
+            Ok(())
+        })?;
+    }
+
+    // Get the source. Default to Xfast_syscall on 11.00 for compatibility.
+    let (source, addr) = match (lstar, idt, handler) {
+        (Some(v), None, None) => (Source::Lstar, v),
+        (None, None, None) => (Source::Lstar, 0x1C0),
+        (None, Some(v), Some(h)) if v < 256 => (Source::Idt(v), h),
+        (None, Some(_), Some(_)) => {
+            return Err(Error::new_spanned(ident, "`idt` must be less than 256"));
+        }
+        (None, Some(_), None) => return Err(Error::new_spanned(ident, "`idt` requires `handler`")),
+        (None, None, Some(_)) => return Err(Error::new_spanned(ident, "`handler` requires `idt`")),
+        (Some(_), idt, _) => {
+            let other = if idt.is_some() { "idt" } else { "handler" };
+
+            return Err(Error::new_spanned(
+                ident,
+                format!("`lstar` cannot be used together with `{other}`"),
+            ));
+        }
+    };
+
+    // Get offset.
+    let off = match base {
+        Some(base) => addr.checked_sub(base).ok_or_else(|| {
+            Error::new_spanned(ident, "the handler address cannot be lower than `base`")
+        })?,
+        None => addr,
+    };
+
+    Ok((source, off))
+}
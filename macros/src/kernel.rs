@@ -0,0 +1,284 @@
+use crate::database::Database;
+use crate::offset::Location;
+use crate::pattern::Pattern;
+use proc_macro2::{Literal, TokenStream};
+use quote::{ToTokens, quote};
+use std::collections::BTreeSet;
+use syn::parse::{Parse, ParseStream};
+use syn::{
+    Error, FnArg, ImplItem, ItemImpl, ItemTrait, LitInt, LitStr, TraitItem, Type, braced,
+    parse_quote,
+};
+use toml::Value;
+
+/// Renders `item` with a hidden macro that forward the items without default implementation to
+/// [`implement()`].
+pub fn export_items(item: ItemTrait) -> syn::Result<TokenStream> {
+    let mut items = Vec::new();
+    let mut offsets = Vec::new();
+
+    for i in &item.items {
+        let mut i = i.clone();
+
+        // Collect the items that need an offset.
+        match &i {
+            TraitItem::Const(v) if v.default.is_none() && is_offset(&v.ty) => {
+                offsets.push(v.ident.to_string())
+            }
+            TraitItem::Fn(v) if v.default.is_none() => offsets.push(v.sig.ident.to_string()),
+            _ => {}
+        }
+
+        match &mut i {
+            TraitItem::Const(v) if v.default.is_none() => v.attrs.clear(),
+            TraitItem::Fn(v) if v.default.is_none() => v.attrs.clear(),
+            TraitItem::Type(v) if v.default.is_none() => v.attrs.clear(),
+            _ => continue,
+        }
+
+        items.push(i);
+    }
+
+    Ok(quote! {
+        #item
+
+        /// Names of the kernel items that need an offset.
+        #[doc(hidden)]
+        pub const __KERNEL_OFFSETS: &[&str] = &[#(#offsets),*];
+
+        #[doc(hidden)]
+        #[macro_export]
+        macro_rules! __kernel_items {
+            ($($args:tt)*) => {
+                $crate::__kernel_impl! { { #(#items)* } $($args)* }
+            };
+        }
+    })
+}
+
+/// Renders `impl` block of the kernel trait using the offset database.
+pub fn implement(input: Input) -> syn::Result<TokenStream> {
+    let db = Database::load(input.path)?;
+    let mut item = input.item;
+    let consts = db.table("consts")?;
+    let offsets = db.table("offsets")?;
+
+    // Get the items that implemented manually.
+    let mut manual = BTreeSet::new();
+
+    for i in &item.items {
+        let name = match i {
+            ImplItem::Const(v) => v.ident.to_string(),
+            ImplItem::Fn(v) => v.sig.ident.to_string(),
+            ImplItem::Type(v) => v.ident.to_string(),
+            _ => continue,
+        };
+
+        manual.insert(name);
+    }
+
+    // Check for unknown entries. We don't return early on the errors from the database so the
+    // impl is still rendered, otherwise there will be a lot of errors due to the missing impl.
+    let mut errors = Vec::new();
+    let names: BTreeSet<String> = input
+        .items
+        .iter()
+        .filter_map(|i| match i {
+            TraitItem::Const(v) => Some(v.ident.to_string()),
+            TraitItem::Fn(v) => Some(v.sig.ident.to_string()),
+            TraitItem::Type(v) => Some(v.ident.to_string()),
+            _ => None,
+        })
+        .collect();
+
+    for (table, entries) in [("consts", consts), ("offsets", offsets)] {
+        for k in entries.into_iter().flat_map(|t| t.keys()) {
+            if !names.contains(k) {
+                errors.push(db.error(format!("unknown entry `{table}.{k}`")));
+            } else if manual.contains(k) {
+                errors.push(db.error(format!("`{k}` is already implemented manually")));
+            } else if table == "offsets" && consts.is_some_and(|t| t.contains_key(k)) {
+                errors.push(db.error(format!("`{k}` is defined in both consts and offsets")));
+            }
+        }
+    }
+
+    // Generate the items.
+    let mut missing = Vec::new();
+
+    for i in input.items {
+        match i {
+            TraitItem::Const(mut i) => {
+                let name = i.ident.to_string();
+
+                if manual.contains(&name) {
+                    continue;
+                }
+
+                if let Some(v) = consts.and_then(|t| t.get(&name)) {
+                    let v = constant(&db, &name, &i.ty, v)?;
+
+                    i.default = Some((parse_quote!(=), v));
+                    item.items.push(syn::parse2(i.into_token_stream())?);
+                } else if let Some(v) = offsets.and_then(|t| t.get(&name)) {
+                    let loc = location(&db, &name, v)?;
+                    let i = crate::offset::transform(loc, TraitItem::Const(i))?;
+
+                    item.items.push(syn::parse2(i)?);
+                } else {
+                    missing.push(name);
+                }
+            }
+            TraitItem::Fn(mut i) => {
+                let name = i.sig.ident.to_string();
+
+                if manual.contains(&name) {
+                    continue;
+                }
+
+                let loc = match offsets.and_then(|t| t.get(&name)) {
+                    Some(v) => location(&db, &name, v)?,
+                    None => {
+                        missing.push(name);
+                        continue;
+                    }
+                };
+
+                // Replace the receiver so it have the same hygiene as the generated body.
+                if let Some(FnArg::Receiver(_)) = i.sig.inputs.first() {
+                    i.sig.inputs[0] = parse_quote!(self);
+                }
+
+                let i = crate::offset::transform(loc, TraitItem::Fn(i))?;
+
+                item.items.push(syn::parse2(i)?);
+            }
+            TraitItem::Type(i) => {
+                let name = i.ident.to_string();
+
+                if !manual.contains(&name) {
+                    missing.push(name);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if !missing.is_empty() {
+        let missing = missing
+            .iter()
+            .map(|v| format!("`{v}`"))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        errors.push(db.error(format!("missing {missing}")));
+    }
+
+    // Render.
+    let track = db.track();
+    let errors = errors.into_iter().map(Error::into_compile_error);
+
+    Ok(quote! {
+        #item
+
+        #track
+        #(#errors)*
+    })
+}
+
+fn is_offset(ty: &Type) -> bool {
+    let p = match ty {
+        Type::Path(v) => v,
+        _ => return false,
+    };
+
+    p.path
+        .segments
+        .last()
+        .is_some_and(|s| s.ident == "Static" || s.ident == "StaticMut" || s.ident == "Function")
+}
+
+fn constant(db: &Database, name: &str, ty: &Type, v: &Value) -> syn::Result<syn::Expr> {
+    let v = match v {
+        Value::Integer(v) => Literal::i64_unsuffixed(*v).into_token_stream(),
+        Value::Boolean(v) => v.into_token_stream(),
+        _ => return Err(db.error(format!("`consts.{name}` must be an integer or boolean"))),
+    };
+
+    // Check if NonZero.
+    let non_zero = match ty {
+        Type::Path(p) => p.path.segments.last().is_some_and(|s| s.ident == "NonZero"),
+        _ => false,
+    };
+
+    if non_zero {
+        Ok(parse_quote!(::core::num::NonZero::new(#v).unwrap()))
+    } else {
+        Ok(parse_quote!(#v))
+    }
+}
+
+fn location(db: &Database, name: &str, v: &Value) -> syn::Result<Location> {
+    let span = db.path().span();
+    let pattern = |p: &str, rel: Option<i64>| {
+        let p = LitStr::new(p, span);
+        let args = match rel {
+            Some(v) => {
+                let v = LitInt::new(&v.to_string(), span);
+
+                quote!(#p, rel = #v)
+            }
+            None => quote!(#p),
+        };
+
+        syn::parse2::<Pattern>(args)
+            .map_err(|e| db.error(format!("invalid pattern for `offsets.{name}`: {e}")))
+    };
+
+    match v {
+        Value::Integer(v) if *v >= 0 => Ok(Location::Fixed(LitInt::new(&v.to_string(), span))),
+        Value::String(v) => pattern(v, None).map(Location::Pattern),
+        Value::Table(t) => {
+            let p = match t.get("pattern") {
+                Some(Value::String(v)) => v,
+                _ => return Err(db.error(format!("`offsets.{name}.pattern` must be a string"))),
+            };
+            let rel = match t.get("rel") {
+                Some(Value::Integer(v)) => Some(*v),
+                Some(_) => return Err(db.error(format!("`offsets.{name}.rel` must be an integer"))),
+                None => None,
+            };
+
+            pattern(p, rel).map(Location::Pattern)
+        }
+        _ => Err(db.error(format!(
+            "`offsets.{name}` must be an offset, a pattern or a table"
+        ))),
+    }
+}
+
+/// Input of [`implement()`].
+pub struct Input {
+    items: Vec<TraitItem>,
+    path: LitStr,
+    item: ItemImpl,
+}
+
+impl Parse for Input {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let content;
+        let mut items = Vec::new();
+
+        braced!(content in input);
+
+        while !content.is_empty() {
+            items.push(content.parse()?);
+        }
+
+        Ok(Self {
+            items,
+            path: input.parse()?,
+            item: input.parse()?,
+        })
+    }
+}
@@ -0,0 +1,237 @@
+use crate::database::Database;
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::visit_mut::VisitMut;
+use syn::{Attribute, Error, Expr, Field, Fields, Ident, ItemStruct, LitInt, Meta, Type, TypePath};
+use toml::{Table, Value};
+
+pub fn transform(mut item: ItemStruct, db: Option<Database>) -> syn::Result<TokenStream> {
+    // Check if repr(C).
+    if !item.attrs.iter().any(is_repr_c) {
+        return Err(Error::new_spanned(item.ident, "expect `#[repr(C)]`"));
+    }
+
+    if !item.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            item.generics,
+            "generic struct is not supported",
+        ));
+    }
+
+    // Get total size.
+    let mut size = None;
+
+    for i in (0..item.attrs.len()).rev() {
+        if item.attrs[i].path().is_ident("size") {
+            if size.is_some() {
+                return Err(Error::new_spanned(&item.attrs[i], "duplicated `size`"));
+            }
+
+            size = Some(item.attrs.remove(i).parse_args::<Expr>()?);
+        }
+    }
+
+    // Get layout from the database.
+    let ident = item.ident.clone();
+    let layout = match &db {
+        Some(db) => Some(layout(db, &ident)?),
+        None => None,
+    };
+
+    if let Some((Some(v), _)) = &layout {
+        if size.is_some() {
+            return Err(Error::new_spanned(
+                &ident,
+                "`size` is already defined in the database",
+            ));
+        }
+
+        size = Some(v.clone());
+    }
+
+    // Get fields.
+    let fields = match &mut item.fields {
+        Fields::Named(v) => std::mem::take(&mut v.named),
+        Fields::Unit => Default::default(),
+        v => return Err(Error::new_spanned(v, "expect named fields")),
+    };
+
+    // Insert padding before each field that have a declared offset.
+    let mut output = Vec::with_capacity(fields.len() * 2);
+    let mut asserts = Vec::new();
+    let mut end = quote!(0usize);
+
+    let mut offsets = layout.map(|v| v.1).unwrap_or_default();
+
+    for mut f in fields {
+        let name = f.ident.clone().unwrap();
+        let ty = sized_type(&f.ty, &ident);
+        let off = match (take_offset(&mut f)?, offsets.remove(&name.to_string())) {
+            (Some(_), Some(_)) => {
+                return Err(Error::new_spanned(
+                    &name,
+                    "the offset is already defined in the database",
+                ));
+            }
+            (Some(v), None) | (None, Some(v)) => Some(v),
+            (None, None) => None,
+        };
+        let off = match off {
+            Some(off) => {
+                output.push(padding(output.len(), quote!((#off) - (#end))));
+                asserts.push(quote! {
+                    assert!(
+                        ::core::mem::offset_of!(#ident, #name) == (#off),
+                        concat!("invalid offset of `", stringify!(#name), "`"),
+                    );
+                });
+
+                quote!((#off))
+            }
+            None => quote!((#end).next_multiple_of(::core::mem::align_of::<#ty>())),
+        };
+
+        end = quote!(#off + ::core::mem::size_of::<#ty>());
+        output.push(f);
+    }
+
+    if let (Some(k), Some(db)) = (offsets.keys().next(), &db) {
+        return Err(db.error(format!("`{ident}` does not have a field `{k}`")));
+    }
+
+    if let Some(size) = &size {
+        output.push(padding(output.len(), quote!((#size) - (#end))));
+        asserts.push(quote! {
+            assert!(
+                ::core::mem::size_of::<#ident>() == (#size),
+                concat!("invalid size of `", stringify!(#ident), "`"),
+            );
+        });
+    }
+
+    // Render.
+    let vis = &item.vis;
+    let attrs = &item.attrs;
+    let track = db.as_ref().map(Database::track);
+
+    Ok(quote! {
+        #(#attrs)*
+        #vis struct #ident {
+            #(#output),*
+        }
+
+        const _: () = {
+            #(#asserts)*
+        };
+
+        #track
+    })
+}
+
+/// Returns size and field offsets of `name` from `structs` table in the database.
+fn layout(
+    db: &Database,
+    name: &Ident,
+) -> syn::Result<(Option<Expr>, std::collections::BTreeMap<String, Expr>)> {
+    let entry = db
+        .table("structs")?
+        .and_then(|t| t.get(&name.to_string()))
+        .ok_or_else(|| db.error(format!("no `structs.{name}` in the database")))?;
+    let entry = match entry {
+        Value::Table(v) => v,
+        _ => return Err(db.error(format!("`structs.{name}` must be a table"))),
+    };
+    let int = |v: &Value, key: &str| match v {
+        Value::Integer(v) if *v >= 0 => {
+            let v = LitInt::new(&format!("{v:#X}"), db.path().span());
+
+            Ok(syn::parse_quote!(#v))
+        }
+        _ => Err(db.error(format!("`structs.{name}.{key}` must be an offset"))),
+    };
+
+    // Get size.
+    let size = match entry.get("size") {
+        Some(v) => Some(int(v, "size")?),
+        None => None,
+    };
+
+    // Get fields.
+    let mut fields = std::collections::BTreeMap::new();
+    let empty = Table::new();
+    let table = match entry.get("fields") {
+        Some(Value::Table(v)) => v,
+        Some(_) => return Err(db.error(format!("`structs.{name}.fields` must be a table"))),
+        None => &empty,
+    };
+
+    for (k, v) in table {
+        fields.insert(k.clone(), int(v, &format!("fields.{k}"))?);
+    }
+
+    for k in entry.keys() {
+        if k != "size" && k != "fields" {
+            return Err(db.error(format!("unknown entry `structs.{name}.{k}`")));
+        }
+    }
+
+    Ok((size, fields))
+}
+
+fn is_repr_c(attr: &Attribute) -> bool {
+    let list = match &attr.meta {
+        Meta::List(v) if v.path.is_ident("repr") => v,
+        _ => return false,
+    };
+
+    let mut found = false;
+    let _ = list.parse_nested_meta(|m| {
+        found |= m.path.is_ident("C");
+        Ok(())
+    });
+
+    found
+}
+
+fn take_offset(f: &mut Field) -> syn::Result<Option<Expr>> {
+    let mut off = None;
+
+    for i in (0..f.attrs.len()).rev() {
+        if f.attrs[i].path().is_ident("at") {
+            if off.is_some() {
+                return Err(Error::new_spanned(&f.attrs[i], "duplicated `at`"));
+            }
+
+            off = Some(f.attrs.remove(i).parse_args::<Expr>()?);
+        }
+    }
+
+    Ok(off)
+}
+
+fn padding(index: usize, len: TokenStream) -> Field {
+    let name = format_ident!("_pad{}", index);
+
+    syn::parse_quote!(#name: [u8; #len])
+}
+
+/// Returns `ty` with `Self` replaced by `ident` so it can be used inside an array length.
+fn sized_type(ty: &Type, ident: &Ident) -> Type {
+    struct ReplaceSelf<'a>(&'a Ident);
+
+    impl VisitMut for ReplaceSelf<'_> {
+        fn visit_type_path_mut(&mut self, i: &mut TypePath) {
+            if i.qself.is_none() && i.path.is_ident("Self") {
+                i.path = self.0.clone().into();
+            } else {
+                syn::visit_mut::visit_type_path_mut(self, i);
+            }
+        }
+    }
+
+    let mut ty = ty.clone();
+
+    ReplaceSelf(ident).visit_type_mut(&mut ty);
+
+    ty
+}
@@ -1,10 +1,30 @@
+use self::database::Database;
+use self::kernel::Input;
+use self::offset::Location;
+use self::pattern::Pattern;
 use proc_macro::TokenStream;
-use syn::{Error, ItemStruct, LitInt, TraitItem, parse_macro_input};
+use quote::ToTokens;
+use syn::parse::Nothing;
+use syn::{Error, ItemImpl, ItemStruct, ItemTrait, LitInt, LitStr, TraitItem, parse_macro_input};
 
+mod database;
 mod derive;
+mod kernel;
+mod layout;
 mod offset;
+mod pattern;
 
-#[proc_macro_derive(MappedKernel)]
+/// Implements `okf::MappedKernel` and [`Default`] for a `struct Kernel(*const u8)`.
+///
+/// The kernel base is calculated from the address of a kernel handler at runtime. By default it is
+/// `Xfast_syscall` from `LSTAR` register with an offset `0x1C0` from the base. Use
+/// `#[mapped_kernel]` to change this:
+///
+/// - `lstar = OFFSET`: use `LSTAR` register with `OFFSET` as an offset of the handler.
+/// - `idt = VECTOR, handler = OFFSET`: use the handler of `VECTOR` in the IDT instead.
+/// - `base = ADDR`: treat `lstar` or `handler` as a virtual address linked with `ADDR` as a base
+///   instead of an offset (e.g. `lstar = 0xffffffff822001c0, base = 0xffffffff82200000`).
+#[proc_macro_derive(MappedKernel, attributes(mapped_kernel))]
 pub fn derive_mapped_kernel(item: TokenStream) -> TokenStream {
     let item = parse_macro_input!(item as ItemStruct);
 
@@ -13,12 +33,134 @@ pub fn derive_mapped_kernel(item: TokenStream) -> TokenStream {
         .into()
 }
 
+/// Generates padding for a `#[repr(C)]` struct that mirror a kernel structure.
+///
+/// Put `#[at(OFFSET)]` on a field to place it at `OFFSET` and `#[size(SIZE)]` after this attribute
+/// to pad the struct to `SIZE` bytes. The fields without `#[at]` will be placed right after the
+/// previous field. Both offsets and total size are asserted at compile time.
+///
+/// The offsets and size can also be loaded from `structs` table of the offset database by
+/// specifying a path to the database (see [`macro@kernel_impl`] for more details):
+///
+/// ```toml
+/// [structs.Thread]
+/// size = 0x400
+/// fields = { cred = 0x130 }
+/// ```
+///
+/// ```ignore
+/// #[kernel_struct]
+/// #[repr(C)]
+/// #[size(0x400)]
+/// pub struct Thread {
+///     #[at(0x130)]
+///     cred: *mut Ucred,
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn kernel_struct(args: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args as Option<LitStr>);
+    let item = parse_macro_input!(item as ItemStruct);
+
+    args.map(Database::load)
+        .transpose()
+        .and_then(|db| self::layout::transform(item, db))
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Implements `okf::Kernel` from an offset database.
+///
+/// The argument is a path to a TOML file relative to the crate root. The associated types and any
+/// items that require a custom implementation must be implemented manually in the `impl` block. The
+/// remaining items will be loaded from the database:
+///
+/// ```toml
+/// [consts]
+/// EINTR = 4
+/// LK_EXCLUSIVE = 0x80000
+///
+/// [offsets]
+/// M_TEMP = 0x15415B0
+/// fget = 0x419040
+/// malloc = "55 48 89 E5 41 57 41 56 ?? ?? 49 89 FE"
+/// MOUNTLIST = { pattern = "48 8D 3D ?? ?? ?? ??", rel = 3 }
+/// ```
+///
+/// All of types that was used by the items from the database must be in scope. It is a compile
+/// error if the database has missing or unknown entries.
+///
+/// ```ignore
+/// #[kernel_impl("offsets.toml")]
+/// impl okf::Kernel for Kernel {
+///     type File = File;
+///     ...
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn kernel_impl(args: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args as LitStr);
+    let item = parse_macro_input!(item as ItemImpl);
+
+    quote::quote!(::okf::__kernel_items! { #args #item }).into()
+}
+
+#[doc(hidden)]
+#[proc_macro_attribute]
+pub fn kernel_trait(args: TokenStream, item: TokenStream) -> TokenStream {
+    parse_macro_input!(args as Nothing);
+    let item = parse_macro_input!(item as ItemTrait);
+
+    self::kernel::export_items(item)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+#[doc(hidden)]
+#[proc_macro]
+pub fn __kernel_impl(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as Input);
+
+    self::kernel::implement(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
 #[proc_macro_attribute]
 pub fn offset(args: TokenStream, item: TokenStream) -> TokenStream {
     let args = parse_macro_input!(args as LitInt);
     let item = parse_macro_input!(item as TraitItem);
 
-    self::offset::transform(args, item)
+    self::offset::transform(Location::Fixed(args), item)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Same as `offset` but locate the item by scanning the kernel text for a byte signature.
+///
+/// The signature is a space-separated hex bytes with `??` as a wildcard. It must have exactly one
+/// match in the kernel otherwise the access to the item will panic. The scan will be done once on
+/// the first access. Specify `rel = N` to use a target of 32-bit relative displacement at `N` bytes
+/// from the start of the match instead of the match itself:
+///
+/// ```ignore
+/// #[pattern("48 8D 3D ?? ?? ?? ?? E8", rel = 3)]
+/// const M_TEMP: StaticMut<Self::Malloc>;
+/// ```
+#[proc_macro_attribute]
+pub fn pattern(args: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args as Pattern);
+    let item = parse_macro_input!(item as TraitItem);
+
+    self::offset::transform(Location::Pattern(args), item)
         .unwrap_or_else(Error::into_compile_error)
         .into()
 }
+
+/// Constructs a `okf::pattern::Signature` with the same syntax as `pattern` attribute.
+#[proc_macro]
+pub fn signature(args: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args as Pattern);
+
+    args.into_token_stream().into()
+}
@@ -1,44 +1,70 @@
+use crate::pattern::Pattern;
 use proc_macro2::TokenStream;
-use quote::ToTokens;
+use quote::{ToTokens, quote};
 use syn::punctuated::Punctuated;
 use syn::{
     Error, FnArg, LitInt, Pat, PatType, Token, TraitItem, TraitItemConst, TraitItemFn, parse_quote,
 };
 
-pub fn transform(args: LitInt, item: TraitItem) -> syn::Result<TokenStream> {
+/// Location of a kernel item.
+pub enum Location {
+    Fixed(LitInt),
+    Pattern(Pattern),
+}
+
+pub fn transform(loc: Location, item: TraitItem) -> syn::Result<TokenStream> {
     match item {
-        TraitItem::Const(i) => transform_const(args, i),
-        TraitItem::Fn(i) => transform_fn(args, i),
+        TraitItem::Const(i) => transform_const(loc, i),
+        TraitItem::Fn(i) => transform_fn(loc, i),
         v => Err(Error::new_spanned(v, "unsupported offset item")),
     }
 }
 
-fn transform_const(args: LitInt, mut item: TraitItemConst) -> syn::Result<TokenStream> {
+fn transform_const(loc: Location, mut item: TraitItemConst) -> syn::Result<TokenStream> {
     // Check if body present.
     if let Some((b, _)) = item.default {
         return Err(Error::new_spanned(b, "expect `;`"));
     }
 
     // Set body.
-    let offset: usize = args.base10_parse()?;
     let ty = &item.ty;
+    let body = match loc {
+        Location::Fixed(v) => {
+            let offset: usize = v.base10_parse()?;
+
+            parse_quote!(unsafe { <#ty>::new(#offset) })
+        }
+        Location::Pattern(v) => parse_quote!({
+            static SIG: ::okf::pattern::Signature = #v;
+            unsafe { <#ty>::with_signature(&SIG) }
+        }),
+    };
 
-    item.default = Some((
-        parse_quote!(=),
-        parse_quote!(unsafe { <#ty>::new(#offset) }),
-    ));
+    item.default = Some((parse_quote!(=), body));
 
     Ok(item.into_token_stream())
 }
 
-fn transform_fn(args: LitInt, mut item: TraitItemFn) -> syn::Result<TokenStream> {
+fn transform_fn(loc: Location, mut item: TraitItemFn) -> syn::Result<TokenStream> {
     // Check if body present.
     if let Some(b) = item.default {
         return Err(Error::new_spanned(b, "expect `;`"));
     }
 
+    // Get offset.
+    let offset = match loc {
+        Location::Fixed(v) => {
+            let v: usize = v.base10_parse()?;
+
+            quote!(#v)
+        }
+        Location::Pattern(v) => quote!({
+            static SIG: ::okf::pattern::Signature = #v;
+            unsafe { SIG.resolve(self.addr()) }.expect("no unique match for signature")
+        }),
+    };
+
     // Set body.
-    let offset: usize = args.base10_parse()?;
     let sig = &item.sig;
     let ret = &sig.output;
     let mut params = Punctuated::<&PatType, Token![,]>::new();
@@ -0,0 +1,52 @@
+use proc_macro2::TokenStream;
+use quote::{ToTokens, quote};
+use syn::parse::{Parse, ParseStream};
+use syn::{Error, LitInt, LitStr, Token};
+
+/// Arguments of `#[pattern]` and `signature!`.
+pub struct Pattern {
+    bytes: Vec<Option<u8>>,
+    rel: Option<usize>,
+}
+
+impl Parse for Pattern {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        // Parse bytes.
+        let lit: LitStr = input.parse()?;
+        let bytes = okf_pattern::parse(&lit.value()).map_err(|e| Error::new_spanned(&lit, e))?;
+
+        // Parse options.
+        let mut rel = None;
+
+        if input.parse::<Option<Token![,]>>()?.is_some() && !input.is_empty() {
+            let name: syn::Ident = input.parse()?;
+
+            if name != "rel" {
+                return Err(Error::new_spanned(name, "unknown option"));
+            }
+
+            input.parse::<Token![=]>()?;
+            rel = Some(input.parse::<LitInt>()?.base10_parse()?);
+            input.parse::<Option<Token![,]>>()?;
+        }
+
+        Ok(Self { bytes, rel })
+    }
+}
+
+impl ToTokens for Pattern {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let bytes = self.bytes.iter().map(|b| match b {
+            Some(v) => quote!(Some(#v)),
+            None => quote!(None),
+        });
+        let rel = match self.rel {
+            Some(v) => quote!(Some(#v)),
+            None => quote!(None),
+        };
+
+        tokens.extend(quote! {
+            ::okf::pattern::Signature::new(&[#(#bytes),*], #rel)
+        });
+    }
+}
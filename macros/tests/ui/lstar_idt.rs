@@ -0,0 +1,7 @@
+use okf_macros::MappedKernel;
+
+#[derive(Clone, Copy, MappedKernel)]
+#[mapped_kernel(lstar = 0x1C0, idt = 3)]
+struct Kernel(*const u8);
+
+fn main() {}
@@ -0,0 +1,15 @@
+use core::ffi::c_int;
+
+pub const ENOENT: c_int = 2;
+pub const EBADF: c_int = 9;
+pub const EFAULT: c_int = 14;
+pub const EEXIST: c_int = 17;
+pub const ENOTDIR: c_int = 20;
+pub const EISDIR: c_int = 21;
+pub const EINVAL: c_int = 22;
+pub const EWOULDBLOCK: c_int = 35;
+pub const EPROTOTYPE: c_int = 41;
+pub const EOPNOTSUPP: c_int = 45;
+pub const EAFNOSUPPORT: c_int = 47;
+pub const EADDRINUSE: c_int = 48;
+pub const ENOSYS: c_int = 78;
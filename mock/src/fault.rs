@@ -0,0 +1,36 @@
+use core::ffi::c_int;
+use core::num::NonZero;
+
+/// Kernel function that can be made to fail with [`crate::MockKernel::inject()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Call {
+    Fget,
+    FgetWrite,
+    KernClose,
+    KernFsync,
+    KernOpenat,
+    KernWritev,
+    Malloc,
+    Realloc,
+    Sleep,
+    Soaccept,
+    Sobind,
+    Socreate,
+    Solisten,
+    UmaZalloc,
+    VfsBusy,
+    VopLookup,
+    VopRead,
+    VopReadDir,
+}
+
+/// Failure to inject into the next invocation of a [`Call`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// Return the errno without doing anything. [`Call::Malloc`],
+    /// [`Call::Realloc`] and [`Call::UmaZalloc`] return a null pointer instead.
+    Errno(NonZero<c_int>),
+    /// Transfer at most this number of bytes then succeed. Only [`Call::KernWritev`],
+    /// [`Call::VopRead`] and [`Call::VopReadDir`] honor this.
+    Short(usize),
+}
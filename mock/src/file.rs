@@ -0,0 +1,34 @@
+use okf::fd::OpenFlags;
+use std::sync::atomic::AtomicU32;
+
+/// Implementation of [`okf::file::File`] for [`crate::MockKernel`].
+#[repr(C)]
+pub struct File {
+    refcnt: AtomicU32,
+    pub(crate) node: usize,
+    pub(crate) offset: usize,
+    pub(crate) flags: OpenFlags,
+}
+
+impl File {
+    pub(crate) fn new(node: usize, flags: OpenFlags) -> Self {
+        Self {
+            refcnt: AtomicU32::new(1),
+            node,
+            offset: 0,
+            flags,
+        }
+    }
+
+    pub(crate) fn is_writable(&self) -> bool {
+        let mode = self.flags & OpenFlags::O_ACCMODE;
+
+        mode == OpenFlags::O_WRONLY || mode == OpenFlags::O_RDWR
+    }
+}
+
+impl okf::file::File for File {
+    fn refcnt(&self) -> &AtomicU32 {
+        &self.refcnt
+    }
+}
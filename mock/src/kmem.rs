@@ -0,0 +1,15 @@
+use std::alloc::Layout;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Implementation of [`okf::kmem::VmMap`] for [`crate::MockKernel`].
+///
+/// Physical address on the mocked kernel is the same as the virtual address.
+#[repr(C)]
+pub struct VmMap {}
+
+impl okf::kmem::VmMap for VmMap {}
+
+/// Layout and `malloc_type` of the memory from `contigmalloc`, which need to be known by
+/// `contigfree`.
+pub(crate) static CONTIG: Mutex<Option<HashMap<usize, (Layout, usize)>>> = Mutex::new(None);
@@ -0,0 +1,2409 @@
+#![cfg_attr(all(test, feature = "allocator-api"), feature(allocator_api))]
+
+use self::errno::{
+    EADDRINUSE, EAFNOSUPPORT, EBADF, EFAULT, EINVAL, EISDIR, ENOENT, ENOSYS, ENOTDIR, EOPNOTSUPP,
+    EPROTOTYPE, EWOULDBLOCK,
+};
+use self::file::File;
+use self::kmem::{CONTIG, VmMap};
+use self::lock::{Cv, LockObject, Mtx, RwLock, Sx};
+use self::malloc::Malloc;
+use self::mount::{Filesystem, FsOps, FsStats, Mount};
+use self::namei::ComponentName;
+use self::pcpu::Pcpu;
+use self::proc::Proc;
+use self::sleepq::Sleeper;
+use self::socket::Socket;
+use self::state::NodeKind;
+use self::syscall::Sysent;
+use self::thread::Thread;
+use self::ucred::Ucred;
+use self::uio::Uio;
+use self::uma::UmaZone;
+use self::vnode::{Vnode, VnodeOp, VopLock, VopLookup, VopRead, VopReadDir, VopUnlock, VopVector};
+use core::ffi::{CStr, c_char, c_int, c_void};
+use core::mem::offset_of;
+use core::num::NonZero;
+use core::ptr::null_mut;
+use okf::fd::{AT_FDCWD, OpenFlags};
+use okf::file::File as _;
+use okf::firmware::KernelImage;
+use okf::malloc::MallocFlags;
+use okf::pcpu::Pcpu as _;
+use okf::queue::{TailQueue, TailQueueEntry};
+use okf::socket::{AF_INET, SOCK_DGRAM, SOCK_STREAM, SockAddr, SockAddrIn};
+use okf::thread::Thread as _;
+use okf::uio::{UioRw, UioSeg};
+use okf::uma::{UmaCtor, UmaDtor, UmaFini, UmaInit};
+use okf::vnode::{DirEnt, Vnode as _, VopArgs, VopLock as _};
+use okf::{Function, Kernel as _, MappedKernel, Static, StaticMut};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::marker::PhantomPinned;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+pub use self::fault::*;
+
+mod errno;
+mod fault;
+mod file;
+mod kmem;
+mod lock;
+mod malloc;
+mod mount;
+mod namei;
+mod pcpu;
+mod proc;
+mod sleepq;
+mod socket;
+mod state;
+mod syscall;
+mod thread;
+mod ucred;
+mod uio;
+mod uma;
+mod vnode;
+
+/// Implementation of [`okf::Kernel`] that run on the host for unit testing.
+///
+/// Each host thread has its own VFS, FD table and injected faults so tests running in parallel does
+/// not interfere with each other. The VFS is empty when the thread first use the kernel.
+///
+/// [`okf::Kernel::PANIC`] is not backed by any code so [`okf::panic::panic()`] cannot be used.
+#[derive(Default, Clone, Copy)]
+pub struct MockKernel;
+
+impl MockKernel {
+    /// Create a regular file at `path` with `data` as its content, replacing the previous content
+    /// if it already exists. All missing parents will be created.
+    ///
+    /// # Panics
+    /// If `path` or one of its parent is not a valid node.
+    pub fn create_file(self, path: &str, data: impl Into<Vec<u8>>) {
+        state::with(|s| {
+            let node = s.create_all(path, false).unwrap();
+
+            match &mut s.node_mut(node).kind {
+                NodeKind::File(v) => *v = data.into(),
+                NodeKind::Dir(_) => panic!("{path} is a directory"),
+            }
+        })
+    }
+
+    /// Create a directory at `path`. All missing parents will be created.
+    ///
+    /// # Panics
+    /// If `path` or one of its parent is not a valid node.
+    pub fn create_dir(self, path: &str) {
+        state::with(|s| s.create_all(path, true).unwrap());
+    }
+
+    /// Returns the content of a regular file at `path`.
+    pub fn read_file(self, path: &str) -> Option<Vec<u8>> {
+        state::with(|s| match &s.node(s.lookup(path).ok()?).kind {
+            NodeKind::File(v) => Some(v.clone()),
+            NodeKind::Dir(_) => None,
+        })
+    }
+
+    /// Make the next invocation of `call` that has not been faulted fail with `fault`.
+    ///
+    /// Multiple faults on the same [`Call`] will be applied in the same order as they are injected.
+    pub fn inject(self, call: Call, fault: Fault) {
+        state::with(|s| s.inject(call, fault));
+    }
+
+    /// Returns number of FD that currently open.
+    pub fn open_files(self) -> usize {
+        state::with(|s| s.open_files())
+    }
+
+    /// Returns number of sockets that does not closed yet.
+    pub fn live_sockets(self) -> usize {
+        state::with(|s| s.sockets)
+    }
+
+    /// Returns number of memory blocks from [`okf::Kernel::malloc()`] that does not freed yet.
+    pub fn live_allocations(self) -> usize {
+        state::with(|s| s.allocs)
+    }
+
+    fn fault(call: Call) -> Option<Fault> {
+        state::with(|s| s.fault(call))
+    }
+
+    fn errno(call: Call) -> Option<c_int> {
+        match Self::fault(call) {
+            Some(Fault::Errno(v)) => Some(v.get()),
+            _ => None,
+        }
+    }
+
+    fn limit(call: Call) -> Result<usize, c_int> {
+        match Self::fault(call) {
+            Some(Fault::Errno(v)) => Err(v.get()),
+            Some(Fault::Short(v)) => Ok(v),
+            None => Ok(usize::MAX),
+        }
+    }
+}
+
+impl MappedKernel for MockKernel {
+    fn addr(self) -> *const u8 {
+        (&raw const IMAGE).cast()
+    }
+}
+
+impl okf::firmware::Firmware for MockKernel {
+    const VERSION: u16 = 0;
+    const SDK_VERSION: Static<u32> = unsafe { Static::new(offset_of!(Image, sdk_version)) };
+
+    fn image() -> Option<KernelImage> {
+        Some(KernelImage::new(
+            (&raw const IMAGE).cast(),
+            size_of::<Image>(),
+        ))
+    }
+}
+
+impl okf::Kernel for MockKernel {
+    const ACCEPT_MTX: StaticMut<Self::Mtx> = unsafe { StaticMut::new(offset_of!(Image, accept)) };
+    const EINTR: NonZero<c_int> = NonZero::new(4).unwrap();
+    const EIO: NonZero<c_int> = NonZero::new(5).unwrap();
+    const ENOSYS: NonZero<c_int> = NonZero::new(ENOSYS).unwrap();
+    const EWOULDBLOCK: NonZero<c_int> = NonZero::new(EWOULDBLOCK).unwrap();
+    const KERNEL_MAP: StaticMut<*mut Self::VmMap> =
+        unsafe { StaticMut::new(offset_of!(Image, kernel_map)) };
+    const LK_EXCLUSIVE: c_int = 0x80000;
+    const LK_SHARED: c_int = 0x200000;
+    const LOCK_DEBUG: bool = true;
+    const LOOKUP: u64 = 0;
+    const M_TEMP: StaticMut<Self::Malloc> = unsafe { StaticMut::new(offset_of!(Image, temp)) };
+    const MBF_MNTLSTLOCK: c_int = 2;
+    const MBF_NOWAIT: c_int = 1;
+    const MNT_RDONLY: u64 = 0x0000000000000001;
+    const MOUNTLIST: StaticMut<TailQueue<Self::Mount>> =
+        unsafe { StaticMut::new(offset_of!(Image, mountlist)) };
+    const MOUNTLIST_MTX: StaticMut<Self::Mtx> =
+        unsafe { StaticMut::new(offset_of!(Image, mountlist_mtx)) };
+    const MTX_DEF: c_int = 0;
+    const MTX_SPIN: c_int = 1;
+    const NOCPU: u32 = 0xff;
+    const PAGE_SIZE: usize = 0x1000;
+    const PANIC: Function<unsafe extern "C" fn(*const c_char, ...) -> !> =
+        unsafe { Function::new(offset_of!(Image, panic)) };
+    const SYSENT: StaticMut<Self::Sysent> = unsafe { StaticMut::new(offset_of!(Image, sysent)) };
+    const SYS_MAXSYSCALL: c_int = 32;
+    const VDIR: c_int = 2;
+    const VOP_LOCK1: StaticMut<Self::VnodeOp> =
+        unsafe { StaticMut::new(offset_of!(Image, vop_lock1)) };
+    const VOP_LOOKUP: StaticMut<Self::VnodeOp> =
+        unsafe { StaticMut::new(offset_of!(Image, vop_lookup)) };
+    const VOP_READ: StaticMut<Self::VnodeOp> =
+        unsafe { StaticMut::new(offset_of!(Image, vop_read)) };
+    const VOP_READDIR: StaticMut<Self::VnodeOp> =
+        unsafe { StaticMut::new(offset_of!(Image, vop_readdir)) };
+    const VOP_UNLOCK: StaticMut<Self::VnodeOp> =
+        unsafe { StaticMut::new(offset_of!(Image, vop_unlock)) };
+    const VREG: c_int = 1;
+
+    type ComponentName = ComponentName;
+    type Cv = Cv;
+    type File = File;
+    type Filesystem = Filesystem;
+    type FsOps = FsOps;
+    type FsStats = FsStats;
+    type LockObject = LockObject;
+    type Malloc = Malloc;
+    type Mount = Mount;
+    type Mtx = Mtx;
+    type Pcpu = Pcpu;
+    type Proc = Proc;
+    type RwLock = RwLock;
+    type Socket = Socket;
+    type Sx = Sx;
+    type Sysent = Sysent;
+    type Thread = Thread;
+    type Ucred = Ucred;
+    type Uio = Uio;
+    type UmaZone = UmaZone;
+    type VmMap = VmMap;
+    type Vnode = Vnode;
+    type VnodeOp = VnodeOp;
+    type VopLock = VopLock;
+    type VopLookup = VopLookup;
+    type VopRead = VopRead;
+    type VopReadDir = VopReadDir;
+    type VopUnlock = VopUnlock;
+    type VopVector = VopVector;
+
+    unsafe fn contigfree(self, addr: *mut u8, _: usize, ty: *mut Self::Malloc) {
+        unsafe { (*ty).check() };
+
+        let (layout, owner) = CONTIG
+            .lock()
+            .unwrap()
+            .get_or_insert_default()
+            .remove(&(addr as usize))
+            .expect("contigfree on unknown memory");
+
+        assert_eq!(
+            owner, ty as usize,
+            "contigfree with a different malloc_type"
+        );
+
+        unsafe { System.dealloc(addr, layout) };
+
+        state::with(|s| s.allocs -= 1);
+    }
+
+    unsafe fn contigmalloc(
+        self,
+        size: usize,
+        ty: *mut Self::Malloc,
+        flags: MallocFlags,
+        low: u64,
+        high: u64,
+        alignment: usize,
+        boundary: u64,
+    ) -> *mut u8 {
+        unsafe { (*ty).check() };
+        check_wait(flags);
+
+        if Self::fault(Call::Malloc).is_some() {
+            return null_mut();
+        }
+
+        // Allocate.
+        let layout = match Layout::from_size_align(size, alignment.max(Self::PAGE_SIZE)) {
+            Ok(v) => v,
+            Err(_) => return null_mut(),
+        };
+
+        let mem = match flags.contains(MallocFlags::ZERO) {
+            true => unsafe { System.alloc_zeroed(layout) },
+            false => unsafe { System.alloc(layout) },
+        };
+
+        if mem.is_null() {
+            return null_mut();
+        }
+
+        // Check constraints.
+        let start = mem as u64;
+        let end = start + size as u64 - 1;
+
+        if start < low || end > high || (boundary != 0 && start / boundary != end / boundary) {
+            unsafe { System.dealloc(mem, layout) };
+            return null_mut();
+        }
+
+        CONTIG
+            .lock()
+            .unwrap()
+            .get_or_insert_default()
+            .insert(mem as usize, (layout, ty as usize));
+
+        state::with(|s| s.allocs += 1);
+
+        mem
+    }
+
+    unsafe fn critical_enter(self) {
+        state::with(|s| s.critnest += 1);
+    }
+
+    unsafe fn critical_exit(self) {
+        state::with(|s| {
+            assert_ne!(s.critnest, 0);
+            s.critnest -= 1;
+        });
+    }
+
+    unsafe fn cv_broadcastpri(self, cv: *mut Self::Cv, _: c_int) {
+        let n = sleepq::wakeup(cv as usize, true);
+
+        unsafe { (*cv).waiters().fetch_sub(n as i32, Ordering::Relaxed) };
+    }
+
+    unsafe fn cv_destroy(self, cv: *mut Self::Cv) {
+        assert_eq!(
+            unsafe { (*cv).waiters().load(Ordering::Relaxed) },
+            0,
+            "destroying a cv with waiters"
+        );
+    }
+
+    unsafe fn cv_init(self, cv: *mut Self::Cv, desc: *const c_char) {
+        unsafe { cv.write(Cv::new(desc)) };
+    }
+
+    unsafe fn cv_signal(self, cv: *mut Self::Cv) {
+        let n = sleepq::wakeup(cv as usize, false);
+
+        unsafe { (*cv).waiters().fetch_sub(n as i32, Ordering::Relaxed) };
+    }
+
+    unsafe fn cv_timedwait(
+        self,
+        cv: *mut Self::Cv,
+        lock: *mut Self::LockObject,
+        timo: c_int,
+    ) -> c_int {
+        unsafe { cv_sleep(cv, lock, timo) }
+    }
+
+    unsafe fn cv_timedwait_sig(
+        self,
+        cv: *mut Self::Cv,
+        lock: *mut Self::LockObject,
+        timo: c_int,
+    ) -> c_int {
+        if let Some(e) = Self::errno(Call::Sleep) {
+            return e;
+        }
+
+        unsafe { cv_sleep(cv, lock, timo) }
+    }
+
+    unsafe fn cv_wait(self, cv: *mut Self::Cv, lock: *mut Self::LockObject) {
+        unsafe { cv_sleep(cv, lock, 0) };
+    }
+
+    unsafe fn cv_wait_sig(self, cv: *mut Self::Cv, lock: *mut Self::LockObject) -> c_int {
+        if let Some(e) = Self::errno(Call::Sleep) {
+            return e;
+        }
+
+        unsafe { cv_sleep(cv, lock, 0) }
+    }
+
+    unsafe fn fget(
+        self,
+        _: *mut Self::Thread,
+        fd: c_int,
+        fp: *mut *mut Self::File,
+        _: c_int,
+        _: *mut u8,
+    ) -> c_int {
+        if let Some(e) = Self::errno(Call::Fget) {
+            return e;
+        }
+
+        let file = match state::with(|s| s.file(fd)) {
+            Some(v) => v,
+            None => return EBADF,
+        };
+
+        unsafe { (*file).refcnt().fetch_add(1, Ordering::Relaxed) };
+        unsafe { *fp = file };
+
+        0
+    }
+
+    unsafe fn fget_write(
+        self,
+        _: *mut Self::Thread,
+        fd: c_int,
+        _: c_int,
+        fp: *mut *mut Self::File,
+    ) -> c_int {
+        if let Some(e) = Self::errno(Call::FgetWrite) {
+            return e;
+        }
+
+        let file = match state::with(|s| s.file(fd)) {
+            Some(v) if unsafe { (*v).is_writable() } => v,
+            _ => return EBADF,
+        };
+
+        unsafe { (*file).refcnt().fetch_add(1, Ordering::Relaxed) };
+        unsafe { *fp = file };
+
+        0
+    }
+
+    unsafe fn fdrop(self, fp: *mut Self::File, _: *mut Self::Thread) -> c_int {
+        assert_eq!(unsafe { (*fp).refcnt().load(Ordering::Acquire) }, 0);
+
+        drop(unsafe { Box::from_raw(fp) });
+
+        0
+    }
+
+    unsafe fn free(self, addr: *mut u8, ty: *mut Self::Malloc) {
+        let ty = unsafe { &*ty };
+
+        ty.check();
+
+        if addr.is_null() {
+            return;
+        }
+
+        let (addr, layout) = unsafe { alloc_header(addr) };
+
+        unsafe { System.dealloc(addr, layout) };
+
+        ty.inuse().fetch_sub(1, Ordering::Relaxed);
+        state::with(|s| s.allocs -= 1);
+    }
+
+    unsafe fn kern_openat(
+        self,
+        td: *mut Self::Thread,
+        fd: c_int,
+        path: *const c_char,
+        seg: UioSeg,
+        flags: OpenFlags,
+        _: c_int,
+    ) -> c_int {
+        if let Some(e) = Self::errno(Call::KernOpenat) {
+            return e;
+        }
+
+        if seg != UioSeg::Kernel {
+            return EFAULT;
+        }
+
+        let path = match unsafe { CStr::from_ptr(path).to_str() } {
+            Ok(v) => v,
+            Err(_) => return EINVAL,
+        };
+
+        if fd != AT_FDCWD && !path.starts_with('/') {
+            return EBADF;
+        }
+
+        // Get the node.
+        let file = state::with(|s| {
+            let node = match s.lookup(path) {
+                Ok(_) if flags.contains(OpenFlags::O_CREAT | OpenFlags::O_EXCL) => {
+                    return Err(errno::EEXIST);
+                }
+                Ok(v) => v,
+                Err(ENOENT) if flags.contains(OpenFlags::O_CREAT) => s.create(path, false)?,
+                Err(e) => return Err(e),
+            };
+
+            let file = File::new(node, flags);
+
+            match &mut s.node_mut(node).kind {
+                NodeKind::File(_) if !file.is_writable() => {}
+                NodeKind::File(v) if flags.contains(OpenFlags::O_TRUNC) => v.clear(),
+                NodeKind::File(_) => {}
+                NodeKind::Dir(_) if file.is_writable() => return Err(EISDIR),
+                NodeKind::Dir(_) => {}
+            }
+
+            Ok(s.install(Box::into_raw(Box::new(file))))
+        });
+
+        match file {
+            Ok(fd) => {
+                unsafe { (*td).set_ret(0, fd.try_into().unwrap()) };
+                0
+            }
+            Err(e) => e,
+        }
+    }
+
+    unsafe fn kern_close(self, _: *mut Self::Thread, fd: c_int) -> c_int {
+        if let Some(e) = Self::errno(Call::KernClose) {
+            return e;
+        }
+
+        let fp = match state::with(|s| s.remove(fd)) {
+            Some(v) => v,
+            None => return EBADF,
+        };
+
+        if unsafe { (*fp).refcnt().fetch_sub(1, Ordering::Release) } == 1 {
+            unsafe { self.fdrop(fp, null_mut()) };
+        }
+
+        0
+    }
+
+    unsafe fn kern_fsync(self, _: *mut Self::Thread, fd: c_int, _: c_int) -> c_int {
+        if let Some(e) = Self::errno(Call::KernFsync) {
+            return e;
+        }
+
+        match state::with(|s| s.file(fd)) {
+            Some(_) => 0,
+            None => EBADF,
+        }
+    }
+
+    unsafe fn kern_writev(self, td: *mut Self::Thread, fd: c_int, auio: *mut Self::Uio) -> c_int {
+        let limit = match Self::limit(Call::KernWritev) {
+            Ok(v) => v,
+            Err(e) => return e,
+        };
+
+        let fp = match state::with(|s| s.file(fd)) {
+            Some(v) if unsafe { (*v).is_writable() } => v,
+            _ => return EBADF,
+        };
+
+        // Collect data to write.
+        let uio = unsafe { &mut *auio };
+        let len = usize::try_from(uio.res).unwrap().min(limit);
+        let mut data = vec![0u8; len];
+        let len = unsafe { uiomove(data.as_mut_ptr(), len, uio) };
+
+        // Write.
+        let file = unsafe { &mut *fp };
+        let end = file.offset + len;
+
+        state::with(|s| match &mut s.node_mut(file.node).kind {
+            NodeKind::File(v) => {
+                if v.len() < end {
+                    v.resize(end, 0);
+                }
+
+                v[file.offset..end].copy_from_slice(&data[..len]);
+            }
+            NodeKind::Dir(_) => unreachable!(),
+        });
+
+        file.offset = end;
+
+        unsafe { (*td).set_ret(0, len) };
+
+        0
+    }
+
+    unsafe fn kmem_alloc(self, _: *mut Self::VmMap, size: usize) -> *mut u8 {
+        assert_eq!(size % Self::PAGE_SIZE, 0);
+
+        if Self::fault(Call::Malloc).is_some() {
+            return null_mut();
+        }
+
+        let layout = Layout::from_size_align(size, Self::PAGE_SIZE).unwrap();
+        let mem = unsafe { System.alloc_zeroed(layout) };
+
+        if !mem.is_null() {
+            state::with(|s| s.allocs += 1);
+        }
+
+        mem
+    }
+
+    unsafe fn kmem_free(self, _: *mut Self::VmMap, addr: *mut u8, size: usize) {
+        let layout = Layout::from_size_align(size, Self::PAGE_SIZE).unwrap();
+
+        unsafe { System.dealloc(addr, layout) };
+
+        state::with(|s| s.allocs -= 1);
+    }
+
+    unsafe fn malloc(self, size: usize, ty: *mut Self::Malloc, flags: MallocFlags) -> *mut u8 {
+        let ty = unsafe { &*ty };
+
+        ty.check();
+        check_wait(flags);
+
+        if Self::fault(Call::Malloc).is_some() {
+            return null_mut();
+        }
+
+        // Allocate.
+        let layout = match Layout::from_size_align(ALLOC_HEADER + size, ALLOC_HEADER) {
+            Ok(v) => v,
+            Err(_) => return null_mut(),
+        };
+
+        let mem = match flags.contains(MallocFlags::ZERO) {
+            true => unsafe { System.alloc_zeroed(layout) },
+            false => unsafe { System.alloc(layout) },
+        };
+
+        if mem.is_null() {
+            return null_mut();
+        }
+
+        // Store allocation size so we can free it later.
+        unsafe { mem.cast::<usize>().write(size) };
+
+        ty.inuse().fetch_add(1, Ordering::Relaxed);
+        state::with(|s| s.allocs += 1);
+
+        unsafe { mem.add(ALLOC_HEADER) }
+    }
+
+    unsafe fn malloc_init(self, ty: *mut Self::Malloc) {
+        unsafe { (*ty).set_registered(true) };
+    }
+
+    unsafe fn malloc_uninit(self, ty: *mut Self::Malloc) {
+        unsafe { (*ty).set_registered(false) };
+    }
+
+    unsafe fn mtx_destroy(self, m: *mut Self::Mtx) {
+        assert!(!unsafe { (*m).is_owned() }, "destroying a locked mutex");
+    }
+
+    unsafe fn mtx_init(
+        self,
+        m: *mut Self::Mtx,
+        name: *const c_char,
+        _: *const c_char,
+        opts: c_int,
+    ) {
+        let mtx = match opts & Self::MTX_SPIN {
+            0 => Mtx::new(name),
+            _ => Mtx::new_spin(name),
+        };
+
+        unsafe { m.write(mtx) };
+    }
+
+    unsafe fn mtx_lock_flags(self, m: *mut Self::Mtx, _: c_int, _: *const c_char, _: c_int) {
+        assert!(!unsafe { (*m).is_spin() }, "mtx_lock on a spin mutex");
+        check_sleepable();
+
+        unsafe { Mtx::lock(m, Pcpu::curthread() as usize) };
+    }
+
+    unsafe fn mtx_lock_spin_flags(self, m: *mut Self::Mtx, _: c_int, _: *const c_char, _: c_int) {
+        assert!(unsafe { (*m).is_spin() }, "mtx_lock_spin on a sleep mutex");
+
+        unsafe { self.spinlock_enter() };
+        unsafe { Mtx::lock(m, Pcpu::curthread() as usize) };
+    }
+
+    unsafe fn mtx_trylock_flags(
+        self,
+        m: *mut Self::Mtx,
+        _: c_int,
+        _: *const c_char,
+        _: c_int,
+    ) -> c_int {
+        assert!(!unsafe { (*m).is_spin() }, "mtx_trylock on a spin mutex");
+
+        unsafe { Mtx::try_lock(m, Pcpu::curthread() as usize) }.into()
+    }
+
+    unsafe fn mtx_unlock_flags(self, m: *mut Self::Mtx, _: c_int, _: *const c_char, _: c_int) {
+        unsafe { Mtx::unlock(m, Pcpu::curthread() as usize) };
+    }
+
+    unsafe fn mtx_unlock_spin_flags(self, m: *mut Self::Mtx, _: c_int, _: *const c_char, _: c_int) {
+        unsafe { Mtx::unlock(m, Pcpu::curthread() as usize) };
+        unsafe { self.spinlock_exit() };
+    }
+
+    unsafe fn pmap_kextract(self, va: usize) -> u64 {
+        va as u64
+    }
+
+    unsafe fn realloc(
+        self,
+        addr: *mut u8,
+        size: usize,
+        ty: *mut Self::Malloc,
+        flags: MallocFlags,
+    ) -> *mut u8 {
+        if addr.is_null() {
+            return unsafe { self.malloc(size, ty, flags) };
+        }
+
+        unsafe { (*ty).check() };
+        check_wait(flags);
+
+        if Self::fault(Call::Realloc).is_some() {
+            return null_mut();
+        }
+
+        // Resize.
+        let (addr, layout) = unsafe { alloc_header(addr) };
+        let mem = match ALLOC_HEADER.checked_add(size) {
+            Some(v) => unsafe { System.realloc(addr, layout, v) },
+            None => return null_mut(),
+        };
+
+        if mem.is_null() {
+            return null_mut();
+        }
+
+        unsafe { mem.cast::<usize>().write(size) };
+        unsafe { mem.add(ALLOC_HEADER) }
+    }
+
+    unsafe fn rw_rlock(self, rw: *mut Self::RwLock, _: *const c_char, _: c_int) {
+        unsafe { (*rw).state().slock(Pcpu::curthread() as usize) };
+    }
+
+    unsafe fn rw_runlock(self, rw: *mut Self::RwLock, _: *const c_char, _: c_int) {
+        unsafe { (*rw).state().sunlock() };
+    }
+
+    unsafe fn rw_try_rlock(self, rw: *mut Self::RwLock, _: *const c_char, _: c_int) -> c_int {
+        unsafe { (*rw).state().try_slock(Pcpu::curthread() as usize) }.into()
+    }
+
+    unsafe fn rw_try_wlock(self, rw: *mut Self::RwLock, _: *const c_char, _: c_int) -> c_int {
+        unsafe { (*rw).state().try_xlock(Pcpu::curthread() as usize) }.into()
+    }
+
+    unsafe fn rw_wlock(self, rw: *mut Self::RwLock, _: *const c_char, _: c_int) {
+        unsafe { (*rw).state().xlock(Pcpu::curthread() as usize) };
+    }
+
+    unsafe fn rw_wunlock(self, rw: *mut Self::RwLock, _: *const c_char, _: c_int) {
+        unsafe { (*rw).state().xunlock(Pcpu::curthread() as usize) };
+    }
+
+    unsafe fn sleep(
+        self,
+        ident: *mut (),
+        lock: *mut Self::LockObject,
+        priority: c_int,
+        _: *const c_char,
+        timo: c_int,
+    ) -> c_int {
+        if let Some(e) = Self::errno(Call::Sleep) {
+            return e;
+        }
+
+        check_sleepable();
+
+        // Release the interlock after we are on the queue so the wakeup will not be lost.
+        let sleeper = Sleeper::new(ident as usize);
+        let td = Pcpu::curthread() as usize;
+        let held = match lock.is_null() {
+            true => None,
+            false => Some(unsafe { LockObject::unlock(lock, td) }),
+        };
+
+        let woken = sleeper.sleep(timeout(timo));
+
+        if let Some(h) = held
+            && (priority & PDROP) == 0
+        {
+            unsafe { LockObject::lock(lock, td, h) };
+        }
+
+        match woken {
+            true => 0,
+            false => EWOULDBLOCK,
+        }
+    }
+
+    unsafe fn smp_rendezvous(
+        self,
+        setup: Option<unsafe extern "C" fn(*mut c_void)>,
+        action: Option<unsafe extern "C" fn(*mut c_void)>,
+        teardown: Option<unsafe extern "C" fn(*mut c_void)>,
+        arg: *mut c_void,
+    ) {
+        // The calling thread is the only CPU.
+        unsafe { self.spinlock_enter() };
+
+        for f in [setup, action, teardown].into_iter().flatten() {
+            unsafe { f(arg) };
+        }
+
+        unsafe { self.spinlock_exit() };
+    }
+
+    unsafe fn soaccept(self, so: *mut Self::Socket, nam: *mut *mut SockAddr) -> c_int {
+        if let Some(e) = Self::errno(Call::Soaccept) {
+            return e;
+        }
+
+        let addr = match unsafe { ((*so).backlog, &(*so).addr) } {
+            (Some(_), Some(v)) => SockAddrIn::new(v.sin_addr, u16::from_be(v.sin_port)),
+            _ => return EINVAL,
+        };
+
+        // The caller is responsible to free the address.
+        let mem = unsafe { self.malloc(size_of::<SockAddrIn>(), null_mut(), MallocFlags::WAITOK) };
+
+        unsafe { mem.cast::<SockAddrIn>().write(addr) };
+        unsafe { *nam = mem.cast() };
+
+        0
+    }
+
+    unsafe fn sobind(
+        self,
+        so: *mut Self::Socket,
+        nam: *mut SockAddr,
+        _: *mut Self::Thread,
+    ) -> c_int {
+        if let Some(e) = Self::errno(Call::Sobind) {
+            return e;
+        }
+
+        // Check address.
+        let so = unsafe { &mut *so };
+        let nam = unsafe { &*nam };
+
+        if nam.sa_family != AF_INET as u8 || usize::from(nam.sa_len) != size_of::<SockAddrIn>() {
+            return EAFNOSUPPORT;
+        }
+
+        if so.addr.is_some() {
+            return EINVAL;
+        }
+
+        // Bind.
+        let nam = unsafe { (nam as *const SockAddr).cast::<SockAddrIn>().read() };
+        let port = u16::from_be(nam.sin_port);
+
+        if port != 0 && !state::with(|s| s.ports.insert(port)) {
+            return EADDRINUSE;
+        }
+
+        so.addr = Some(nam);
+
+        0
+    }
+
+    unsafe fn soclose(self, so: *mut Self::Socket) -> c_int {
+        let so = unsafe { Box::from_raw(so) };
+
+        state::with(|s| {
+            if let Some(v) = &so.addr {
+                s.ports.remove(&u16::from_be(v.sin_port));
+            }
+
+            s.sockets -= 1;
+        });
+
+        0
+    }
+
+    unsafe fn socreate(
+        self,
+        dom: c_int,
+        aso: *mut *mut Self::Socket,
+        ty: c_int,
+        _: c_int,
+        _: *mut Self::Ucred,
+        _: *mut Self::Thread,
+    ) -> c_int {
+        if let Some(e) = Self::errno(Call::Socreate) {
+            return e;
+        }
+
+        if dom != AF_INET {
+            return EAFNOSUPPORT;
+        }
+
+        if ty != SOCK_STREAM && ty != SOCK_DGRAM {
+            return EPROTOTYPE;
+        }
+
+        state::with(|s| s.sockets += 1);
+
+        unsafe { *aso = Box::into_raw(Box::new(Socket::new(ty))) };
+
+        0
+    }
+
+    unsafe fn solisten(self, so: *mut Self::Socket, backlog: c_int, _: *mut Self::Thread) -> c_int {
+        if let Some(e) = Self::errno(Call::Solisten) {
+            return e;
+        }
+
+        let so = unsafe { &mut *so };
+
+        if so.ty != SOCK_STREAM {
+            return EOPNOTSUPP;
+        }
+
+        so.backlog = Some(backlog);
+
+        0
+    }
+
+    unsafe fn spinlock_enter(self) {
+        state::with(|s| s.spinlocks += 1);
+    }
+
+    unsafe fn spinlock_exit(self) {
+        state::with(|s| {
+            assert_ne!(s.spinlocks, 0);
+            s.spinlocks -= 1;
+        });
+    }
+
+    unsafe fn strlen(self, s: *const c_char) -> usize {
+        unsafe { CStr::from_ptr(s).count_bytes() }
+    }
+
+    unsafe fn sx_destroy(self, sx: *mut Self::Sx) {
+        assert!(
+            !unsafe { (*sx).state().is_locked() },
+            "destroying a locked sx"
+        );
+    }
+
+    unsafe fn sx_init(self, sx: *mut Self::Sx, desc: *const c_char, _: c_int) {
+        unsafe { sx.write(Sx::new(desc)) };
+    }
+
+    unsafe fn sx_slock(self, sx: *mut Self::Sx, _: c_int, _: *const c_char, _: c_int) -> c_int {
+        unsafe { (*sx).state().slock(Pcpu::curthread() as usize) };
+        0
+    }
+
+    unsafe fn sx_sunlock(self, sx: *mut Self::Sx, _: *const c_char, _: c_int) {
+        unsafe { (*sx).state().sunlock() };
+    }
+
+    unsafe fn sx_try_slock(self, sx: *mut Self::Sx, _: *const c_char, _: c_int) -> c_int {
+        unsafe { (*sx).state().try_slock(Pcpu::curthread() as usize) }.into()
+    }
+
+    unsafe fn sx_try_xlock(self, sx: *mut Self::Sx, _: *const c_char, _: c_int) -> c_int {
+        unsafe { (*sx).state().try_xlock(Pcpu::curthread() as usize) }.into()
+    }
+
+    unsafe fn sx_xlock(self, sx: *mut Self::Sx, _: c_int, _: *const c_char, _: c_int) -> c_int {
+        unsafe { (*sx).state().xlock(Pcpu::curthread() as usize) };
+        0
+    }
+
+    unsafe fn sx_xunlock(self, sx: *mut Self::Sx, _: *const c_char, _: c_int) {
+        unsafe { (*sx).state().xunlock(Pcpu::curthread() as usize) };
+    }
+
+    unsafe fn uma_zcreate(
+        self,
+        name: *const c_char,
+        size: usize,
+        ctor: Option<UmaCtor>,
+        dtor: Option<UmaDtor>,
+        uminit: Option<UmaInit>,
+        fini: Option<UmaFini>,
+        align: c_int,
+        _: u32,
+    ) -> *mut Self::UmaZone {
+        assert!(
+            uminit.is_none() && fini.is_none(),
+            "uma_init and uma_fini is not supported"
+        );
+
+        let zone = UmaZone {
+            name,
+            layout: Layout::from_size_align(size, (align + 1).try_into().unwrap()).unwrap(),
+            ctor,
+            dtor,
+            items: AtomicUsize::new(0),
+        };
+
+        Box::into_raw(Box::new(zone))
+    }
+
+    unsafe fn uma_zdestroy(self, zone: *mut Self::UmaZone) {
+        let zone = unsafe { Box::from_raw(zone) };
+        let name = unsafe { CStr::from_ptr(zone.name) };
+
+        assert_eq!(
+            zone.items.load(Ordering::Relaxed),
+            0,
+            "zone {name:?} was destroyed with items still allocated"
+        );
+    }
+
+    unsafe fn uma_zalloc_arg(
+        self,
+        zone: *mut Self::UmaZone,
+        arg: *mut c_void,
+        flags: MallocFlags,
+    ) -> *mut u8 {
+        let zone = unsafe { &*zone };
+
+        check_wait(flags);
+
+        if Self::fault(Call::UmaZalloc).is_some() {
+            return null_mut();
+        }
+
+        // Allocate.
+        let item = unsafe { System.alloc(zone.layout) };
+
+        if item.is_null() {
+            return null_mut();
+        }
+
+        // Construct.
+        let size = zone.layout.size().try_into().unwrap();
+
+        if let Some(ctor) = zone.ctor
+            && unsafe { ctor(item.cast(), size, arg, flags.bits()) } != 0
+        {
+            unsafe { System.dealloc(item, zone.layout) };
+            return null_mut();
+        }
+
+        zone.items.fetch_add(1, Ordering::Relaxed);
+
+        item
+    }
+
+    unsafe fn uma_zfree_arg(self, zone: *mut Self::UmaZone, item: *mut u8, arg: *mut c_void) {
+        let zone = unsafe { &*zone };
+
+        if item.is_null() {
+            return;
+        }
+
+        if let Some(dtor) = zone.dtor {
+            unsafe { dtor(item.cast(), zone.layout.size().try_into().unwrap(), arg) };
+        }
+
+        unsafe { System.dealloc(item, zone.layout) };
+
+        zone.items.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    unsafe fn vfs_busy(self, mp: *mut Self::Mount, _: c_int) -> c_int {
+        if let Some(e) = Self::errno(Call::VfsBusy) {
+            return e;
+        }
+
+        unsafe { (*mp).busy.fetch_add(1, Ordering::Relaxed) };
+
+        0
+    }
+
+    unsafe fn vfs_unbusy(self, mp: *mut Self::Mount) {
+        let prev = unsafe { (*mp).busy.fetch_sub(1, Ordering::Relaxed) };
+
+        assert_ne!(prev, 0);
+    }
+
+    unsafe fn vn_lock(
+        self,
+        vp: *mut Self::Vnode,
+        flags: c_int,
+        file: *const c_char,
+        line: c_int,
+    ) -> c_int {
+        let mut args = unsafe { VopLock::new(self, vp, flags, file, line) };
+
+        unsafe { self.vop_lock1((*vp).ops(), &mut args) }
+    }
+
+    unsafe fn vop_lock1(self, _: *mut Self::VopVector, args: *mut Self::VopLock) -> c_int {
+        let args = unsafe { &*args };
+
+        assert_eq!(args.desc, self.get(Self::VOP_LOCK1).as_mut_ptr());
+
+        unsafe { vlock(args.vp, args.flags) };
+
+        0
+    }
+
+    unsafe fn vop_lookup(
+        self,
+        _: *mut Self::VopVector,
+        args: &mut VopArgs<'_, Self, Self::VopLookup>,
+    ) -> c_int {
+        assert_eq!(args.desc, self.get(Self::VOP_LOOKUP).as_mut_ptr());
+
+        if let Some(e) = Self::errno(Call::VopLookup) {
+            return e;
+        }
+
+        // Get name to lookup.
+        let cn = unsafe { &*args.cn };
+        let name =
+            unsafe { std::slice::from_raw_parts(cn.name.cast(), cn.len.try_into().unwrap()) };
+        let name = match std::str::from_utf8(name) {
+            Ok(v) => v,
+            Err(_) => return ENOENT,
+        };
+
+        // Lookup.
+        let dir = unsafe { (*args.vp).node };
+        let vp = state::with(|s| {
+            let node = s.node(dir);
+            let node = match (&node.kind, name) {
+                (NodeKind::File(_), _) => return Err(ENOTDIR),
+                (NodeKind::Dir(_), ".") => dir,
+                (NodeKind::Dir(_), "..") => node.parent,
+                (NodeKind::Dir(c), n) => *c.get(n).ok_or(ENOENT)?,
+            };
+
+            Ok(s.vref(node))
+        });
+
+        match vp {
+            Ok(v) => {
+                unsafe { vlock(v, cn.lk) };
+                unsafe { *args.out = v };
+                0
+            }
+            Err(e) => e,
+        }
+    }
+
+    unsafe fn vop_read(
+        self,
+        _: *mut Self::VopVector,
+        args: &mut VopArgs<'_, Self, Self::VopRead>,
+    ) -> c_int {
+        assert_eq!(args.desc, self.get(Self::VOP_READ).as_mut_ptr());
+
+        let limit = match Self::limit(Call::VopRead) {
+            Ok(v) => v,
+            Err(e) => return e,
+        };
+
+        // Get data to read.
+        let uio = unsafe { &mut *args.uio };
+        let node = unsafe { (*args.vp).node };
+        let mut data = match state::with(|s| match &s.node(node).kind {
+            NodeKind::File(v) => Ok(v.clone()),
+            NodeKind::Dir(_) => Err(EISDIR),
+        }) {
+            Ok(v) => v,
+            Err(e) => return e,
+        };
+
+        // Copy.
+        let off = usize::try_from(uio.off).unwrap().min(data.len());
+        let len = (data.len() - off).min(limit);
+
+        unsafe { uiomove(data[off..].as_mut_ptr(), len, uio) };
+
+        0
+    }
+
+    unsafe fn vop_readdir(
+        self,
+        _: *mut Self::VopVector,
+        args: &mut VopArgs<'_, Self, Self::VopReadDir>,
+    ) -> c_int {
+        assert_eq!(args.desc, self.get(Self::VOP_READDIR).as_mut_ptr());
+
+        let limit = match Self::limit(Call::VopReadDir) {
+            Ok(v) => v,
+            Err(e) => return e,
+        };
+
+        // Build directory entries.
+        let node = unsafe { (*args.vp).node };
+        let ents = match state::with(|s| {
+            let n = s.node(node);
+            let children = match &n.kind {
+                NodeKind::File(_) => return Err(ENOTDIR),
+                NodeKind::Dir(v) => v,
+            };
+
+            let mut ents = vec![(".".to_owned(), node), ("..".to_owned(), n.parent)];
+
+            ents.extend(children.iter().map(|(k, v)| (k.clone(), *v)));
+
+            Ok(ents
+                .into_iter()
+                .map(|(name, i)| {
+                    let ty = match s.node(i).kind {
+                        NodeKind::File(_) => DT_REG,
+                        NodeKind::Dir(_) => DT_DIR,
+                    };
+
+                    dirent(&name, i, ty)
+                })
+                .collect::<Vec<_>>())
+        }) {
+            Ok(v) => v,
+            Err(e) => return e,
+        };
+
+        // Skip the entries that already read.
+        let uio = unsafe { &mut *args.uio };
+        let mut off = 0;
+        let mut ents = ents.into_iter().peekable();
+
+        while off < usize::try_from(uio.off).unwrap() {
+            match ents.next() {
+                Some(v) => off += v.len(),
+                None => break,
+            }
+        }
+
+        if off != usize::try_from(uio.off).unwrap() {
+            return EINVAL;
+        }
+
+        // Copy the entries that fit.
+        let mut avail = usize::try_from(uio.res).unwrap().min(limit);
+        let mut copied = 0;
+
+        while let Some(ent) = ents.next_if(|v| v.len() <= avail) {
+            let mut ent = ent;
+
+            unsafe { uiomove(ent.as_mut_ptr(), ent.len(), uio) };
+
+            avail -= ent.len();
+            copied += 1;
+        }
+
+        if copied == 0 && ents.peek().is_some() {
+            return EINVAL;
+        }
+
+        if !args.eof.is_null() {
+            unsafe { *args.eof = ents.peek().is_none().into() };
+        }
+
+        if !args.ncookies.is_null() {
+            unsafe { *args.ncookies = 0 };
+            unsafe { *args.cookies = null_mut() };
+        }
+
+        0
+    }
+
+    unsafe fn vop_unlock(self, _: *mut Self::VopVector, args: *mut Self::VopUnlock) -> c_int {
+        let args = unsafe { &*args };
+
+        assert_eq!(args.desc, self.get(Self::VOP_UNLOCK).as_mut_ptr());
+
+        unsafe { (*args.vp).lock.unlock(Pcpu::curthread() as usize) };
+
+        0
+    }
+
+    unsafe fn vput(self, vp: *mut Self::Vnode) {
+        let vp = unsafe { &mut *vp };
+
+        assert_ne!(vp.refs, 0);
+
+        vp.lock.unlock(Pcpu::curthread() as usize);
+        vp.refs -= 1;
+    }
+
+    unsafe fn wakeup(self, ident: *mut ()) {
+        sleepq::wakeup(ident as usize, true);
+    }
+
+    unsafe fn wakeup_one(self, ident: *mut ()) {
+        sleepq::wakeup(ident as usize, false);
+    }
+}
+
+const ALLOC_HEADER: usize = 16;
+const PDROP: c_int = 0x200;
+const DT_DIR: u8 = 4;
+const DT_REG: u8 = 8;
+
+/// Offset of the only [`VopVector`] in the kernel.
+const VOP_VECTOR: StaticMut<VopVector> = unsafe { StaticMut::new(offset_of!(Image, vop_vector)) };
+
+/// Sleeps on `cv` the same as `_cv_timedwait`. Zero `timo` means no timeout.
+///
+/// # Safety
+/// `cv` and `lock` cannot be null and `lock` must be held by the current thread.
+unsafe fn cv_sleep(cv: *mut Cv, lock: *mut LockObject, timo: c_int) -> c_int {
+    check_sleepable();
+
+    let sleeper = Sleeper::new(cv as usize);
+    let td = Pcpu::curthread() as usize;
+
+    unsafe { (*cv).waiters().fetch_add(1, Ordering::Relaxed) };
+
+    let held = unsafe { LockObject::unlock(lock, td) };
+
+    // The waker already removed us from the waiter count.
+    let woken = sleeper.sleep(timeout(timo));
+
+    if !woken {
+        unsafe { (*cv).waiters().fetch_sub(1, Ordering::Relaxed) };
+    }
+
+    unsafe { LockObject::lock(lock, td, held) };
+
+    match woken {
+        true => 0,
+        false => EWOULDBLOCK,
+    }
+}
+
+/// Panics if the current thread is not allowed to sleep.
+fn check_sleepable() {
+    state::with(|s| {
+        assert!(
+            s.critnest == 0 && s.spinlocks == 0,
+            "sleeping in a critical section"
+        )
+    });
+}
+
+/// Converts `timo` in ticks to [`Duration`]. Each tick is one millisecond on the mock.
+fn timeout(timo: c_int) -> Option<Duration> {
+    match u64::try_from(timo) {
+        Ok(v) if v != 0 => Some(Duration::from_millis(v)),
+        _ => None,
+    }
+}
+
+/// Locks `vp` with the lock type in `flags` the same as `lockmgr`.
+///
+/// # Safety
+/// `vp` cannot be null.
+unsafe fn vlock(vp: *mut Vnode, flags: c_int) {
+    let lock = unsafe { &(*vp).lock };
+    let td = Pcpu::curthread() as usize;
+
+    if flags & MockKernel::LK_EXCLUSIVE != 0 {
+        lock.xlock(td);
+    } else if flags & MockKernel::LK_SHARED != 0 {
+        lock.slock(td);
+    } else {
+        panic!("no lock type in {flags:#x}");
+    }
+}
+
+/// Returns the original address and layout of the memory from [`okf::Kernel::malloc()`].
+///
+/// # Safety
+/// `addr` must be the value returned from [`okf::Kernel::malloc()`].
+/// # Panics
+/// If `flags` does not contains exactly one of [`MallocFlags::WAITOK`] and
+/// [`MallocFlags::NOWAIT`].
+fn check_wait(flags: MallocFlags) {
+    if flags.contains(MallocFlags::WAITOK) == flags.contains(MallocFlags::NOWAIT) {
+        panic!("malloc flags must contains either M_WAITOK or M_NOWAIT");
+    }
+}
+
+unsafe fn alloc_header(addr: *mut u8) -> (*mut u8, Layout) {
+    let addr = unsafe { addr.sub(ALLOC_HEADER) };
+    let size = unsafe { addr.cast::<usize>().read() };
+
+    (addr, unsafe {
+        Layout::from_size_align_unchecked(ALLOC_HEADER + size, ALLOC_HEADER)
+    })
+}
+
+/// Move at most `n` bytes between `buf` and `uio` and returns the moved bytes.
+///
+/// The direction of the move is depend on [`Uio::op`].
+///
+/// # Safety
+/// `buf` must be valid for `n` bytes.
+unsafe fn uiomove(buf: *mut u8, n: usize, uio: &mut Uio) -> usize {
+    let mut moved = 0;
+
+    assert_eq!(uio.seg, UioSeg::Kernel);
+
+    while moved < n && uio.res > 0 && uio.len > 0 {
+        let iov = unsafe { &mut *uio.iov };
+        let len = iov.len.min(n - moved);
+
+        if len == 0 {
+            uio.iov = unsafe { uio.iov.add(1) };
+            uio.len -= 1;
+            continue;
+        }
+
+        match uio.op {
+            UioRw::Read => unsafe { iov.ptr.copy_from_nonoverlapping(buf.add(moved), len) },
+            UioRw::Write => unsafe { iov.ptr.copy_to_nonoverlapping(buf.add(moved), len) },
+        }
+
+        iov.ptr = unsafe { iov.ptr.add(len) };
+        iov.len -= len;
+        uio.res -= len as isize;
+        uio.off += len as isize;
+        moved += len;
+    }
+
+    moved
+}
+
+/// Build a `dirent` for `name`.
+fn dirent(name: &str, node: usize, ty: u8) -> Vec<u8> {
+    let hdr = size_of::<DirEnt<0>>();
+    let len = (hdr + name.len() + 1).next_multiple_of(4);
+    let mut buf = vec![0u8; len];
+
+    buf[..4].copy_from_slice(&u32::try_from(node + 1).unwrap().to_ne_bytes());
+    buf[4..6].copy_from_slice(&u16::try_from(len).unwrap().to_ne_bytes());
+    buf[6] = ty;
+    buf[7] = name.len().try_into().unwrap();
+    buf[hdr..(hdr + name.len())].copy_from_slice(name.as_bytes());
+
+    buf
+}
+
+/// Fake kernel image.
+#[repr(C)]
+struct Image {
+    accept: Mtx,
+    kernel_map: *mut VmMap,
+    vm_map: VmMap,
+    temp: Malloc,
+    mountlist: TailQueue<Mount>,
+    mountlist_mtx: Mtx,
+    root: Mount,
+    fs: Filesystem,
+    fs_ops: FsOps,
+    panic: u8,
+    vop_lock1: VnodeOp,
+    vop_lookup: VnodeOp,
+    vop_read: VnodeOp,
+    vop_readdir: VnodeOp,
+    vop_unlock: VnodeOp,
+    vop_vector: VopVector,
+    sysent: [Sysent; 32],
+    sdk_version: u32,
+}
+
+static mut IMAGE: Image = Image {
+    accept: Mtx::new(c"accept".as_ptr()),
+    kernel_map: unsafe { &raw mut IMAGE.vm_map },
+    vm_map: VmMap {},
+    temp: Malloc::registered(c"temp".as_ptr()),
+    mountlist: TailQueue {
+        first: unsafe { &raw mut IMAGE.root },
+        last: unsafe { &raw mut IMAGE.root.entry.next },
+        pin: PhantomPinned,
+    },
+    mountlist_mtx: Mtx::new(c"mountlist".as_ptr()),
+    root: Mount {
+        mtx: Mtx::new(c"struct mount mtx".as_ptr()),
+        entry: TailQueueEntry {
+            next: null_mut(),
+            prev: unsafe { &raw mut IMAGE.mountlist.first },
+            pin: PhantomPinned,
+        },
+        ops: unsafe { &raw const IMAGE.fs_ops },
+        fs: unsafe { &raw mut IMAGE.fs },
+        flags: 0,
+        stats: FsStats {
+            mounted_from: cstr(b"mockfs"),
+        },
+        busy: AtomicUsize::new(0),
+    },
+    fs: Filesystem {
+        name: cstr(b"mockfs"),
+    },
+    fs_ops: FsOps {},
+    panic: 0,
+    vop_lock1: VnodeOp::new(c"vop_lock1".as_ptr()),
+    vop_lookup: VnodeOp::new(c"vop_lookup".as_ptr()),
+    vop_read: VnodeOp::new(c"vop_read".as_ptr()),
+    vop_readdir: VnodeOp::new(c"vop_readdir".as_ptr()),
+    vop_unlock: VnodeOp::new(c"vop_unlock".as_ptr()),
+    vop_vector: VopVector {},
+    sysent: syscall::sysent(),
+    sdk_version: 0x8001,
+};
+
+const fn cstr<const L: usize>(s: &[u8]) -> [c_char; L] {
+    let mut buf = [0; L];
+    let mut i = 0;
+
+    while i < s.len() {
+        buf[i] = s[i] as c_char;
+        i += 1;
+    }
+
+    buf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::alloc::GlobalAlloc;
+    use core::fmt::Write;
+    use okf::Allocator;
+    use okf::Kernel;
+    use okf::cpu::{CriticalGuard, SpinlockGuard};
+    use okf::fd::{openat, write_all};
+    use okf::firmware::KernelVisitor;
+    use okf::kmem::{Contiguous, Pages, PhysRange};
+    use okf::lock::{self, Mtx as _, MtxLock, RwReadLock, RwWriteLock};
+    use okf::malloc::MallocType;
+    use okf::mount::{FsOps as _, Mount as _};
+    use okf::namei::ComponentName as _;
+    use okf::notification::Notification;
+    use okf::proc::Proc as _;
+    use okf::socket::{InAddr, OwnedSocket, bind, listen};
+    use okf::syscall::{Syscall, SyscallError, Sysent as _, call};
+    use okf::trace::syscalls::{Filter, SyscallTrace, TraceError};
+    use okf::uio::{IoVec, Uio as _};
+    use okf::uma::Zone;
+    use okf::vnode::{LockedVnode, VopLookup as _, VopRead as _};
+    use std::sync::Mutex;
+
+    /// Lock for the tests that use `sysent` table since it is shared between all threads.
+    static SYSENT: Mutex<()> = Mutex::new(());
+
+    #[test]
+    fn write_file() {
+        let k = MockKernel;
+        let flags = OpenFlags::O_WRONLY | OpenFlags::O_CREAT;
+
+        k.create_dir("/data");
+
+        // Write.
+        let fd = unsafe { openat(k, AT_FDCWD, c"/data/log".as_ptr(), UioSeg::Kernel, flags, 0) };
+        let fd = fd.unwrap();
+
+        unsafe { write_all(k, fd.as_raw_fd(), b"Hello", Pcpu::curthread()).unwrap() };
+        unsafe { write_all(k, fd.as_raw_fd(), b", world!", Pcpu::curthread()).unwrap() };
+
+        assert_eq!(k.open_files(), 1);
+        drop(fd);
+
+        assert_eq!(k.open_files(), 0);
+        assert_eq!(k.read_file("/data/log").unwrap(), b"Hello, world!");
+
+        // Open a file that does not exists.
+        let path = c"/data/missing".as_ptr();
+        let flags = OpenFlags::O_RDONLY;
+
+        match unsafe { openat(k, AT_FDCWD, path, UioSeg::Kernel, flags, 0) } {
+            Ok(_) => panic!("opening a missing file should fail"),
+            Err(e) => assert_eq!(e.get(), ENOENT),
+        }
+    }
+
+    #[test]
+    fn write_all_retry() {
+        let k = MockKernel;
+        let flags = OpenFlags::O_WRONLY | OpenFlags::O_CREAT;
+        let fd = unsafe { openat(k, AT_FDCWD, c"/log".as_ptr(), UioSeg::Kernel, flags, 0) };
+        let fd = fd.unwrap();
+
+        // Interrupted and short writes should be retried.
+        k.inject(Call::KernWritev, Fault::Errno(MockKernel::EINTR));
+        k.inject(Call::KernWritev, Fault::Short(3));
+        k.inject(Call::KernWritev, Fault::Errno(MockKernel::EINTR));
+        k.inject(Call::KernWritev, Fault::Short(4));
+
+        unsafe { write_all(k, fd.as_raw_fd(), b"Hello, world!", Pcpu::curthread()).unwrap() };
+
+        assert_eq!(k.read_file("/log").unwrap(), b"Hello, world!");
+
+        // Writing nothing should fail with EIO.
+        k.inject(Call::KernWritev, Fault::Short(0));
+
+        assert_eq!(
+            unsafe { write_all(k, fd.as_raw_fd(), b"abc", Pcpu::curthread()) },
+            Err(MockKernel::EIO)
+        );
+
+        // Other errors should be returned as-is.
+        let e = NonZero::new(EFAULT).unwrap();
+
+        k.inject(Call::KernWritev, Fault::Errno(e));
+
+        assert_eq!(
+            unsafe { write_all(k, fd.as_raw_fd(), b"abc", Pcpu::curthread()) },
+            Err(e)
+        );
+    }
+
+    #[test]
+    fn socket() {
+        let k = MockKernel;
+        let td = Pcpu::curthread();
+        let mut addr = SockAddrIn::new(InAddr::ANY, 9020);
+
+        // Bind and listen.
+        let so = unsafe { OwnedSocket::new(k, AF_INET, SOCK_STREAM, 0, td).unwrap() };
+
+        unsafe { bind(k, so.as_raw(), addr.as_mut(), td).unwrap() };
+        unsafe { listen(k, so.as_raw(), 128, td).unwrap() };
+
+        // Bind on the same port.
+        let other = unsafe { OwnedSocket::new(k, AF_INET, SOCK_STREAM, 0, td).unwrap() };
+
+        assert_eq!(
+            unsafe { bind(k, other.as_raw(), addr.as_mut(), td) },
+            Err(NonZero::new(EADDRINUSE).unwrap())
+        );
+
+        assert_eq!(k.live_sockets(), 2);
+        drop(other);
+        drop(so);
+        assert_eq!(k.live_sockets(), 0);
+
+        // Inject error.
+        let e = NonZero::new(EPROTOTYPE).unwrap();
+
+        k.inject(Call::Socreate, Fault::Errno(e));
+
+        assert!(matches!(
+            unsafe { OwnedSocket::new(k, AF_INET, SOCK_STREAM, 0, td) },
+            Err(v) if v == e
+        ));
+    }
+
+    #[test]
+    fn notification() {
+        let k = MockKernel;
+        let mut n = Notification::new();
+
+        // The first device does not exists so it should fallback to the second one.
+        k.create_file("/dev/notification1", []);
+
+        write!(n, "Hello, world!").unwrap();
+        n.send(k);
+
+        let data = k.read_file("/dev/notification1").unwrap();
+
+        assert_eq!(data.len(), 0xC30);
+        assert_eq!(&data[0x2D..0x3B], b"Hello, world!\0");
+        assert_eq!(k.open_files(), 0);
+    }
+
+    #[test]
+    fn allocator() {
+        let k = MockKernel;
+        let a = Allocator::<MockKernel>::new();
+
+        // Aligned allocation.
+        let layout = Layout::from_size_align(100, 64).unwrap();
+        let mem = unsafe { a.alloc(layout) };
+
+        assert!(!mem.is_null());
+        assert_eq!(mem as usize % 64, 0);
+        assert_eq!(k.live_allocations(), 1);
+
+        unsafe { mem.write_bytes(0xFF, 100) };
+        unsafe { a.dealloc(mem, layout) };
+
+        // Zeroed allocation.
+        let layout = Layout::from_size_align(32, 8).unwrap();
+        let mem = unsafe { a.alloc_zeroed(layout) };
+        let data = unsafe { std::slice::from_raw_parts(mem, 32) };
+
+        assert!(data.iter().all(|&b| b == 0));
+
+        unsafe { a.dealloc(mem, layout) };
+
+        assert_eq!(k.live_allocations(), 0);
+
+        // Out of memory.
+        k.inject(Call::Malloc, Fault::Errno(NonZero::new(12).unwrap()));
+
+        assert!(unsafe { a.alloc(layout) }.is_null());
+        assert_eq!(k.live_allocations(), 0);
+    }
+
+    #[test]
+    fn any_kernel() {
+        okf::any_kernel! {
+            enum AnyKernel {
+                Mock(MockKernel),
+            }
+        }
+
+        struct Send;
+
+        impl KernelVisitor for Send {
+            type Output = ();
+
+            fn visit<K: Kernel>(self, k: K) {
+                let mut n = Notification::new();
+
+                write!(n, "Hello, world!").unwrap();
+                n.send(k);
+            }
+        }
+
+        let k = AnyKernel::detect().unwrap();
+
+        MockKernel.create_file("/dev/notification0", []);
+
+        assert_eq!(k.version(), 0);
+        k.with(Send);
+
+        assert!(
+            !MockKernel
+                .read_file("/dev/notification0")
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn vfs() {
+        let k = MockKernel;
+        let td = Pcpu::curthread();
+
+        k.create_file("/system/config", "abcdef");
+
+        // Get root vnode.
+        let mtx = k.get(MockKernel::MOUNTLIST_MTX).as_mut_ptr();
+        let lock = unsafe { MtxLock::new(k, mtx) };
+        let mp = unsafe { (*k.get(MockKernel::MOUNTLIST).as_mut_ptr()).first };
+
+        drop(lock);
+
+        let root = unsafe { (*mp).ops().root(mp, MockKernel::LK_SHARED).unwrap() };
+        let root = unsafe { LockedVnode::from_raw(k, root) };
+
+        // Lookup.
+        let lk = MockKernel::LK_SHARED;
+        let mut name = *b"system\0";
+        let mut cn =
+            unsafe { ComponentName::new(k, MockKernel::LOOKUP, lk, name.as_mut_ptr().cast(), td) };
+
+        let mut dir = null_mut();
+        let mut args = unsafe { VopLookup::new(k, &root, &mut dir, &mut cn) };
+
+        assert_eq!(unsafe { k.vop_lookup(null_mut(), &mut args) }, 0);
+
+        let dir = unsafe { LockedVnode::from_raw(k, dir) };
+        let mut name = *b"config\0";
+        let mut cn =
+            unsafe { ComponentName::new(k, MockKernel::LOOKUP, lk, name.as_mut_ptr().cast(), td) };
+
+        let mut vp = null_mut();
+        let mut args = unsafe { VopLookup::new(k, &dir, &mut vp, &mut cn) };
+
+        assert_eq!(unsafe { k.vop_lookup(null_mut(), &mut args) }, 0);
+
+        let vp = unsafe { LockedVnode::from_raw(k, vp) };
+
+        // Read.
+        let mut buf = [0u8; 4];
+        let mut vec = IoVec {
+            ptr: buf.as_mut_ptr(),
+            len: buf.len(),
+        };
+
+        let mut uio = unsafe { Uio::read(&mut vec, 2, td).unwrap() };
+        let mut args = unsafe { VopRead::new(k, &vp, &mut uio, 0, (*td).cred()) };
+
+        assert_eq!(unsafe { k.vop_read(null_mut(), &mut args) }, 0);
+        assert_eq!(&buf, b"cdef");
+        assert_eq!(uio.remaining(), 0);
+        assert_eq!(uio.offset(), 6);
+
+        // Relock with our own reference.
+        let raw = state::with(|s| s.vref(unsafe { (*vp.as_ptr()).node }));
+
+        drop(vp);
+
+        let vp = unsafe { LockedVnode::new(k, raw, MockKernel::LK_EXCLUSIVE).unwrap() };
+
+        assert!(unsafe { (*raw).lock.is_locked() });
+
+        drop(vp);
+
+        assert!(!unsafe { (*raw).lock.is_locked() });
+        assert_eq!(unsafe { (*raw).refs }, 1);
+
+        unsafe { (*raw).refs -= 1 };
+    }
+
+    #[test]
+    fn syscall() {
+        let _lock = SYSENT.lock().unwrap();
+
+        #[repr(C)]
+        struct Args {
+            a: usize,
+            b: usize,
+        }
+
+        let k = MockKernel;
+        let sc = unsafe {
+            Syscall::register(k, None, |td: &mut Thread, args: &Args| {
+                match args.a.checked_add(args.b) {
+                    Some(v) => td.set_ret(0, v),
+                    None => return Err(NonZero::new(EINVAL).unwrap()),
+                }
+
+                Ok(())
+            })
+        };
+        let sc = sc.unwrap();
+        let entry = unsafe {
+            &*k.get(MockKernel::SYSENT)
+                .as_mut_ptr()
+                .add(sc.number() as usize)
+        };
+
+        assert_eq!(entry.narg(), 2);
+
+        // Invoke.
+        let td = Pcpu::curthread();
+        let mut args = Args { a: 1, b: 2 };
+
+        assert_eq!(unsafe { entry.call()(td, (&raw mut args).cast()) }, 0);
+        assert_eq!(unsafe { (*td).ret(0) }, 3);
+
+        args.a = usize::MAX;
+
+        assert_eq!(unsafe { entry.call()(td, (&raw mut args).cast()) }, EINVAL);
+
+        // The same entry cannot be registered twice.
+        let r = unsafe { Syscall::register(k, Some(sc.number()), |_: &mut Thread, _: &()| Ok(())) };
+
+        assert!(matches!(r, Err(SyscallError::Patch(_))));
+
+        // Unregister.
+        sc.unregister();
+
+        assert_eq!(entry.narg(), 0);
+        assert_eq!(unsafe { entry.call()(td, null_mut()) }, ENOSYS);
+    }
+
+    #[test]
+    fn syscall_call() {
+        let _lock = SYSENT.lock().unwrap();
+        let k = MockKernel;
+        let td = Pcpu::curthread();
+
+        // getpid.
+        let pid = unsafe { call(k, td, 20, &()) };
+
+        assert_eq!(pid, Ok(unsafe { (*(*td).proc()).pid().unwrap() as usize }));
+
+        // nosys should not be invoked.
+        unsafe { (*td).set_ret(0, 123) };
+
+        assert_eq!(unsafe { call(k, td, 0, &()) }, Err(MockKernel::ENOSYS));
+        assert_eq!(unsafe { call(k, td, 1, &()) }, Err(MockKernel::ENOSYS));
+        assert_eq!(unsafe { (*td).ret(0) }, 123);
+
+        // Out of range.
+        assert_eq!(unsafe { call(k, td, 32, &()) }, Err(MockKernel::ENOSYS));
+    }
+
+    #[test]
+    fn trace_syscalls() {
+        static TRACE: SyscallTrace<2> = SyscallTrace::new();
+
+        let _lock = SYSENT.lock().unwrap();
+        let k = MockKernel;
+        let td = Pcpu::curthread();
+        let pid = unsafe { (*(*td).proc()).pid().unwrap() };
+        let tracing = unsafe { TRACE.start(k, Filter::Pid(pid)).unwrap() };
+
+        // Only one trace can be active and sysent cannot be modified.
+        let r = unsafe { Syscall::register(k, None, |_: &mut Thread, _: &()| Ok(())) };
+
+        assert!(matches!(r, Err(SyscallError::Patch(_))));
+        assert!(matches!(
+            unsafe { TRACE.start(k, Filter::Pid(pid)) },
+            Err(TraceError::Busy)
+        ));
+
+        // Record. The nosys should still be detected while the table is wrapped.
+        assert_eq!(unsafe { call(k, td, 20, &()) }, Ok(pid as usize));
+        assert_eq!(
+            unsafe { call(k, td, 1, &[1usize, 2]) },
+            Err(MockKernel::ENOSYS)
+        );
+        assert_eq!(unsafe { call(k, td, 20, &()) }, Ok(pid as usize));
+        assert_eq!(unsafe { call(k, td, 20, &()) }, Ok(pid as usize));
+        assert_eq!(TRACE.dropped(), 1);
+
+        let r = TRACE.pop().unwrap();
+
+        assert_eq!(r.to_string(), format!("[{pid} MOCK00000] 20() = {pid:#x}"));
+        assert_eq!(TRACE.pop().unwrap().code, 20);
+        assert!(TRACE.pop().is_none());
+
+        // The kernel invoke the first entry without changing the number if it is not valid.
+        let entry = unsafe { &*k.get(MockKernel::SYSENT).as_mut_ptr() };
+        let mut args = [0usize; 8];
+        let prev = unsafe { (*td).syscall().unwrap() };
+
+        for code in [-1, 1024] {
+            unsafe { (*td).set_syscall(code) };
+
+            let error = unsafe { entry.call()(td, args.as_mut_ptr().cast()) };
+            let r = TRACE.pop().unwrap();
+
+            assert_eq!(error, ENOSYS);
+            assert_eq!(r.code, code);
+            assert_eq!(r.error, ENOSYS);
+        }
+
+        unsafe { (*td).set_syscall(prev) };
+
+        // Stop.
+        drop(tracing);
+
+        assert_eq!(unsafe { call(k, td, 20, &()) }, Ok(pid as usize));
+        assert!(TRACE.pop().is_none());
+
+        // Title ID that does not match.
+        let tracing = unsafe { TRACE.start(k, Filter::TitleId(b"CUSA00001")).unwrap() };
+
+        assert_eq!(unsafe { call(k, td, 20, &()) }, Ok(pid as usize));
+        assert!(TRACE.pop().is_none());
+
+        drop(tracing);
+    }
+
+    #[test]
+    fn malloc_type() {
+        static TYPE: MallocType<MockKernel> = MallocType::new(c"okf");
+
+        let a = Allocator::with_type(&TYPE);
+        let layout = Layout::from_size_align(16, 8).unwrap();
+        let mem = unsafe { a.alloc(layout) };
+        let ty = unsafe { &*TYPE.get() };
+
+        assert!(!mem.is_null());
+        assert_eq!(ty.inuse().load(Ordering::Relaxed), 1);
+
+        unsafe { a.dealloc(mem, layout) };
+
+        assert_eq!(ty.inuse().load(Ordering::Relaxed), 0);
+
+        // The type should be registered again after unregistered.
+        unsafe { TYPE.uninit() };
+
+        let mem = unsafe { a.alloc(layout) };
+
+        assert!(!mem.is_null());
+
+        unsafe { a.dealloc(mem, layout) };
+        unsafe { TYPE.uninit() };
+    }
+
+    #[test]
+    fn malloc_type_nowait() {
+        static TYPE: MallocType<MockKernel> = MallocType::new(c"okf");
+
+        // Registering the type may sleep so M_TEMP should be used instead.
+        let a = Allocator::with_type(&TYPE).nowait();
+        let layout = Layout::from_size_align(16, 8).unwrap();
+        let mem = unsafe { a.alloc(layout) };
+
+        assert!(!mem.is_null());
+        assert!(TYPE.try_get().is_none());
+
+        // The memory should be resized and freed with M_TEMP even if the type was registered.
+        let ty = unsafe { &*TYPE.get() };
+        let mem = unsafe { a.realloc(mem, layout, 32) };
+        let layout = Layout::from_size_align(32, 8).unwrap();
+
+        assert!(!mem.is_null());
+        assert_eq!(ty.inuse().load(Ordering::Relaxed), 0);
+
+        unsafe { a.dealloc(mem, layout) };
+
+        assert_eq!(ty.inuse().load(Ordering::Relaxed), 0);
+
+        // Use the type once it was registered.
+        let mem = unsafe { a.alloc(layout) };
+
+        assert_eq!(ty.inuse().load(Ordering::Relaxed), 1);
+
+        unsafe { a.dealloc(mem, layout) };
+        unsafe { TYPE.uninit() };
+    }
+
+    #[test]
+    #[cfg(feature = "allocator-api")]
+    fn allocator_api() {
+        let mut v = Vec::new_in(Allocator::<MockKernel>::new().nowait());
+
+        v.try_reserve(16).unwrap();
+        v.push(1u32);
+
+        assert_eq!(v, [1]);
+
+        // Zero-sized.
+        let mut v = Vec::new_in(Allocator::<MockKernel>::new());
+
+        v.push(());
+
+        assert_eq!(v.len(), 1);
+    }
+
+    #[test]
+    fn allocator_realloc() {
+        let k = MockKernel;
+        let a = Allocator::<MockKernel>::new().nowait();
+
+        for align in [8, 32] {
+            let layout = Layout::from_size_align(16, align).unwrap();
+            let mem = unsafe { a.alloc(layout) };
+
+            unsafe { mem.copy_from_nonoverlapping(b"0123456789ABCDEF".as_ptr(), 16) };
+
+            // Grow.
+            let mem = unsafe { a.realloc(mem, layout, 1024) };
+            let layout = Layout::from_size_align(1024, align).unwrap();
+
+            assert!(!mem.is_null());
+            assert_eq!(mem as usize % align, 0);
+            assert_eq!(
+                unsafe { std::slice::from_raw_parts(mem, 16) },
+                b"0123456789ABCDEF"
+            );
+
+            // Failed. Over-aligned memory is always moved by us.
+            let call = if align > 8 {
+                Call::Malloc
+            } else {
+                Call::Realloc
+            };
+
+            k.inject(call, Fault::Errno(NonZero::new(12).unwrap()));
+
+            assert!(unsafe { a.realloc(mem, layout, 4096) }.is_null());
+
+            unsafe { a.dealloc(mem, layout) };
+        }
+
+        assert_eq!(k.live_allocations(), 0);
+    }
+
+    #[test]
+    fn uma_zone() {
+        static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+        struct Item(u64);
+
+        impl Drop for Item {
+            fn drop(&mut self) {
+                DROPPED.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        // Zone without constructor.
+        let k = MockKernel;
+        let z = Zone::new(k, c"okf").unwrap();
+        let mut item = z.alloc(Item(1), MallocFlags::WAITOK).unwrap();
+
+        item.0 += 1;
+
+        assert_eq!(item.0, 2);
+
+        drop(item);
+
+        assert_eq!(DROPPED.load(Ordering::Relaxed), 1);
+
+        // Zone with constructor.
+        let z = Zone::with_callbacks(
+            k,
+            c"okf",
+            |v: &mut std::mem::MaybeUninit<Item>| {
+                v.write(Item(10));
+                Ok(())
+            },
+            |v: &mut Item| v.0 = 0,
+        );
+        let z = z.unwrap();
+        let item = z.construct(MallocFlags::NOWAIT).unwrap();
+
+        assert_eq!(item.0, 10);
+
+        drop(item);
+
+        assert_eq!(DROPPED.load(Ordering::Relaxed), 2);
+
+        // Allocation failure.
+        k.inject(Call::UmaZalloc, Fault::Errno(NonZero::new(12).unwrap()));
+
+        assert!(z.construct(MallocFlags::NOWAIT).is_none());
+        assert_eq!(DROPPED.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn kmem() {
+        let k = MockKernel;
+
+        // Pages.
+        let p = Pages::new(k, 100).unwrap();
+        let data = unsafe { std::slice::from_raw_parts(p.as_ptr(), p.len()) };
+
+        assert_eq!(p.len(), MockKernel::PAGE_SIZE);
+        assert_eq!(p.as_ptr() as usize % MockKernel::PAGE_SIZE, 0);
+        assert_eq!(p.phys(10), p.as_ptr() as u64 + 10);
+        assert!(data.iter().all(|&b| b == 0));
+
+        drop(p);
+
+        // Contiguous memory.
+        static TYPE: MallocType<MockKernel> = MallocType::new(c"okf");
+
+        let range = PhysRange {
+            alignment: 0x10000,
+            ..Default::default()
+        };
+        let m = Contiguous::new(k, &TYPE, 0x3000, &range, MallocFlags::WAITOK).unwrap();
+
+        assert_eq!(m.phys() % 0x10000, 0);
+
+        drop(m);
+
+        // Unsatisfiable range.
+        let range = PhysRange {
+            high: 0,
+            ..Default::default()
+        };
+
+        assert!(Contiguous::new(k, &TYPE, 0x1000, &range, MallocFlags::WAITOK).is_none());
+        assert_eq!(k.live_allocations(), 0);
+    }
+
+    #[test]
+    fn mutex() {
+        static COUNTER: lock::Mutex<MockKernel, usize> = lock::Mutex::new(c"okf", 0);
+
+        // Contend from multiple threads.
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                std::thread::spawn(|| {
+                    for _ in 0..1000 {
+                        *COUNTER.lock() += 1;
+                    }
+                })
+            })
+            .collect();
+
+        for t in threads {
+            t.join().unwrap();
+        }
+
+        assert_eq!(*COUNTER.lock(), 4000);
+
+        // Destroy.
+        let m = lock::Mutex::<MockKernel, _>::new(c"okf", vec![1]);
+
+        m.lock().push(2);
+
+        assert_eq!(m.into_inner(), [1, 2]);
+    }
+
+    #[test]
+    fn sx() {
+        let l = lock::SxLock::<MockKernel, _>::new(c"okf", vec![1]);
+
+        // Multiple readers.
+        let r1 = l.read();
+        let r2 = l.read();
+
+        assert_eq!(*r1, [1]);
+        assert_eq!(*r2, [1]);
+
+        drop(r1);
+        drop(r2);
+
+        // Writer must wait for the reader on the other thread.
+        std::thread::scope(|s| {
+            let r = l.read();
+            let w = s.spawn(|| l.write().push(2));
+
+            std::thread::sleep(Duration::from_millis(50));
+
+            assert_eq!(*r, [1]);
+
+            drop(r);
+            w.join().unwrap();
+        });
+
+        assert_eq!(l.into_inner(), [1, 2]);
+    }
+
+    #[test]
+    fn rwlock() {
+        let k = MockKernel;
+        let mut rw = super::lock::RwLock::new(c"okf".as_ptr());
+        let rw = &raw mut rw;
+
+        // Readers can share.
+        let r1 = unsafe { RwReadLock::new(k, rw) };
+        let r2 = unsafe { RwReadLock::new(k, rw) };
+
+        drop(r1);
+        drop(r2);
+
+        // Writer.
+        let w = unsafe { RwWriteLock::new(k, rw) };
+
+        assert!(unsafe { (*rw).state().is_locked() });
+
+        drop(w);
+
+        assert!(!unsafe { (*rw).state().is_locked() });
+    }
+
+    #[test]
+    fn condvar() {
+        static READY: lock::Mutex<MockKernel, bool> = lock::Mutex::new(c"okf", false);
+        static CV: lock::Condvar<MockKernel> = lock::Condvar::new(c"okf");
+
+        // Wait for the other thread.
+        let mut ready = READY.lock();
+        let t = std::thread::spawn(|| {
+            *READY.lock() = true;
+            CV.notify_all();
+        });
+
+        while !*ready {
+            CV.wait(&mut ready);
+        }
+
+        t.join().unwrap();
+
+        // Timeout.
+        assert_eq!(
+            CV.wait_timeout(&mut ready, 10),
+            Err(MockKernel::EWOULDBLOCK)
+        );
+
+        // Interrupted.
+        MockKernel.inject(Call::Sleep, Fault::Errno(MockKernel::EINTR));
+
+        assert_eq!(CV.wait_sig(&mut ready), Err(MockKernel::EINTR));
+        assert!(*ready);
+    }
+
+    #[test]
+    fn wakeup() {
+        static CHAN: lock::Mutex<MockKernel, usize> = lock::Mutex::new(c"okf", 0);
+
+        let k = MockKernel;
+        let ident = &raw const CHAN as usize;
+        let woken = CHAN.lock();
+        let t = std::thread::spawn(move || {
+            *CHAN.lock() += 1;
+
+            unsafe { k.wakeup_one(ident as *mut ()) };
+        });
+
+        // The value will be changed by the other thread while we are sleeping.
+        loop {
+            if *woken != 0 {
+                break;
+            }
+
+            let m = unsafe { (*CHAN.as_raw()).lock_mut() };
+            let r = unsafe { k.sleep(ident as *mut (), m, 0, c"okf".as_ptr(), 0) };
+
+            assert_eq!(r, 0);
+        }
+
+        drop(woken);
+        t.join().unwrap();
+    }
+
+    #[test]
+    fn spin_mutex() {
+        static COUNTER: lock::SpinMutex<MockKernel, usize> = lock::SpinMutex::new(c"okf", 0);
+
+        let k = MockKernel;
+        let mut v = COUNTER.lock();
+
+        *v += 1;
+
+        assert_eq!(state::with(|s| s.spinlocks), 1);
+
+        drop(v);
+
+        assert_eq!(*COUNTER.lock(), 1);
+        assert_eq!(state::with(|s| s.spinlocks), 0);
+
+        // Critical section.
+        let outer = CriticalGuard::new(k);
+        let inner = SpinlockGuard::new(k);
+
+        assert_eq!(state::with(|s| (s.critnest, s.spinlocks)), (1, 1));
+
+        drop(inner);
+        drop(outer);
+
+        assert_eq!(state::with(|s| (s.critnest, s.spinlocks)), (0, 0));
+    }
+
+    #[test]
+    #[cfg(feature = "lock-debug")]
+    fn lock_order() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static A: lock::Mutex<MockKernel, ()> = lock::Mutex::new(c"a", ());
+        static B: lock::SxLock<MockKernel, ()> = lock::SxLock::new(c"b", ());
+        static REVERSED: AtomicUsize = AtomicUsize::new(0);
+
+        lock::debug::set_reversal_handler(|r| {
+            if r.held == B.as_raw() as usize && r.acquiring == A.as_raw() as usize {
+                REVERSED.fetch_add(1, Ordering::Relaxed);
+            }
+        });
+
+        // Establish A -> B.
+        let k = MockKernel;
+        let a = A.lock();
+        let b = B.write();
+
+        drop(b);
+        drop(a);
+
+        assert_eq!(REVERSED.load(Ordering::Relaxed), 0);
+
+        // Reverse it.
+        let b = B.read();
+        let a = A.lock();
+
+        drop(a);
+        drop(b);
+
+        assert_eq!(REVERSED.load(Ordering::Relaxed), 1);
+
+        // Check statistics.
+        let stats = lock::debug::stats(k, A.as_raw().cast()).unwrap();
+
+        assert_eq!(stats.acquisitions, 2);
+        assert!(stats.max_hold <= stats.total_hold);
+    }
+
+    #[test]
+    #[cfg(feature = "lock-debug")]
+    fn condvar_stats() {
+        static LOCK: lock::Mutex<MockKernel, ()> = lock::Mutex::new(c"okf", ());
+        static CV: lock::Condvar<MockKernel> = lock::Condvar::new(c"okf");
+
+        // The lock is released while waiting.
+        let k = MockKernel;
+        let mut guard = LOCK.lock();
+
+        assert_eq!(
+            CV.wait_timeout(&mut guard, 10),
+            Err(MockKernel::EWOULDBLOCK)
+        );
+
+        drop(guard);
+
+        let stats = lock::debug::stats(k, LOCK.as_raw().cast()).unwrap();
+
+        assert_eq!(stats.acquisitions, 2);
+        assert_eq!(stats.contentions, 0);
+        assert!(stats.max_hold <= stats.total_hold);
+    }
+
+    #[test]
+    #[cfg(feature = "lock-debug")]
+    fn lock_contention() {
+        static LOCK: lock::Mutex<MockKernel, ()> = lock::Mutex::new(c"okf", ());
+
+        // Make the other thread wait for us.
+        let k = MockKernel;
+        let guard = LOCK.lock();
+        let t = std::thread::spawn(|| drop(LOCK.lock()));
+
+        std::thread::sleep(std::time::Duration::from_millis(100));
+
+        drop(guard);
+        t.join().unwrap();
+
+        let stats = lock::debug::stats(k, LOCK.as_raw().cast()).unwrap();
+
+        assert_eq!(stats.acquisitions, 2);
+        assert_eq!(stats.contentions, 1);
+    }
+}
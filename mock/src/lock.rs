@@ -0,0 +1,377 @@
+use crate::MockKernel;
+use core::ffi::c_char;
+use std::sync::atomic::{AtomicI32, AtomicUsize, Ordering};
+
+/// Implementation of [`okf::lock::LockObject`] for [`MockKernel`].
+#[repr(C)]
+pub struct LockObject {
+    name: *const c_char,
+    class: LockClass,
+}
+
+impl LockObject {
+    /// Releases the lock that own this object the same as `lc_unlock`.
+    ///
+    /// # Safety
+    /// `lo` cannot be null and the lock must be held by `td`.
+    pub(crate) unsafe fn unlock(lo: *mut Self, td: usize) -> Held {
+        // Lock object is always the first field of all lock types.
+        match unsafe { (*lo).class } {
+            LockClass::Mtx => {
+                unsafe { Mtx::unlock(lo.cast(), td) };
+                Held::Exclusive
+            }
+            LockClass::Sx | LockClass::Rw => {
+                let state = unsafe { Self::state(lo) };
+                let held = match state.is_exclusive() {
+                    true => Held::Exclusive,
+                    false => Held::Shared,
+                };
+
+                state.unlock(td);
+                held
+            }
+        }
+    }
+
+    /// Acquires the lock that own this object again the same as `lc_lock`.
+    ///
+    /// # Safety
+    /// `lo` cannot be null.
+    pub(crate) unsafe fn lock(lo: *mut Self, td: usize, held: Held) {
+        match unsafe { (*lo).class } {
+            LockClass::Mtx => unsafe { Mtx::lock(lo.cast(), td) },
+            LockClass::Sx | LockClass::Rw => {
+                let state = unsafe { Self::state(lo) };
+
+                match held {
+                    Held::Shared => state.slock(td),
+                    Held::Exclusive => state.xlock(td),
+                }
+            }
+        }
+    }
+
+    unsafe fn state<'a>(lo: *mut Self) -> &'a SharedState {
+        match unsafe { (*lo).class } {
+            LockClass::Mtx => unreachable!(),
+            LockClass::Sx => unsafe { (*lo.cast::<Sx>()).state() },
+            LockClass::Rw => unsafe { (*lo.cast::<RwLock>()).state() },
+        }
+    }
+}
+
+impl okf::lock::LockObject for LockObject {}
+
+/// Type of the lock that own [`LockObject`].
+#[derive(Clone, Copy)]
+enum LockClass {
+    Mtx,
+    Sx,
+    Rw,
+}
+
+/// How the lock was held before [`LockObject::unlock()`].
+#[derive(Clone, Copy)]
+pub(crate) enum Held {
+    Shared,
+    Exclusive,
+}
+
+/// Implementation of [`okf::lock::Mtx`] for [`MockKernel`].
+///
+/// Each mutex is a spin lock that records the address of the owning thread.
+#[repr(C)]
+pub struct Mtx {
+    lock: LockObject,
+    owner: AtomicUsize,
+    spin: bool,
+}
+
+impl Mtx {
+    pub(crate) const fn new(name: *const c_char) -> Self {
+        Self {
+            lock: LockObject {
+                name,
+                class: LockClass::Mtx,
+            },
+            owner: AtomicUsize::new(0),
+            spin: false,
+        }
+    }
+
+    pub(crate) const fn new_spin(name: *const c_char) -> Self {
+        Self {
+            spin: true,
+            ..Self::new(name)
+        }
+    }
+
+    /// # Safety
+    /// `m` cannot be null.
+    pub(crate) unsafe fn lock(m: *mut Self, td: usize) {
+        let owner = unsafe { &(*m).owner };
+
+        loop {
+            match owner.compare_exchange_weak(0, td, Ordering::Acquire, Ordering::Relaxed) {
+                Ok(_) => break,
+                Err(v) if v == td => panic!("recursed on non-recursive mutex"),
+                Err(_) => std::thread::yield_now(),
+            }
+        }
+    }
+
+    /// Returns `true` if `m` was locked.
+    ///
+    /// # Safety
+    /// `m` cannot be null.
+    pub(crate) unsafe fn try_lock(m: *mut Self, td: usize) -> bool {
+        let owner = unsafe { &(*m).owner };
+
+        owner
+            .compare_exchange(0, td, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+    }
+
+    /// # Safety
+    /// `m` cannot be null.
+    pub(crate) unsafe fn unlock(m: *mut Self, td: usize) {
+        let owner = unsafe { &(*m).owner };
+
+        if owner
+            .compare_exchange(td, 0, Ordering::Release, Ordering::Relaxed)
+            .is_err()
+        {
+            panic!("unlocking a mutex that is not owned by the current thread");
+        }
+    }
+
+    pub(crate) fn is_owned(&self) -> bool {
+        self.owner.load(Ordering::Relaxed) != 0
+    }
+
+    pub(crate) fn is_spin(&self) -> bool {
+        self.spin
+    }
+}
+
+impl okf::lock::Mtx<MockKernel> for Mtx {
+    fn lock_mut(&mut self) -> &mut LockObject {
+        &mut self.lock
+    }
+}
+
+/// Implementation of [`okf::lock::Sx`] for [`MockKernel`].
+#[repr(C)]
+pub struct Sx {
+    lock: LockObject,
+    state: SharedState,
+}
+
+impl Sx {
+    pub(crate) const fn new(name: *const c_char) -> Self {
+        Self {
+            lock: LockObject {
+                name,
+                class: LockClass::Sx,
+            },
+            state: SharedState::new(),
+        }
+    }
+
+    pub(crate) fn state(&self) -> &SharedState {
+        &self.state
+    }
+}
+
+impl okf::lock::Sx<MockKernel> for Sx {
+    fn lock_mut(&mut self) -> &mut LockObject {
+        &mut self.lock
+    }
+}
+
+/// Implementation of [`okf::lock::RwLock`] for [`MockKernel`].
+#[repr(C)]
+pub struct RwLock {
+    lock: LockObject,
+    state: SharedState,
+}
+
+impl RwLock {
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub(crate) const fn new(name: *const c_char) -> Self {
+        Self {
+            lock: LockObject {
+                name,
+                class: LockClass::Rw,
+            },
+            state: SharedState::new(),
+        }
+    }
+
+    pub(crate) fn state(&self) -> &SharedState {
+        &self.state
+    }
+}
+
+impl okf::lock::RwLock<MockKernel> for RwLock {
+    fn lock_mut(&mut self) -> &mut LockObject {
+        &mut self.lock
+    }
+}
+
+/// State of a shared/exclusive lock.
+///
+/// The value is zero when unlocked, the address of the owning thread with the lowest bit set
+/// when exclusively locked or twice the number of the readers when shared locked.
+pub(crate) struct SharedState(AtomicUsize);
+
+impl SharedState {
+    const EXCLUSIVE: usize = 1;
+
+    pub const fn new() -> Self {
+        Self(AtomicUsize::new(0))
+    }
+
+    pub fn slock(&self, td: usize) {
+        let mut cur = self.0.load(Ordering::Relaxed);
+
+        loop {
+            if cur == td | Self::EXCLUSIVE {
+                panic!("shared locking a lock that is exclusively locked by the current thread");
+            } else if cur & Self::EXCLUSIVE != 0 {
+                std::thread::yield_now();
+                cur = self.0.load(Ordering::Relaxed);
+                continue;
+            }
+
+            match self
+                .0
+                .compare_exchange_weak(cur, cur + 2, Ordering::Acquire, Ordering::Relaxed)
+            {
+                Ok(_) => break,
+                Err(v) => cur = v,
+            }
+        }
+    }
+
+    /// Returns `true` if the lock was acquired.
+    pub fn try_slock(&self, td: usize) -> bool {
+        let mut cur = self.0.load(Ordering::Relaxed);
+
+        loop {
+            if cur == td | Self::EXCLUSIVE {
+                panic!("shared locking a lock that is exclusively locked by the current thread");
+            } else if cur & Self::EXCLUSIVE != 0 {
+                return false;
+            }
+
+            match self
+                .0
+                .compare_exchange_weak(cur, cur + 2, Ordering::Acquire, Ordering::Relaxed)
+            {
+                Ok(_) => return true,
+                Err(v) => cur = v,
+            }
+        }
+    }
+
+    pub fn sunlock(&self) {
+        let mut cur = self.0.load(Ordering::Relaxed);
+
+        loop {
+            if cur == 0 || cur & Self::EXCLUSIVE != 0 {
+                panic!("shared unlocking a lock that is not shared locked");
+            }
+
+            match self
+                .0
+                .compare_exchange_weak(cur, cur - 2, Ordering::Release, Ordering::Relaxed)
+            {
+                Ok(_) => break,
+                Err(v) => cur = v,
+            }
+        }
+    }
+
+    pub fn xlock(&self, td: usize) {
+        let new = td | Self::EXCLUSIVE;
+
+        loop {
+            match self
+                .0
+                .compare_exchange_weak(0, new, Ordering::Acquire, Ordering::Relaxed)
+            {
+                Ok(_) => break,
+                Err(v) if v == new => panic!("recursed on non-recursive lock"),
+                Err(_) => std::thread::yield_now(),
+            }
+        }
+    }
+
+    /// Returns `true` if the lock was acquired.
+    pub fn try_xlock(&self, td: usize) -> bool {
+        self.0
+            .compare_exchange(
+                0,
+                td | Self::EXCLUSIVE,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            )
+            .is_ok()
+    }
+
+    pub fn xunlock(&self, td: usize) {
+        if self
+            .0
+            .compare_exchange(
+                td | Self::EXCLUSIVE,
+                0,
+                Ordering::Release,
+                Ordering::Relaxed,
+            )
+            .is_err()
+        {
+            panic!("exclusive unlocking a lock that is not owned by the current thread");
+        }
+    }
+
+    /// Releases either a shared or an exclusive lock.
+    pub fn unlock(&self, td: usize) {
+        if self.is_exclusive() {
+            self.xunlock(td);
+        } else {
+            self.sunlock();
+        }
+    }
+
+    pub fn is_exclusive(&self) -> bool {
+        self.0.load(Ordering::Relaxed) & Self::EXCLUSIVE != 0
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.0.load(Ordering::Relaxed) != 0
+    }
+}
+
+/// Implementation of [`okf::lock::Cv`] for [`MockKernel`].
+#[repr(C)]
+pub struct Cv {
+    description: *const c_char,
+    waiters: AtomicI32,
+}
+
+impl Cv {
+    pub(crate) const fn new(description: *const c_char) -> Self {
+        Self {
+            description,
+            waiters: AtomicI32::new(0),
+        }
+    }
+
+    pub(crate) fn waiters(&self) -> &AtomicI32 {
+        &self.waiters
+    }
+}
+
+impl okf::lock::Cv for Cv {}
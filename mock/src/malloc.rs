@@ -0,0 +1,69 @@
+use core::ffi::{c_char, c_ulong};
+use okf::malloc::M_MAGIC;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/// Implementation of [`okf::malloc::Malloc`] for [`crate::MockKernel`].
+#[repr(C)]
+pub struct Malloc {
+    magic: c_ulong,
+    shortdesc: *const c_char,
+    registered: AtomicBool,
+    inuse: AtomicUsize,
+}
+
+impl Malloc {
+    /// Returns a type that already registered.
+    pub(crate) const fn registered(desc: *const c_char) -> Self {
+        Self {
+            magic: M_MAGIC,
+            shortdesc: desc,
+            registered: AtomicBool::new(true),
+            inuse: AtomicUsize::new(0),
+        }
+    }
+
+    /// # Panics
+    /// If the type is not registered.
+    pub(crate) fn check(&self) {
+        self.check_magic();
+
+        assert!(
+            self.registered.load(Ordering::Relaxed),
+            "malloc type is not registered"
+        );
+    }
+
+    pub(crate) fn set_registered(&self, v: bool) {
+        self.check_magic();
+
+        if self.registered.swap(v, Ordering::Relaxed) == v {
+            panic!("malloc type is already in the requested state");
+        }
+
+        if !v && self.inuse.load(Ordering::Relaxed) != 0 {
+            panic!("malloc_uninit with memory still in use");
+        }
+    }
+
+    /// Returns number of allocations that does not freed yet.
+    pub(crate) fn inuse(&self) -> &AtomicUsize {
+        &self.inuse
+    }
+
+    fn check_magic(&self) {
+        assert_eq!(self.magic, M_MAGIC, "bad malloc type magic");
+    }
+}
+
+impl okf::malloc::Malloc for Malloc {
+    unsafe fn init(this: *mut Self, desc: *const c_char) {
+        unsafe {
+            this.write(Self {
+                magic: M_MAGIC,
+                shortdesc: desc,
+                registered: AtomicBool::new(false),
+                inuse: AtomicUsize::new(0),
+            })
+        };
+    }
+}
@@ -0,0 +1,89 @@
+use crate::MockKernel;
+use crate::lock::Mtx;
+use crate::vnode::Vnode;
+use core::ffi::{c_char, c_int};
+use core::num::NonZero;
+use okf::queue::TailQueueEntry;
+use std::sync::atomic::AtomicUsize;
+
+/// Implementation of [`okf::mount::Mount`] for [`MockKernel`].
+///
+/// There is only one mount, which is the root of the VFS that belong to the calling thread.
+#[repr(C)]
+pub struct Mount {
+    pub(crate) mtx: Mtx,
+    pub(crate) entry: TailQueueEntry<Self>,
+    pub(crate) ops: *const FsOps,
+    pub(crate) fs: *mut Filesystem,
+    pub(crate) flags: u64,
+    pub(crate) stats: FsStats,
+    pub(crate) busy: AtomicUsize,
+}
+
+impl okf::mount::Mount<MockKernel> for Mount {
+    fn mtx(&self) -> *mut Mtx {
+        &self.mtx as *const Mtx as *mut Mtx
+    }
+
+    unsafe fn entry(&self) -> &TailQueueEntry<Self> {
+        &self.entry
+    }
+
+    unsafe fn entry_mut(&mut self) -> &mut TailQueueEntry<Self> {
+        &mut self.entry
+    }
+
+    fn fs(&self) -> *mut Filesystem {
+        self.fs
+    }
+
+    fn ops(&self) -> &'static FsOps {
+        unsafe { &*self.ops }
+    }
+
+    unsafe fn flags(&self) -> u64 {
+        self.flags
+    }
+
+    fn stats(&self) -> *mut FsStats {
+        &self.stats as *const FsStats as *mut FsStats
+    }
+}
+
+/// Implementation of [`okf::mount::Filesystem`] for [`MockKernel`].
+#[repr(C)]
+pub struct Filesystem {
+    pub(crate) name: [c_char; 16],
+}
+
+impl okf::mount::Filesystem for Filesystem {
+    fn name(&self) -> *const c_char {
+        self.name.as_ptr()
+    }
+}
+
+/// Implementation of [`okf::mount::FsOps`] for [`MockKernel`].
+#[repr(C)]
+pub struct FsOps {}
+
+impl okf::mount::FsOps<MockKernel> for FsOps {
+    unsafe fn root(&self, _: *mut Mount, flags: c_int) -> Result<*mut Vnode, NonZero<c_int>> {
+        let vp = crate::state::with(|s| s.vref(0));
+
+        unsafe { crate::vlock(vp, flags) };
+
+        Ok(vp)
+    }
+}
+
+/// Implementation of [`okf::mount::FsStats`] for [`MockKernel`].
+#[repr(C)]
+pub struct FsStats {
+    pub(crate) mounted_from: [c_char; 88],
+}
+
+impl okf::mount::FsStats for FsStats {
+    fn mounted_from(&self) -> *const c_char {
+        self.mounted_from.as_ptr()
+    }
+}
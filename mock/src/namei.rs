@@ -0,0 +1,36 @@
+use crate::thread::Thread;
+use crate::ucred::Ucred;
+use core::ffi::{c_char, c_int};
+use okf::Kernel;
+
+/// Implementation of [`okf::namei::ComponentName`] for [`crate::MockKernel`].
+#[repr(C)]
+pub struct ComponentName {
+    op: u64,
+    td: *mut Thread,
+    cred: *mut Ucred,
+    pub(crate) lk: c_int,
+    pub(crate) name: *mut c_char,
+    pub(crate) len: isize,
+}
+
+impl okf::namei::ComponentName<crate::MockKernel> for ComponentName {
+    unsafe fn new(
+        k: crate::MockKernel,
+        op: u64,
+        lk: c_int,
+        buf: *mut c_char,
+        td: *mut Thread,
+    ) -> Self {
+        use okf::thread::Thread;
+
+        Self {
+            op,
+            td,
+            cred: unsafe { (*td).cred() },
+            lk,
+            name: buf,
+            len: unsafe { k.strlen(buf) as _ },
+        }
+    }
+}
@@ -0,0 +1,18 @@
+use crate::MockKernel;
+use crate::thread::Thread;
+
+/// Implementation of [`okf::pcpu::Pcpu`] for [`MockKernel`].
+///
+/// Each host thread is treated as a kernel thread running on CPU 0.
+#[repr(C)]
+pub struct Pcpu {}
+
+impl okf::pcpu::Pcpu<MockKernel> for Pcpu {
+    fn curthread() -> *mut Thread {
+        crate::state::with(|s| s.thread())
+    }
+
+    fn cpuid() -> u32 {
+        0
+    }
+}
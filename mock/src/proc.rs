@@ -0,0 +1,18 @@
+use core::ffi::c_int;
+
+/// Implementation of [`okf::proc::Proc`] for [`crate::MockKernel`].
+#[repr(C)]
+pub struct Proc {
+    pub(crate) pid: c_int,
+    pub(crate) title_id: [u8; 10],
+}
+
+impl okf::proc::Proc for Proc {
+    fn pid(&self) -> Option<c_int> {
+        Some(self.pid)
+    }
+
+    fn title_id(&self) -> Option<&[u8; 10]> {
+        Some(&self.title_id)
+    }
+}
@@ -0,0 +1,70 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// Wait channel and ticket of all sleeping threads in the order they went to sleep.
+static QUEUE: Mutex<Vec<(usize, u64)>> = Mutex::new(Vec::new());
+static WOKEN: Condvar = Condvar::new();
+static NEXT: AtomicU64 = AtomicU64::new(0);
+
+/// A thread that is going to sleep on a wait channel.
+///
+/// This must be created before releasing the interlock so a wakeup in between will not be lost.
+pub struct Sleeper(u64);
+
+impl Sleeper {
+    pub fn new(chan: usize) -> Self {
+        let ticket = NEXT.fetch_add(1, Ordering::Relaxed);
+
+        QUEUE.lock().unwrap().push((chan, ticket));
+
+        Self(ticket)
+    }
+
+    /// Returns [`false`] if `timeout` was elapsed before the thread was woken up.
+    pub fn sleep(self, timeout: Option<Duration>) -> bool {
+        let deadline = timeout.map(|v| Instant::now() + v);
+        let mut q = QUEUE.lock().unwrap();
+
+        loop {
+            let i = match q.iter().position(|&(_, t)| t == self.0) {
+                Some(v) => v,
+                None => return true,
+            };
+
+            q = match deadline {
+                Some(d) => match d.checked_duration_since(Instant::now()) {
+                    Some(v) if !v.is_zero() => WOKEN.wait_timeout(q, v).unwrap().0,
+                    _ => {
+                        q.remove(i);
+                        return false;
+                    }
+                },
+                None => WOKEN.wait(q).unwrap(),
+            };
+        }
+    }
+}
+
+/// Wakes up the threads that sleeping on `chan`. Returns number of the threads that was woken up.
+pub fn wakeup(chan: usize, all: bool) -> usize {
+    let mut q = QUEUE.lock().unwrap();
+    let mut n = 0;
+
+    q.retain(|&(c, _)| {
+        if c != chan || (n != 0 && !all) {
+            return true;
+        }
+
+        n += 1;
+        false
+    });
+
+    drop(q);
+
+    if n != 0 {
+        WOKEN.notify_all();
+    }
+
+    n
+}
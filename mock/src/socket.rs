@@ -0,0 +1,39 @@
+use core::ffi::{c_int, c_short, c_ushort};
+use okf::socket::SockAddrIn;
+use std::sync::atomic::{AtomicU16, Ordering};
+
+/// Implementation of [`okf::socket::Socket`] for [`crate::MockKernel`].
+#[repr(C)]
+pub struct Socket {
+    timeout: c_short,
+    error: AtomicU16,
+    pub(crate) ty: c_int,
+    pub(crate) addr: Option<SockAddrIn>,
+    pub(crate) backlog: Option<c_int>,
+}
+
+impl Socket {
+    pub(crate) fn new(ty: c_int) -> Self {
+        Self {
+            timeout: 0,
+            error: AtomicU16::new(0),
+            ty,
+            addr: None,
+            backlog: None,
+        }
+    }
+}
+
+impl okf::socket::Socket for Socket {
+    fn error(&self) -> c_ushort {
+        self.error.load(Ordering::Relaxed)
+    }
+
+    fn set_error(&self, v: c_ushort) {
+        self.error.store(v, Ordering::Relaxed);
+    }
+
+    fn timeout(&self) -> *mut c_short {
+        &self.timeout as *const c_short as _
+    }
+}
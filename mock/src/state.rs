@@ -0,0 +1,235 @@
+use crate::errno::{EEXIST, ENOENT, ENOTDIR};
+use crate::fault::{Call, Fault};
+use crate::file::File;
+use crate::proc::Proc;
+use crate::thread::Thread;
+use crate::ucred::Ucred;
+use crate::vnode::Vnode;
+use core::ffi::c_int;
+use okf::Kernel;
+use okf::file::File as _;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicI32, Ordering};
+
+thread_local! {
+    static STATE: RefCell<Option<State>> = const { RefCell::new(None) };
+}
+
+/// Invoke `f` with the state of the calling thread.
+///
+/// `f` must not call into [`crate::MockKernel`] otherwise it will panic.
+pub fn with<R>(f: impl FnOnce(&mut State) -> R) -> R {
+    STATE.with_borrow_mut(|s| f(s.get_or_insert_with(State::new)))
+}
+
+/// State of the mocked kernel for a single host thread.
+///
+/// Each host thread behave like a process with its own VFS and FD table.
+pub struct State {
+    thread: Box<Thread>,
+    nodes: Vec<Node>,
+    files: Vec<Option<*mut File>>,
+    faults: HashMap<Call, VecDeque<Fault>>,
+    pub ports: HashSet<u16>,
+    pub sockets: usize,
+    pub allocs: usize,
+    pub critnest: usize,
+    pub spinlocks: usize,
+    _cred: Box<Ucred>,
+    _proc: Box<Proc>,
+}
+
+impl State {
+    fn new() -> Self {
+        static PID: AtomicI32 = AtomicI32::new(1);
+
+        let mut proc = Box::new(Proc {
+            pid: PID.fetch_add(1, Ordering::Relaxed),
+            title_id: *b"MOCK00000\0",
+        });
+        let mut cred = Box::new(Ucred { uid: 0 });
+        let thread = Box::new(Thread::new(&mut *proc, &mut *cred));
+        let root = Node {
+            kind: NodeKind::Dir(BTreeMap::new()),
+            parent: 0,
+            vnode: Box::new(Vnode::new(crate::MockKernel::VDIR, 0)),
+        };
+
+        Self {
+            thread,
+            nodes: vec![root],
+            files: Vec::new(),
+            faults: HashMap::new(),
+            ports: HashSet::new(),
+            sockets: 0,
+            allocs: 0,
+            critnest: 0,
+            spinlocks: 0,
+            _cred: cred,
+            _proc: proc,
+        }
+    }
+
+    pub fn thread(&mut self) -> *mut Thread {
+        &mut *self.thread
+    }
+
+    pub fn inject(&mut self, call: Call, fault: Fault) {
+        self.faults.entry(call).or_default().push_back(fault);
+    }
+
+    pub fn fault(&mut self, call: Call) -> Option<Fault> {
+        self.faults.get_mut(&call).and_then(|q| q.pop_front())
+    }
+
+    pub fn node(&self, i: usize) -> &Node {
+        &self.nodes[i]
+    }
+
+    pub fn node_mut(&mut self, i: usize) -> &mut Node {
+        &mut self.nodes[i]
+    }
+
+    /// Returns a pointer to the vnode of `node` with its reference count increased.
+    pub fn vref(&mut self, node: usize) -> *mut Vnode {
+        let vp = &mut self.nodes[node].vnode;
+
+        vp.refs += 1;
+
+        &mut **vp
+    }
+
+    /// Returns index of the node at `path`.
+    pub fn lookup(&self, path: &str) -> Result<usize, c_int> {
+        let mut cur = 0;
+
+        for name in path.split('/').filter(|v| !v.is_empty()) {
+            cur = match &self.nodes[cur].kind {
+                NodeKind::Dir(c) => *c.get(name).ok_or(ENOENT)?,
+                NodeKind::File(_) => return Err(ENOTDIR),
+            };
+        }
+
+        Ok(cur)
+    }
+
+    /// Create a new node at `path`. The parent of `path` must already exists.
+    pub fn create(&mut self, path: &str, dir: bool) -> Result<usize, c_int> {
+        let (parent, name) = match path.trim_end_matches('/').rsplit_once('/') {
+            Some(v) => v,
+            None => ("", path),
+        };
+
+        if name.is_empty() {
+            return Err(EEXIST);
+        }
+
+        // Get parent.
+        let parent = self.lookup(parent)?;
+        let index = self.nodes.len();
+        let children = match &mut self.nodes[parent].kind {
+            NodeKind::Dir(v) => v,
+            NodeKind::File(_) => return Err(ENOTDIR),
+        };
+
+        if children.contains_key(name) {
+            return Err(EEXIST);
+        }
+
+        children.insert(name.into(), index);
+
+        // Create node.
+        let (kind, ty) = match dir {
+            true => (NodeKind::Dir(BTreeMap::new()), crate::MockKernel::VDIR),
+            false => (NodeKind::File(Vec::new()), crate::MockKernel::VREG),
+        };
+
+        self.nodes.push(Node {
+            kind,
+            parent,
+            vnode: Box::new(Vnode::new(ty, index)),
+        });
+
+        Ok(index)
+    }
+
+    /// Same as [`State::create()`] except all missing parents will be created and it is not an
+    /// error if `path` already exists.
+    pub fn create_all(&mut self, path: &str, dir: bool) -> Result<usize, c_int> {
+        let mut cur = String::new();
+        let mut names = path.split('/').filter(|v| !v.is_empty()).peekable();
+        let mut node = 0;
+
+        while let Some(name) = names.next() {
+            let last = names.peek().is_none();
+
+            cur.push('/');
+            cur.push_str(name);
+
+            node = match self.create(&cur, dir || !last) {
+                Ok(v) => v,
+                Err(EEXIST) => self.lookup(&cur)?,
+                Err(e) => return Err(e),
+            };
+        }
+
+        Ok(node)
+    }
+
+    pub fn file(&self, fd: c_int) -> Option<*mut File> {
+        let fd: usize = fd.try_into().ok()?;
+
+        self.files.get(fd).copied().flatten()
+    }
+
+    /// Install `file` on the lowest available FD.
+    pub fn install(&mut self, file: *mut File) -> c_int {
+        let fd = match self.files.iter().position(|f| f.is_none()) {
+            Some(i) => {
+                self.files[i] = Some(file);
+                i
+            }
+            None => {
+                self.files.push(Some(file));
+                self.files.len() - 1
+            }
+        };
+
+        fd.try_into().unwrap()
+    }
+
+    pub fn remove(&mut self, fd: c_int) -> Option<*mut File> {
+        let fd: usize = fd.try_into().ok()?;
+
+        self.files.get_mut(fd)?.take()
+    }
+
+    pub fn open_files(&self) -> usize {
+        self.files.iter().filter(|f| f.is_some()).count()
+    }
+}
+
+impl Drop for State {
+    fn drop(&mut self) {
+        // Release the references that still held by the FD table.
+        for fp in self.files.drain(..).flatten() {
+            if unsafe { (*fp).refcnt().fetch_sub(1, Ordering::Relaxed) } == 1 {
+                drop(unsafe { Box::from_raw(fp) });
+            }
+        }
+    }
+}
+
+/// Node in the VFS.
+pub struct Node {
+    pub kind: NodeKind,
+    pub parent: usize,
+    vnode: Box<Vnode>,
+}
+
+/// Type of [`Node`].
+pub enum NodeKind {
+    File(Vec<u8>),
+    Dir(BTreeMap<String, usize>),
+}
@@ -0,0 +1,62 @@
+use crate::MockKernel;
+use crate::errno::ENOSYS;
+use crate::thread::Thread;
+use core::ffi::c_int;
+use core::mem::transmute;
+use okf::proc::Proc as _;
+use okf::syscall::SyscallFn;
+use okf::thread::Thread as _;
+use std::sync::atomic::{AtomicI32, AtomicPtr, Ordering};
+
+/// Implementation of [`okf::syscall::Sysent`] for [`MockKernel`].
+#[repr(C)]
+pub struct Sysent {
+    narg: AtomicI32,
+    call: AtomicPtr<()>,
+}
+
+impl Sysent {
+    const fn new(narg: c_int, call: *mut ()) -> Self {
+        Self {
+            narg: AtomicI32::new(narg),
+            call: AtomicPtr::new(call),
+        }
+    }
+}
+
+impl okf::syscall::Sysent<MockKernel> for Sysent {
+    fn narg(&self) -> c_int {
+        self.narg.load(Ordering::Relaxed)
+    }
+
+    unsafe fn set_narg(&self, v: c_int) {
+        self.narg.store(v, Ordering::Release);
+    }
+
+    fn call(&self) -> SyscallFn<MockKernel> {
+        unsafe { transmute(self.call.load(Ordering::Acquire)) }
+    }
+
+    unsafe fn set_call(&self, v: SyscallFn<MockKernel>) {
+        self.call.store(v as *mut (), Ordering::Release);
+    }
+}
+
+/// Returns `sysent` table with `getpid` as the only implemented system call.
+pub(crate) const fn sysent() -> [Sysent; 32] {
+    let mut t = [const { Sysent::new(0, nosys as *mut ()) }; 32];
+
+    t[20] = Sysent::new(0, getpid as *mut ());
+    t
+}
+
+unsafe extern "C" fn getpid(td: *mut Thread, _: *mut u8) -> c_int {
+    let pid = unsafe { (*(*td).proc()).pid().unwrap() };
+
+    unsafe { (*td).set_ret(0, pid as usize) };
+    0
+}
+
+unsafe extern "C" fn nosys(_: *mut Thread, _: *mut u8) -> c_int {
+    ENOSYS
+}
@@ -0,0 +1,50 @@
+use crate::MockKernel;
+use crate::proc::Proc;
+use crate::ucred::Ucred;
+use core::ffi::c_int;
+
+/// Implementation of [`okf::thread::Thread`] for [`MockKernel`].
+#[repr(C)]
+pub struct Thread {
+    proc: *mut Proc,
+    cred: *mut Ucred,
+    ret: [usize; 2], // td_retval
+    code: c_int,     // td_sa.code
+}
+
+impl Thread {
+    pub(crate) fn new(proc: *mut Proc, cred: *mut Ucred) -> Self {
+        Self {
+            proc,
+            cred,
+            ret: [0; 2],
+            code: 0,
+        }
+    }
+}
+
+impl okf::thread::Thread<MockKernel> for Thread {
+    fn proc(&self) -> *mut Proc {
+        self.proc
+    }
+
+    fn cred(&self) -> *mut Ucred {
+        self.cred
+    }
+
+    fn ret(&self, i: usize) -> usize {
+        self.ret[i]
+    }
+
+    fn set_ret(&mut self, i: usize, v: usize) {
+        self.ret[i] = v;
+    }
+
+    fn syscall(&self) -> Option<c_int> {
+        Some(self.code)
+    }
+
+    fn set_syscall(&mut self, v: c_int) {
+        self.code = v;
+    }
+}
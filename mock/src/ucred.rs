@@ -0,0 +1,7 @@
+/// Implementation of [`okf::ucred::Ucred`] for [`crate::MockKernel`].
+#[repr(C)]
+pub struct Ucred {
+    pub(crate) uid: u32,
+}
+
+impl okf::ucred::Ucred for Ucred {}
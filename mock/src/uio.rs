@@ -0,0 +1,62 @@
+use crate::MockKernel;
+use crate::thread::Thread;
+use core::ffi::c_int;
+use okf::uio::{IoVec, UioRw, UioSeg};
+
+/// Implementation of [`okf::uio::Uio`] for [`MockKernel`].
+#[repr(C)]
+pub struct Uio {
+    pub(crate) iov: *mut IoVec,
+    pub(crate) len: c_int,
+    pub(crate) off: isize,
+    pub(crate) res: isize,
+    pub(crate) seg: UioSeg,
+    pub(crate) op: UioRw,
+    td: *mut Thread,
+}
+
+impl okf::uio::Uio<MockKernel> for Uio {
+    unsafe fn write(iov: *mut IoVec, td: *mut Thread) -> Option<Self> {
+        let res = unsafe { (*iov).len };
+
+        if res > Self::io_max() {
+            return None;
+        }
+
+        Some(Self {
+            iov,
+            len: 1,
+            off: -1,
+            res: res.try_into().unwrap(),
+            seg: UioSeg::Kernel,
+            op: UioRw::Write,
+            td,
+        })
+    }
+
+    unsafe fn read(iov: *mut IoVec, off: usize, td: *mut Thread) -> Option<Self> {
+        let res = unsafe { (*iov).len };
+
+        if res > Self::io_max() {
+            return None;
+        }
+
+        Some(Self {
+            iov,
+            len: 1,
+            off: off.try_into().unwrap(),
+            res: res.try_into().unwrap(),
+            seg: UioSeg::Kernel,
+            op: UioRw::Read,
+            td,
+        })
+    }
+
+    fn offset(&self) -> isize {
+        self.off
+    }
+
+    fn remaining(&self) -> isize {
+        self.res
+    }
+}
@@ -0,0 +1,15 @@
+use core::ffi::c_char;
+use okf::uma::{UmaCtor, UmaDtor};
+use std::alloc::Layout;
+use std::sync::atomic::AtomicUsize;
+
+/// Implementation of [`okf::uma::UmaZone`] for [`crate::MockKernel`].
+pub struct UmaZone {
+    pub(crate) name: *const c_char,
+    pub(crate) layout: Layout,
+    pub(crate) ctor: Option<UmaCtor>,
+    pub(crate) dtor: Option<UmaDtor>,
+    pub(crate) items: AtomicUsize,
+}
+
+impl okf::uma::UmaZone for UmaZone {}
@@ -0,0 +1,199 @@
+use crate::MockKernel;
+use crate::lock::SharedState;
+use crate::namei::ComponentName;
+use crate::ucred::Ucred;
+use crate::uio::Uio;
+use core::ffi::{c_char, c_int};
+use okf::Kernel;
+use okf::vnode::{LockedVnode, VopArgs};
+
+/// Implementation of [`okf::vnode::Vnode`] for [`MockKernel`].
+#[repr(C)]
+pub struct Vnode {
+    ty: c_int,
+    ops: *mut VopVector,
+    pub(crate) node: usize,
+    pub(crate) refs: usize,
+    pub(crate) lock: SharedState,
+}
+
+impl Vnode {
+    pub(crate) fn new(ty: c_int, node: usize) -> Self {
+        Self {
+            ty,
+            ops: MockKernel.get(crate::VOP_VECTOR).as_mut_ptr(),
+            node,
+            refs: 0,
+            lock: SharedState::new(),
+        }
+    }
+}
+
+impl okf::vnode::Vnode<MockKernel> for Vnode {
+    fn ty(&self) -> c_int {
+        self.ty
+    }
+
+    fn ops(&self) -> *mut VopVector {
+        self.ops
+    }
+}
+
+/// Implementation of [`okf::vnode::VopVector`] for [`MockKernel`].
+#[repr(C)]
+pub struct VopVector {}
+
+impl okf::vnode::VopVector for VopVector {}
+
+/// Implementation of [`okf::vnode::VnodeOp`] for [`MockKernel`].
+#[repr(C)]
+pub struct VnodeOp {
+    name: *const c_char,
+}
+
+impl VnodeOp {
+    pub(crate) const fn new(name: *const c_char) -> Self {
+        Self { name }
+    }
+}
+
+impl okf::vnode::VnodeOp for VnodeOp {}
+
+/// Implementation of [`okf::vnode::VopLock`] for [`MockKernel`].
+#[repr(C)]
+pub struct VopLock {
+    pub(crate) desc: *mut VnodeOp,
+    pub(crate) vp: *mut Vnode,
+    pub(crate) flags: c_int,
+    pub(crate) file: *const c_char,
+    pub(crate) line: c_int,
+}
+
+impl okf::vnode::VopLock<MockKernel> for VopLock {
+    unsafe fn new(
+        k: MockKernel,
+        vp: *mut Vnode,
+        flags: c_int,
+        file: *const c_char,
+        line: c_int,
+    ) -> Self {
+        Self {
+            desc: k.get(MockKernel::VOP_LOCK1).as_mut_ptr(),
+            vp,
+            flags,
+            file,
+            line,
+        }
+    }
+}
+
+/// Implementation of [`okf::vnode::VopUnlock`] for [`MockKernel`].
+#[repr(C)]
+pub struct VopUnlock {
+    pub(crate) desc: *mut VnodeOp,
+    pub(crate) vp: *mut Vnode,
+    pub(crate) flags: c_int,
+}
+
+impl okf::vnode::VopUnlock<MockKernel> for VopUnlock {
+    unsafe fn new(k: MockKernel, vp: *mut Vnode, flags: c_int) -> Self {
+        Self {
+            desc: k.get(MockKernel::VOP_UNLOCK).as_mut_ptr(),
+            vp,
+            flags,
+        }
+    }
+}
+
+/// Implementation of [`okf::vnode::VopRead`] for [`MockKernel`].
+#[repr(C)]
+pub struct VopRead {
+    pub(crate) desc: *mut VnodeOp,
+    pub(crate) vp: *mut Vnode,
+    pub(crate) uio: *mut Uio,
+    pub(crate) flags: c_int,
+    pub(crate) cred: *mut Ucred,
+}
+
+impl okf::vnode::VopRead<MockKernel> for VopRead {
+    unsafe fn new<'a>(
+        k: MockKernel,
+        vp: &'a LockedVnode<MockKernel>,
+        uio: *mut Uio,
+        flags: c_int,
+        cred: *mut Ucred,
+    ) -> VopArgs<'a, MockKernel, Self> {
+        let args = Self {
+            desc: k.get(MockKernel::VOP_READ).as_mut_ptr(),
+            vp: vp.as_ptr(),
+            uio,
+            flags,
+            cred,
+        };
+
+        unsafe { VopArgs::new(vp, args) }
+    }
+}
+
+/// Implementation of [`okf::vnode::VopReadDir`] for [`MockKernel`].
+#[repr(C)]
+pub struct VopReadDir {
+    pub(crate) desc: *mut VnodeOp,
+    pub(crate) vp: *mut Vnode,
+    pub(crate) uio: *mut Uio,
+    pub(crate) cred: *mut Ucred,
+    pub(crate) eof: *mut c_int,
+    pub(crate) ncookies: *mut c_int,
+    pub(crate) cookies: *mut *mut u64,
+}
+
+impl okf::vnode::VopReadDir<MockKernel> for VopReadDir {
+    unsafe fn new<'a>(
+        k: MockKernel,
+        vp: &'a LockedVnode<MockKernel>,
+        uio: *mut Uio,
+        cred: *mut Ucred,
+        eof: *mut c_int,
+        ncookies: *mut c_int,
+        cookies: *mut *mut u64,
+    ) -> VopArgs<'a, MockKernel, Self> {
+        let args = Self {
+            desc: k.get(MockKernel::VOP_READDIR).as_mut_ptr(),
+            vp: vp.as_ptr(),
+            uio,
+            cred,
+            eof,
+            ncookies,
+            cookies,
+        };
+
+        unsafe { VopArgs::new(vp, args) }
+    }
+}
+
+/// Implementation of [`okf::vnode::VopLookup`] for [`MockKernel`].
+#[repr(C)]
+pub struct VopLookup {
+    pub(crate) desc: *mut VnodeOp,
+    pub(crate) vp: *mut Vnode,
+    pub(crate) out: *mut *mut Vnode,
+    pub(crate) cn: *mut ComponentName,
+}
+
+impl okf::vnode::VopLookup<MockKernel> for VopLookup {
+    unsafe fn new<'a>(
+        k: MockKernel,
+        vp: &'a LockedVnode<MockKernel>,
+        out: *mut *mut Vnode,
+        cn: *mut ComponentName,
+    ) -> VopArgs<'a, MockKernel, Self> {
+        let args = Self {
+            desc: k.get(MockKernel::VOP_LOOKUP).as_mut_ptr(),
+            vp: vp.as_ptr(),
+            out,
+            cn,
+        };
+
+        unsafe { VopArgs::new(vp, args) }
+    }
+}
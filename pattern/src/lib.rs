@@ -0,0 +1,70 @@
+use std::fmt::{Display, Formatter};
+
+/// Parses a byte pattern (e.g. `48 8D ?? ?? ?? ?? E8`).
+///
+/// Each byte must be two hexadecimal digits or `?`/`??` for a wildcard. This is the syntax of
+/// `okf::signature` and the patterns in the offset database.
+pub fn parse(pattern: &str) -> Result<Vec<Option<u8>>, PatternError> {
+    let mut bytes = Vec::new();
+
+    for b in pattern.split_ascii_whitespace() {
+        let b = match b {
+            "?" | "??" => None,
+            v if v.len() == 2 => match u8::from_str_radix(v, 16) {
+                Ok(v) => Some(v),
+                Err(_) => return Err(PatternError::InvalidByte(v.into())),
+            },
+            v => return Err(PatternError::InvalidByte(v.into())),
+        };
+
+        bytes.push(b);
+    }
+
+    if bytes.first().is_none_or(|b| b.is_none()) {
+        return Err(PatternError::Wildcard);
+    }
+
+    Ok(bytes)
+}
+
+/// Represents an error when [`parse()`] fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatternError {
+    InvalidByte(String),
+    Wildcard,
+}
+
+impl std::error::Error for PatternError {}
+
+impl Display for PatternError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidByte(v) => write!(f, "invalid byte `{v}`"),
+            Self::Wildcard => f.write_str("expect a pattern that begin with a non-wildcard byte"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            super::parse("48 8d ?? ? FF").unwrap(),
+            [Some(0x48), Some(0x8D), None, None, Some(0xFF)]
+        );
+
+        assert_eq!(super::parse("?? 48"), Err(PatternError::Wildcard));
+        assert_eq!(
+            super::parse("480"),
+            Err(PatternError::InvalidByte("480".into()))
+        );
+        assert_eq!(
+            super::parse("4G"),
+            Err(PatternError::InvalidByte("4G".into()))
+        );
+        assert_eq!(super::parse(""), Err(PatternError::Wildcard));
+    }
+}
@@ -1,9 +1,10 @@
 use core::sync::atomic::AtomicU32;
+use okf::kernel_struct;
 
 /// Implementation of [`okf::file::File`] for 11.00.
+#[kernel_struct("offsets.toml")]
 #[repr(C)]
 pub struct File {
-    pad: [u8; 0x28],
     refcnt: AtomicU32,
 }
 
@@ -0,0 +1,5 @@
+/// Implementation of [`okf::kmem::VmMap`] for 11.00.
+#[repr(C)]
+pub struct VmMap {}
+
+impl okf::kmem::VmMap for VmMap {}
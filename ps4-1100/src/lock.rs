@@ -1,10 +1,11 @@
 use crate::Kernel;
+use core::ffi::{c_char, c_int};
+use okf::kernel_struct;
 
 /// Implementation of [`okf::lock::LockObject`] for 11.00.
+#[kernel_struct("offsets.toml")]
 #[repr(C)]
-pub struct LockObject {
-    pad1: [u8; 0x18],
-}
+pub struct LockObject {}
 
 impl okf::lock::LockObject for LockObject {}
 
@@ -20,3 +21,38 @@ impl okf::lock::Mtx<Kernel> for Mtx {
         &mut self.lock
     }
 }
+
+/// Implementation of [`okf::lock::RwLock`] for 11.00.
+#[repr(C)]
+pub struct RwLock {
+    lock: LockObject,
+    state: usize,
+}
+
+impl okf::lock::RwLock<Kernel> for RwLock {
+    fn lock_mut(&mut self) -> &mut LockObject {
+        &mut self.lock
+    }
+}
+
+/// Implementation of [`okf::lock::Sx`] for 11.00.
+#[repr(C)]
+pub struct Sx {
+    lock: LockObject,
+    state: usize,
+}
+
+impl okf::lock::Sx<Kernel> for Sx {
+    fn lock_mut(&mut self) -> &mut LockObject {
+        &mut self.lock
+    }
+}
+
+/// Implementation of [`okf::lock::Cv`] for 11.00.
+#[repr(C)]
+pub struct Cv {
+    description: *const c_char,
+    waiters: c_int,
+}
+
+impl okf::lock::Cv for Cv {}
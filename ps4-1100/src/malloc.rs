@@ -1,5 +1,25 @@
+use core::ffi::{c_char, c_ulong, c_void};
+use core::ptr::null_mut;
+use okf::malloc::M_MAGIC;
+
 /// Implementation of [`okf::malloc::Malloc`] for 11.00.
 #[repr(C)]
-pub struct Malloc {}
+pub struct Malloc {
+    next: *mut Self,
+    magic: c_ulong,
+    shortdesc: *const c_char,
+    handle: *mut c_void,
+}
 
-impl okf::malloc::Malloc for Malloc {}
+impl okf::malloc::Malloc for Malloc {
+    unsafe fn init(this: *mut Self, desc: *const c_char) {
+        unsafe {
+            this.write(Self {
+                next: null_mut(),
+                magic: M_MAGIC,
+                shortdesc: desc,
+                handle: null_mut(),
+            })
+        };
+    }
+}
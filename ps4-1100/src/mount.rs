@@ -4,19 +4,18 @@ use crate::vnode::Vnode;
 use core::ffi::{c_char, c_int};
 use core::mem::MaybeUninit;
 use core::num::NonZero;
+use okf::kernel_struct;
 use okf::queue::TailQueueEntry;
 
 /// Implementation of [`okf::mount::Mount`] for 11.00.
+#[kernel_struct("offsets.toml")]
 #[repr(C)]
 pub struct Mount {
     mtx: Mtx,
-    pad1: [u8; 0x8],
     entry: TailQueueEntry<Self>,
     ops: *const FsOps,
     fs: *mut Filesystem,
-    pad3: [u8; 0x38],
     flags: u64,
-    pad4: [u8; 0x20],
     stats: FsStats,
 }
 
@@ -51,9 +50,9 @@ impl okf::mount::Mount<Kernel> for Mount {
 }
 
 /// Implementation of [`okf::mount::Filesystem`] for 11.00.
+#[kernel_struct("offsets.toml")]
 #[repr(C)]
 pub struct Filesystem {
-    pad1: [u8; 4],
     name: [c_char; 16],
 }
 
@@ -64,9 +63,9 @@ impl okf::mount::Filesystem for Filesystem {
 }
 
 /// Implementation of [`okf::mount::FsOps`] for 11.00.
+#[kernel_struct("offsets.toml")]
 #[repr(C)]
 pub struct FsOps {
-    pad1: [u8; 0x18],
     root: unsafe extern "C" fn(*mut Mount, c_int, *mut *mut Vnode) -> c_int,
 }
 
@@ -83,11 +82,10 @@ impl okf::mount::FsOps<Kernel> for FsOps {
 }
 
 /// Implementation of [`okf::mount::FsStats`] for 11.00.
+#[kernel_struct("offsets.toml")]
 #[repr(C)]
 pub struct FsStats {
-    pad1: [u8; 0x128],
     mounted_from: [c_char; 88],
-    pad2: [u8; 0x58],
 }
 
 impl okf::mount::FsStats for FsStats {
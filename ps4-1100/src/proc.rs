@@ -0,0 +1,17 @@
+use core::ffi::c_int;
+
+/// Implementation of [`okf::proc::Proc`] for 11.00.
+///
+/// The offset of `p_pid` and the title ID are not verified yet.
+#[repr(C)]
+pub struct Proc {}
+
+impl okf::proc::Proc for Proc {
+    fn pid(&self) -> Option<c_int> {
+        None
+    }
+
+    fn title_id(&self) -> Option<&[u8; 10]> {
+        None
+    }
+}
@@ -1,10 +1,11 @@
 use core::ffi::{c_short, c_ushort};
 use core::sync::atomic::{AtomicU16, Ordering};
+use okf::kernel_struct;
 
 /// Implementation of [`okf::socket::Socket`] for 11.00.
+#[kernel_struct("offsets.toml")]
 #[repr(C)]
 pub struct Socket {
-    pad1: [u8; 0x6d],
     timeout: c_short,
     error: AtomicU16,
 }
@@ -0,0 +1,32 @@
+use crate::Kernel;
+use core::ffi::c_int;
+use core::mem::transmute;
+use core::sync::atomic::{AtomicI32, AtomicUsize, Ordering};
+use okf::kernel_struct;
+use okf::syscall::SyscallFn;
+
+/// Implementation of [`okf::syscall::Sysent`] for 11.00.
+#[kernel_struct("offsets.toml")]
+#[repr(C)]
+pub struct Sysent {
+    narg: AtomicI32,
+    call: AtomicUsize,
+}
+
+impl okf::syscall::Sysent<Kernel> for Sysent {
+    fn narg(&self) -> c_int {
+        self.narg.load(Ordering::Relaxed)
+    }
+
+    unsafe fn set_narg(&self, v: c_int) {
+        self.narg.store(v, Ordering::Release);
+    }
+
+    fn call(&self) -> SyscallFn<Kernel> {
+        unsafe { transmute(self.call.load(Ordering::Acquire)) }
+    }
+
+    unsafe fn set_call(&self, v: SyscallFn<Kernel>) {
+        self.call.store(v as usize, Ordering::Release);
+    }
+}
@@ -1,16 +1,23 @@
 use crate::Kernel;
+use crate::proc::Proc;
 use crate::ucred::Ucred;
+use core::ffi::c_int;
+use okf::kernel_struct;
 
 /// Implementation of [`okf::thread::Thread`] for 11.00.
+#[kernel_struct("offsets.toml")]
 #[repr(C)]
 pub struct Thread {
-    pad1: [u8; 0x130],
+    proc: *mut Proc,
     cred: *mut Ucred,
-    pad2: [u8; 0x260],
     ret: [usize; 2], // td_retval
 }
 
 impl okf::thread::Thread<Kernel> for Thread {
+    fn proc(&self) -> *mut Proc {
+        self.proc
+    }
+
     fn cred(&self) -> *mut Ucred {
         self.cred
     }
@@ -18,4 +25,15 @@ impl okf::thread::Thread<Kernel> for Thread {
     fn ret(&self, i: usize) -> usize {
         self.ret[i]
     }
+
+    fn set_ret(&mut self, i: usize, v: usize) {
+        self.ret[i] = v;
+    }
+
+    fn syscall(&self) -> Option<c_int> {
+        // The offset of td_sa.code is not verified yet.
+        None
+    }
+
+    fn set_syscall(&mut self, _: c_int) {}
 }
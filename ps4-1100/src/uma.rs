@@ -0,0 +1,5 @@
+/// Implementation of [`okf::uma::UmaZone`] for 11.00.
+#[repr(C)]
+pub struct UmaZone {}
+
+impl okf::uma::UmaZone for UmaZone {}
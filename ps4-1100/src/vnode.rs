@@ -1,8 +1,9 @@
 use crate::namei::ComponentName;
 use crate::ucred::Ucred;
 use crate::uio::Uio;
-use core::ffi::c_int;
+use core::ffi::{c_char, c_int};
 use okf::Kernel;
+use okf::vnode::{LockedVnode, VopArgs};
 
 /// Implementation of [`okf::vnode::Vnode`] for 11.00.
 #[repr(C)]
@@ -34,6 +35,34 @@ pub struct VnodeOp {}
 
 impl okf::vnode::VnodeOp for VnodeOp {}
 
+/// Implementation of [`okf::vnode::VopLock`] for 11.00.
+#[repr(C)]
+pub struct VopLock {
+    desc: *mut VnodeOp,
+    vp: *mut Vnode,
+    flags: c_int,
+    file: *const c_char,
+    line: c_int,
+}
+
+impl okf::vnode::VopLock<crate::Kernel> for VopLock {
+    unsafe fn new(
+        k: crate::Kernel,
+        vp: *mut Vnode,
+        flags: c_int,
+        file: *const c_char,
+        line: c_int,
+    ) -> Self {
+        Self {
+            desc: k.get(crate::Kernel::VOP_LOCK1).as_mut_ptr(),
+            vp,
+            flags,
+            file,
+            line,
+        }
+    }
+}
+
 /// Implementation of [`okf::vnode::VopUnlock`] for 11.00.
 #[repr(C)]
 pub struct VopUnlock {
@@ -42,7 +71,15 @@ pub struct VopUnlock {
     flags: c_int,
 }
 
-impl okf::vnode::VopUnlock for VopUnlock {}
+impl okf::vnode::VopUnlock<crate::Kernel> for VopUnlock {
+    unsafe fn new(k: crate::Kernel, vp: *mut Vnode, flags: c_int) -> Self {
+        Self {
+            desc: k.get(crate::Kernel::VOP_UNLOCK).as_mut_ptr(),
+            vp,
+            flags,
+        }
+    }
+}
 
 /// Implementation of [`okf::vnode::VopRead`] for 11.00.
 #[repr(C)]
@@ -55,20 +92,22 @@ pub struct VopRead {
 }
 
 impl okf::vnode::VopRead<crate::Kernel> for VopRead {
-    unsafe fn new(
+    unsafe fn new<'a>(
         k: crate::Kernel,
-        vp: *mut Vnode,
+        vp: &'a LockedVnode<crate::Kernel>,
         uio: *mut Uio,
         flags: c_int,
         cred: *mut Ucred,
-    ) -> Self {
-        Self {
+    ) -> VopArgs<'a, crate::Kernel, Self> {
+        let args = Self {
             desc: k.get(crate::Kernel::VOP_READ).as_mut_ptr(),
-            vp,
+            vp: vp.as_ptr(),
             uio,
             flags,
             cred,
-        }
+        };
+
+        unsafe { VopArgs::new(vp, args) }
     }
 }
 
@@ -85,24 +124,26 @@ pub struct VopReadDir {
 }
 
 impl okf::vnode::VopReadDir<crate::Kernel> for VopReadDir {
-    unsafe fn new(
+    unsafe fn new<'a>(
         k: crate::Kernel,
-        vp: *mut Vnode,
+        vp: &'a LockedVnode<crate::Kernel>,
         uio: *mut Uio,
         cred: *mut Ucred,
         eof: *mut c_int,
         ncookies: *mut c_int,
         cookies: *mut *mut u64,
-    ) -> Self {
-        Self {
+    ) -> VopArgs<'a, crate::Kernel, Self> {
+        let args = Self {
             desc: k.get(crate::Kernel::VOP_READDIR).as_mut_ptr(),
-            vp,
+            vp: vp.as_ptr(),
             uio,
             cred,
             eof,
             ncookies,
             cookies,
-        }
+        };
+
+        unsafe { VopArgs::new(vp, args) }
     }
 }
 
@@ -116,17 +157,19 @@ pub struct VopLookup {
 }
 
 impl okf::vnode::VopLookup<crate::Kernel> for VopLookup {
-    unsafe fn new(
+    unsafe fn new<'a>(
         k: crate::Kernel,
-        vp: *mut Vnode,
+        vp: &'a LockedVnode<crate::Kernel>,
         out: *mut *mut Vnode,
         cn: *mut ComponentName,
-    ) -> Self {
-        Self {
+    ) -> VopArgs<'a, crate::Kernel, Self> {
+        let args = Self {
             desc: k.get(crate::Kernel::VOP_LOOKUP).as_mut_ptr(),
-            vp,
+            vp: vp.as_ptr(),
             out,
             cn,
-        }
+        };
+
+        unsafe { VopArgs::new(vp, args) }
     }
 }
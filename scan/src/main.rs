@@ -0,0 +1,275 @@
+use self::signature::load_signatures;
+use self::symbol::SymbolMap;
+use okf::pattern::Signature;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+mod signature;
+mod symbol;
+
+fn main() -> ExitCode {
+    // Parse arguments.
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("{e}");
+            eprintln!();
+            eprintln!("{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match run(args) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(2),
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Returns `false` if some items are missing or ambiguous.
+fn run(args: Args) -> Result<bool, String> {
+    // Load the kernel.
+    let dump = std::fs::read(&args.dump)
+        .map_err(|e| format!("couldn't read {}: {}", args.dump.display(), e))?;
+    let text = okf::pattern::text(&dump)
+        .ok_or_else(|| format!("{} is not a valid kernel dump", args.dump.display()))?;
+
+    // Load the inputs.
+    let sigs = match &args.signatures {
+        Some(v) => load_signatures(v)?,
+        None => HashMap::new(),
+    };
+    let syms = match &args.symbols {
+        Some(v) => SymbolMap::load(v)?,
+        None => SymbolMap::default(),
+    };
+
+    for k in sigs.keys() {
+        if !okf::__KERNEL_OFFSETS.contains(&k.as_str()) {
+            eprintln!("{k}: unknown item in the signature list");
+        }
+    }
+
+    // Locate the items.
+    let mut offsets = String::new();
+    let mut found = HashMap::new();
+    let mut ok = true;
+
+    writeln!(offsets, "[offsets]").unwrap();
+
+    for &name in okf::__KERNEL_OFFSETS {
+        let r = locate(name, &dump, &text, &syms, sigs.get(name)).and_then(|v| {
+            match found.insert(v, name) {
+                Some(other) => Err(format!("same offset as {other}")),
+                None => Ok(v),
+            }
+        });
+
+        match r {
+            Ok(v) => writeln!(offsets, "{name} = {v:#X}").unwrap(),
+            Err(e) => {
+                eprintln!("{name}: {e}");
+                writeln!(offsets, "# {name} = ? ({e})").unwrap();
+                ok = false;
+            }
+        }
+    }
+
+    // Render the output.
+    let output = match &args.template {
+        Some(v) => {
+            let t = std::fs::read_to_string(v)
+                .map_err(|e| format!("couldn't read {}: {}", v.display(), e))?;
+
+            replace_offsets(&t, &offsets)
+        }
+        None => offsets,
+    };
+
+    match &args.output {
+        Some(v) => std::fs::write(v, output)
+            .map_err(|e| format!("couldn't write {}: {}", v.display(), e))?,
+        None => print!("{output}"),
+    }
+
+    Ok(ok)
+}
+
+fn locate(
+    name: &str,
+    dump: &[u8],
+    text: &okf::pattern::Text,
+    syms: &SymbolMap,
+    sig: Option<&Signature>,
+) -> Result<usize, String> {
+    // Use the symbol if available.
+    if let Some(addr) = syms.get(name) {
+        let off = addr
+            .checked_sub(text.base)
+            .and_then(|v| usize::try_from(v).ok())
+            .filter(|&v| v < dump.len())
+            .ok_or_else(|| format!("symbol address {addr:#x} is outside the dump"))?;
+
+        return Ok(off);
+    }
+
+    // Scan for the signature.
+    let sig = sig.ok_or("no symbol or signature")?;
+    let matches: Vec<usize> = sig.matches(text.data).collect();
+
+    match matches.as_slice() {
+        [] => Err("no match for the signature".into()),
+        &[off] => sig
+            .target(text.data, off)
+            .map(|v| text.offset + v)
+            .ok_or_else(|| "relative displacement is outside the text segment".into()),
+        v => {
+            let list = v
+                .iter()
+                .take(8)
+                .map(|&v| format!("{:#X}", text.offset + v))
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            Err(format!("{} matches for the signature ({list})", v.len()))
+        }
+    }
+}
+
+/// Comment to mark the tables that was copied from the template.
+const UNVERIFIED: &str = "# UNVERIFIED: copied from the template without checking the dump.";
+
+/// Returns `template` with `[offsets]` table replaced by `offsets`.
+///
+/// All other tables will be marked with [`UNVERIFIED`].
+fn replace_offsets(template: &str, offsets: &str) -> String {
+    let mut output = String::with_capacity(template.len() + offsets.len());
+    let mut lines = template.lines().peekable();
+    let mut replaced = false;
+
+    while let Some(line) = lines.next() {
+        if line.trim() != "[offsets]" {
+            // The other tables was not checked against the dump.
+            if line.trim_start().starts_with('[') {
+                output.push_str(UNVERIFIED);
+                output.push('\n');
+            }
+
+            output.push_str(line);
+            output.push('\n');
+            continue;
+        }
+
+        // Skip the old entries, keeping the blank lines before the next table.
+        while lines
+            .peek()
+            .is_some_and(|l| !l.trim_start().starts_with('['))
+        {
+            lines.next();
+        }
+
+        output.push_str(offsets);
+
+        if lines.peek().is_some() {
+            output.push('\n');
+        }
+
+        replaced = true;
+    }
+
+    if !replaced {
+        if !output.is_empty() {
+            output.push('\n');
+        }
+
+        output.push_str(offsets);
+    }
+
+    output
+}
+
+/// Command line arguments.
+struct Args {
+    dump: PathBuf,
+    signatures: Option<PathBuf>,
+    symbols: Option<PathBuf>,
+    template: Option<PathBuf>,
+    output: Option<PathBuf>,
+}
+
+impl Args {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut dump = None;
+        let mut signatures = None;
+        let mut symbols = None;
+        let mut template = None;
+        let mut output = None;
+
+        while let Some(arg) = args.next() {
+            let slot = match arg.as_str() {
+                "-s" | "--signatures" => &mut signatures,
+                "-m" | "--symbols" => &mut symbols,
+                "-t" | "--template" => &mut template,
+                "-o" | "--output" => &mut output,
+                v if v.starts_with('-') => return Err(format!("unknown option {v}")),
+                _ => {
+                    if dump.replace(PathBuf::from(arg)).is_some() {
+                        return Err("multiple kernel dumps is not supported".into());
+                    }
+
+                    continue;
+                }
+            };
+
+            match args.next() {
+                Some(v) => *slot = Some(PathBuf::from(v)),
+                None => return Err(format!("missing value for {arg}")),
+            }
+        }
+
+        Ok(Self {
+            dump: dump.ok_or("no kernel dump is specified")?,
+            signatures,
+            symbols,
+            template,
+            output,
+        })
+    }
+}
+
+const USAGE: &str = "\
+Usage: okf-scan [OPTIONS] <DUMP>
+
+Locate the items required by okf::Kernel in a kernel dump.
+
+Options:
+  -s, --signatures <FILE>  TOML file with byte signatures in [offsets] table
+  -m, --symbols <FILE>     Symbol map in nm format (ADDRESS TYPE NAME)
+  -t, --template <FILE>    offsets.toml of another firmware to copy the other tables from,
+                           which will be marked as unverified
+  -o, --output <FILE>      Write the result to FILE instead of stdout
+
+Exit status is 2 if some items are missing or ambiguous.";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn template() {
+        let template = "[consts]\nEIO = 5\n\n[offsets]\nfget = 0x1\n\n[structs.File]\nsize = 1\n";
+        let output = replace_offsets(template, "[offsets]\nfget = 0x2\n");
+
+        assert_eq!(
+            output,
+            format!(
+                "{UNVERIFIED}\n[consts]\nEIO = 5\n\n[offsets]\nfget = 0x2\n\n{UNVERIFIED}\n[structs.File]\nsize = 1\n"
+            )
+        );
+    }
+}
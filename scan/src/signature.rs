@@ -0,0 +1,51 @@
+use okf::pattern::Signature;
+use std::collections::HashMap;
+use std::path::Path;
+use toml::{Table, Value};
+
+/// Loads `offsets` table from a TOML file with the same format as the offset database.
+pub fn load_signatures(path: &Path) -> Result<HashMap<String, Signature>, String> {
+    let data = std::fs::read_to_string(path)
+        .map_err(|e| format!("couldn't read {}: {}", path.display(), e))?;
+    let table: Table = data
+        .parse()
+        .map_err(|e| format!("couldn't parse {}: {}", path.display(), e))?;
+    let offsets = match table.get("offsets") {
+        Some(Value::Table(v)) => v,
+        _ => return Err(format!("no `offsets` table in {}", path.display())),
+    };
+
+    // Parse signatures.
+    let mut sigs = HashMap::with_capacity(offsets.len());
+
+    for (name, v) in offsets {
+        let (pattern, rel) = match v {
+            Value::String(v) => (v, None),
+            Value::Table(t) => {
+                let p = match t.get("pattern") {
+                    Some(Value::String(v)) => v,
+                    _ => return Err(format!("`offsets.{name}.pattern` must be a string")),
+                };
+                let rel = match t.get("rel") {
+                    Some(Value::Integer(v)) => Some(
+                        usize::try_from(*v)
+                            .map_err(|_| format!("`offsets.{name}.rel` must be positive"))?,
+                    ),
+                    Some(_) => return Err(format!("`offsets.{name}.rel` must be an integer")),
+                    None => None,
+                };
+
+                (p, rel)
+            }
+            // Skip the fixed offsets so the offset database can be used as a signature list.
+            Value::Integer(_) => continue,
+            _ => return Err(format!("`offsets.{name}` must be a pattern or a table")),
+        };
+
+        let pattern = okf_pattern::parse(pattern).map_err(|e| format!("`offsets.{name}`: {e}"))?;
+
+        sigs.insert(name.clone(), Signature::new(pattern.leak(), rel));
+    }
+
+    Ok(sigs)
+}
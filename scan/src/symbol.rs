@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Symbol map in `nm` format.
+#[derive(Default)]
+pub struct SymbolMap(HashMap<String, u64>);
+
+impl SymbolMap {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let data = std::fs::read_to_string(path)
+            .map_err(|e| format!("couldn't read {}: {}", path.display(), e))?;
+
+        Self::parse(&data).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    fn parse(data: &str) -> Result<Self, String> {
+        let mut map = HashMap::new();
+
+        for (i, line) in data.lines().enumerate() {
+            // Skip blank lines and undefined symbols.
+            let fields: Vec<&str> = line.split_ascii_whitespace().collect();
+            let (addr, name) = match fields.as_slice() {
+                [] | ["U", _] => continue,
+                [addr, name] | [addr, _, name] => (*addr, *name),
+                _ => return Err(format!("invalid symbol at line {}", i + 1)),
+            };
+
+            let addr = u64::from_str_radix(addr.trim_start_matches("0x"), 16)
+                .map_err(|_| format!("invalid address at line {}", i + 1))?;
+
+            if map.insert(name.to_owned(), addr).is_some_and(|v| v != addr) {
+                return Err(format!("multiple addresses for {} at line {}", name, i + 1));
+            }
+        }
+
+        Ok(Self(map))
+    }
+
+    /// Returns the address of `item` in [`okf::Kernel`].
+    ///
+    /// The symbol name must be exactly the same as `item` unless it is listed in [`ALIASES`].
+    pub fn get(&self, item: &str) -> Option<u64> {
+        self.0.get(symbol(item)).copied()
+    }
+}
+
+/// Items in [`okf::Kernel`] that have a different name from their symbol.
+const ALIASES: &[(&str, &str)] = &[
+    ("ACCEPT_MTX", "accept_mtx"),
+    ("KERNEL_MAP", "kernel_map"),
+    ("MOUNTLIST", "mountlist"),
+    ("MOUNTLIST_MTX", "mountlist_mtx"),
+    ("PANIC", "panic"),
+    ("SYSENT", "sysent"),
+    ("VOP_LOCK1", "vop_lock1_desc"),
+    ("VOP_LOOKUP", "vop_lookup_desc"),
+    ("VOP_READ", "vop_read_desc"),
+    ("VOP_READDIR", "vop_readdir_desc"),
+    ("VOP_UNLOCK", "vop_unlock_desc"),
+    ("vop_lock1", "VOP_LOCK1_APV"),
+    ("vop_lookup", "VOP_LOOKUP_APV"),
+    ("vop_read", "VOP_READ_APV"),
+    ("vop_readdir", "VOP_READDIR_APV"),
+    ("vop_unlock", "VOP_UNLOCK_APV"),
+];
+
+/// Returns the symbol name of `item` in [`okf::Kernel`].
+fn symbol(item: &str) -> &str {
+    ALIASES
+        .iter()
+        .find(|&&(k, _)| k == item)
+        .map_or(item, |&(_, v)| v)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let map = SymbolMap::parse(
+            "ffffffff82619040 T fget\n\n                 U printf\nffffffff83c6ad60 mountlist\n",
+        )
+        .unwrap();
+
+        assert_eq!(map.get("fget"), Some(0xffffffff82619040));
+        assert_eq!(map.get("MOUNTLIST"), Some(0xffffffff83c6ad60));
+        assert_eq!(map.get("printf"), None);
+
+        // No case-insensitive match.
+        let map = SymbolMap::parse("ffffffff82531f70 T VOP_READ_APV\n").unwrap();
+
+        assert_eq!(map.get("vop_read"), Some(0xffffffff82531f70));
+        assert_eq!(map.get("VOP_READ"), None);
+
+        // Collision.
+        assert!(SymbolMap::parse("ffffffff82619040 T fget\nffffffff82619050 t fget\n").is_err());
+    }
+
+    #[test]
+    fn aliases() {
+        for &(item, sym) in ALIASES {
+            assert!(
+                okf::__KERNEL_OFFSETS.contains(&item),
+                "{item} is not an item"
+            );
+            assert!(!okf::__KERNEL_OFFSETS.contains(&sym), "{sym} is an item");
+        }
+    }
+}
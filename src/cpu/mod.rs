@@ -0,0 +1,156 @@
+use crate::Kernel;
+use core::arch::asm;
+use core::marker::PhantomData;
+
+const CR0_WP: usize = 1 << 16;
+const RFLAGS_IF: usize = 1 << 9;
+
+/// RAII struct to disable interrupts on the current CPU.
+///
+/// The interrupts will be enabled again when dropped if it was enabled at the time this guard was
+/// created so it is safe to nest.
+pub struct InterruptGuard {
+    enabled: bool,
+    phantom: PhantomData<*const ()>, // Must stay on the same CPU.
+}
+
+impl InterruptGuard {
+    pub fn new() -> Self {
+        let rflags: usize;
+
+        unsafe {
+            asm!(
+                "pushfq",
+                "pop {}",
+                "cli",
+                out(reg) rflags,
+                options(preserves_flags)
+            )
+        };
+
+        Self {
+            enabled: rflags & RFLAGS_IF != 0,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl Default for InterruptGuard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for InterruptGuard {
+    fn drop(&mut self) {
+        if self.enabled {
+            unsafe { asm!("sti", options(preserves_flags, nostack)) };
+        }
+    }
+}
+
+/// RAII struct to allow writing to read-only kernel memory (e.g. kernel text) on the current CPU.
+///
+/// This clear CR0.WP with the interrupts disabled. Both CR0 and the interrupts will be restored
+/// to the previous state when dropped so it is safe to nest.
+///
+/// [`crate::panic::panic()`] will set CR0.WP before invoking the kernel panic in case of a panic
+/// happens while this guard is active.
+pub struct WriteProtectGuard {
+    cr0: usize,
+    _interrupt: InterruptGuard, // Must be dropped after CR0 is restored.
+}
+
+impl WriteProtectGuard {
+    /// # Safety
+    /// The caller is responsible to make sure the read-only memory will not be corrupted.
+    pub unsafe fn new() -> Self {
+        let interrupt = InterruptGuard::new();
+        let cr0 = unsafe { read_cr0() };
+
+        unsafe { write_cr0(cr0 & !CR0_WP) };
+
+        Self {
+            cr0,
+            _interrupt: interrupt,
+        }
+    }
+}
+
+impl Drop for WriteProtectGuard {
+    fn drop(&mut self) {
+        unsafe { write_cr0(self.cr0) };
+    }
+}
+
+/// RAII struct to prevent the current thread from being preempted with `critical_enter`.
+///
+/// The thread can still be interrupted but it will stay on the current CPU until this guard is
+/// dropped so it is safe to access per-CPU data. This can be nested.
+pub struct CriticalGuard<K: Kernel> {
+    kern: K,
+    phantom: PhantomData<*const ()>, // Must stay on the same CPU.
+}
+
+impl<K: Kernel> CriticalGuard<K> {
+    pub fn new(kern: K) -> Self {
+        unsafe { kern.critical_enter() };
+
+        Self {
+            kern,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<K: Kernel> Drop for CriticalGuard<K> {
+    fn drop(&mut self) {
+        unsafe { self.kern.critical_exit() };
+    }
+}
+
+/// RAII struct to disable both interrupts and preemption with `spinlock_enter`.
+///
+/// Unlike [`InterruptGuard`] the kernel knows the interrupts are disabled. Nothing can sleep while
+/// this guard is active. This can be nested.
+pub struct SpinlockGuard<K: Kernel> {
+    kern: K,
+    phantom: PhantomData<*const ()>, // Must stay on the same CPU.
+}
+
+impl<K: Kernel> SpinlockGuard<K> {
+    pub fn new(kern: K) -> Self {
+        unsafe { kern.spinlock_enter() };
+
+        Self {
+            kern,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<K: Kernel> Drop for SpinlockGuard<K> {
+    fn drop(&mut self) {
+        unsafe { self.kern.spinlock_exit() };
+    }
+}
+
+/// Enables CR0.WP on the current CPU.
+///
+/// # Safety
+/// This will break any [`WriteProtectGuard`] that is active on the current CPU.
+pub(crate) unsafe fn enable_write_protect() {
+    unsafe { write_cr0(read_cr0() | CR0_WP) };
+}
+
+unsafe fn read_cr0() -> usize {
+    let v;
+
+    unsafe { asm!("mov {}, cr0", out(reg) v, options(preserves_flags, nomem, nostack)) };
+
+    v
+}
+
+unsafe fn write_cr0(v: usize) {
+    unsafe { asm!("mov cr0, {}", in(reg) v, options(preserves_flags, nostack)) };
+}
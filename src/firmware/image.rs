@@ -0,0 +1,191 @@
+use core::arch::asm;
+
+/// Maximum number of `PT_LOAD` segments to record.
+const MAX_SEGMENTS: usize = 8;
+
+/// Maximum distance from `Xfast_syscall` to the beginning of the kernel.
+const MAX_SCAN: usize = 0x4000000;
+
+/// Size of the page to scan for the ELF header.
+const PAGE: usize = 0x1000;
+
+/// Mapped kernel image that was located without any firmware-specific offsets.
+#[derive(Clone, Copy)]
+pub struct KernelImage {
+    base: *const u8,
+    segments: [(usize, usize); MAX_SEGMENTS],
+    len: usize,
+}
+
+impl KernelImage {
+    /// Creates a [`KernelImage`] with a single segment of `len` bytes.
+    pub const fn new(base: *const u8, len: usize) -> Self {
+        let mut segments = [(0, 0); MAX_SEGMENTS];
+
+        segments[0] = (0, len);
+
+        Self {
+            base,
+            segments,
+            len: 1,
+        }
+    }
+
+    /// Locates the kernel by scanning backward from `Xfast_syscall` in `LSTAR` for the ELF header.
+    ///
+    /// All pages between the beginning of the kernel and `Xfast_syscall` are mapped so this never
+    /// fault on any firmware.
+    pub fn from_lstar() -> Option<Self> {
+        let mut rdx: usize;
+        let mut rax: usize;
+
+        unsafe {
+            asm!(
+                "rdmsr",
+                in("ecx") 0xc0000082u32,
+                out("rdx") rdx,
+                out("rax") rax,
+                options(pure, nomem, preserves_flags, nostack)
+            )
+        };
+
+        let lstar = (rdx << 32) | rax;
+        let end = lstar.saturating_sub(MAX_SCAN);
+        let mut addr = lstar & !(PAGE - 1);
+
+        loop {
+            if let Some(v) = unsafe { Self::from_elf(addr as *const u8) } {
+                return Some(v);
+            }
+
+            addr = addr.checked_sub(PAGE)?;
+
+            if addr < end {
+                return None;
+            }
+        }
+    }
+
+    /// Returns [`None`] if `hdr` is not an ELF header of x86-64 executable or its program headers
+    /// are not within the first page.
+    ///
+    /// # Safety
+    /// `hdr` must be readable for 4096 bytes.
+    pub unsafe fn from_elf(hdr: *const u8) -> Option<Self> {
+        let page = unsafe { &*hdr.cast::<[u8; PAGE]>() };
+
+        if page[..7] != [0x7F, b'E', b'L', b'F', 2, 1, 1] || read::<2>(page, 0x12)? != 0x3E {
+            return None;
+        }
+
+        // Load segments.
+        let phoff = read::<8>(page, 0x20)?;
+        let phentsize = read::<2>(page, 0x36)?;
+        let phnum = read::<2>(page, 0x38)?;
+        let mut segments = [(0, 0); MAX_SEGMENTS];
+        let mut len = 0;
+        let mut low = usize::MAX;
+
+        if phentsize < 56 {
+            return None;
+        }
+
+        for i in 0..phnum {
+            let off = phoff.checked_add(i * phentsize)?;
+
+            if read::<4>(page, off)? != 1 {
+                continue;
+            }
+
+            let addr = read::<8>(page, off + 0x10)?;
+            let size = read::<8>(page, off + 0x28)?;
+
+            *segments.get_mut(len)? = (addr, addr.checked_add(size)?);
+            len += 1;
+            low = low.min(addr);
+        }
+
+        if len == 0 {
+            return None;
+        }
+
+        // Make the segments relative to the header.
+        for (start, end) in &mut segments[..len] {
+            *start -= low;
+            *end -= low;
+        }
+
+        Some(Self {
+            base: hdr,
+            segments,
+            len,
+        })
+    }
+
+    pub fn base(&self) -> *const u8 {
+        self.base
+    }
+
+    /// Returns `true` if `len` bytes at `off` from [`KernelImage::base()`] is within a segment.
+    pub fn contains(&self, off: usize, len: usize) -> bool {
+        let end = match off.checked_add(len) {
+            Some(v) => v,
+            None => return false,
+        };
+
+        self.segments[..self.len]
+            .iter()
+            .any(|&(s, e)| off >= s && end <= e)
+    }
+}
+
+fn read<const N: usize>(data: &[u8], off: usize) -> Option<usize> {
+    let mut buf = [0; 8];
+
+    buf[..N].copy_from_slice(data.get(off..off.checked_add(N)?)?);
+
+    Some(usize::from_le_bytes(buf))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_elf() {
+        // Build an ELF with two PT_LOAD and a PT_NOTE.
+        let mut page = [0u8; PAGE];
+
+        page[..7].copy_from_slice(&[0x7F, b'E', b'L', b'F', 2, 1, 1]);
+        page[0x12] = 0x3E;
+        page[0x20] = 0x40;
+        page[0x36] = 56;
+        page[0x38] = 3;
+
+        for (i, (ty, addr, size)) in [(1, 0x1000, 0x200), (4, 0, 0), (1, 0x3000, 0x100)]
+            .into_iter()
+            .enumerate()
+        {
+            let ph = &mut page[(0x40 + i * 56)..];
+
+            ph[..4].copy_from_slice(&u32::to_le_bytes(ty));
+            ph[0x10..0x18].copy_from_slice(&u64::to_le_bytes(addr));
+            ph[0x28..0x30].copy_from_slice(&u64::to_le_bytes(size));
+        }
+
+        // Check.
+        let img = unsafe { KernelImage::from_elf(page.as_ptr()).unwrap() };
+
+        assert_eq!(img.base(), page.as_ptr());
+        assert!(img.contains(0, 0x200));
+        assert!(!img.contains(0x1FF, 2));
+        assert!(img.contains(0x2000, 4));
+        assert!(!img.contains(0x20FE, 4));
+        assert!(!img.contains(usize::MAX, 2));
+
+        // Not an executable for x86-64.
+        page[0x12] = 0xB7;
+
+        assert!(unsafe { KernelImage::from_elf(page.as_ptr()) }.is_none());
+    }
+}
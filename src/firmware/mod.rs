@@ -0,0 +1,133 @@
+pub use self::image::*;
+
+use crate::{Kernel, Offset, Static};
+
+mod image;
+
+/// Provides information to identify a [`Kernel`] implementation at runtime.
+///
+/// Use [`any_kernel`](crate::any_kernel) to generate a type that can dispatch to one of multiple
+/// firmwares.
+pub trait Firmware: Kernel {
+    /// Version of the firmware in BCD (e.g. `0x1100` for 11.00).
+    const VERSION: u16;
+
+    /// Variable of `kern.sdk_version` sysctl (e.g. `0x11008001` for 11.00).
+    ///
+    /// The upper 16 bits must be the same as [`Firmware::VERSION`].
+    const SDK_VERSION: Static<u32>;
+
+    /// Locates the running kernel without using any offsets of this firmware.
+    ///
+    /// The default implementation is [`KernelImage::from_lstar()`].
+    fn image() -> Option<KernelImage> {
+        KernelImage::from_lstar()
+    }
+
+    /// Returns [`Some`] if the running kernel is [`Firmware::VERSION`].
+    ///
+    /// The kernel base calculated by this firmware must point to the image returned from
+    /// [`Firmware::image()`] and [`Firmware::SDK_VERSION`] must be within the image before it is
+    /// read so this never read an unmapped memory.
+    fn detect() -> Option<Self> {
+        let img = Self::image()?;
+        let k = Self::default();
+
+        if k.addr() != img.base() {
+            return None;
+        }
+
+        // Read the version.
+        let off = Self::SDK_VERSION.get(k.addr());
+
+        if !img.contains(off, size_of::<u32>()) {
+            return None;
+        }
+
+        let v = unsafe { k.addr().add(off).cast::<u32>().read_unaligned() };
+
+        match (v >> 16) as u16 == Self::VERSION {
+            true => Some(k),
+            false => None,
+        }
+    }
+}
+
+/// Operation to run with a [`Kernel`] that is detected at runtime.
+///
+/// This is required because a closure cannot be generic over [`Kernel`].
+pub trait KernelVisitor {
+    type Output;
+
+    fn visit<K: Kernel>(self, k: K) -> Self::Output;
+}
+
+/// Generates an enum that dispatch to one of [`Firmware`] implementation that match with the
+/// running kernel.
+///
+/// ```ignore
+/// okf::any_kernel! {
+///     pub enum AnyKernel {
+///         V1100(okf_1100::Kernel),
+///     }
+/// }
+///
+/// struct Hello;
+///
+/// impl KernelVisitor for Hello {
+///     type Output = ();
+///
+///     fn visit<K: Kernel>(self, k: K) {
+///         let mut n = Notification::new();
+///
+///         write!(n, "Hello, world!").unwrap();
+///         n.send(k);
+///     }
+/// }
+///
+/// if let Some(k) = AnyKernel::detect() {
+///     k.with(Hello);
+/// }
+/// ```
+#[macro_export]
+macro_rules! any_kernel {
+    (
+        $(#[$attr:meta])*
+        $vis:vis enum $name:ident {
+            $($variant:ident($ty:ty)),+ $(,)?
+        }
+    ) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy)]
+        $vis enum $name {
+            $($variant($ty)),+
+        }
+
+        impl $name {
+            /// Returns [`None`] if the running kernel is not one of the supported firmware.
+            $vis fn detect() -> Option<Self> {
+                $(
+                    if let Some(k) = <$ty as $crate::firmware::Firmware>::detect() {
+                        return Some(Self::$variant(k));
+                    }
+                )+
+
+                None
+            }
+
+            /// Returns the firmware version of the detected kernel in BCD.
+            $vis fn version(self) -> u16 {
+                match self {
+                    $(Self::$variant(_) => <$ty as $crate::firmware::Firmware>::VERSION),+
+                }
+            }
+
+            /// Invoke `v` with the detected kernel.
+            $vis fn with<V: $crate::firmware::KernelVisitor>(self, v: V) -> V::Output {
+                match self {
+                    $(Self::$variant(k) => v.visit(k)),+
+                }
+            }
+        }
+    };
+}
@@ -0,0 +1,365 @@
+/// Decoded x86-64 instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Insn {
+    /// Length of the instruction in bytes.
+    pub len: usize,
+    /// Offset and size of the relative operand within the instruction (e.g. `rel8` of `jmp` or
+    /// `disp32` of RIP-relative addressing).
+    pub rel: Option<(usize, usize)>,
+    pub kind: InsnKind,
+}
+
+/// Type of [`Insn`] in term of control flow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InsnKind {
+    Other,
+    /// Unconditional `jmp` with relative operand.
+    Jmp,
+    /// `jcc` with relative operand.
+    Jcc,
+    /// `call` with relative operand.
+    Call,
+    /// `loop`, `loope`, `loopne` or `jrcxz`.
+    Loop,
+    /// Any instruction that does not continue to the next instruction (e.g. `ret` or `int3`).
+    End,
+}
+
+/// Decodes the instruction at the beginning of `code`.
+///
+/// Returns [`None`] if the instruction is invalid, not supported (e.g. VEX or EVEX) or `code` is
+/// too short.
+pub fn decode(code: &[u8]) -> Option<Insn> {
+    let mut i = 0;
+    let mut opsize = false;
+    let mut addrsize = false;
+    let mut rexw = false;
+
+    // Legacy prefixes.
+    loop {
+        match *code.get(i)? {
+            0x66 => opsize = true,
+            0x67 => addrsize = true,
+            0xF0 | 0xF2 | 0xF3 | 0x26 | 0x2E | 0x36 | 0x3E | 0x64 | 0x65 => {}
+            _ => break,
+        }
+
+        i += 1;
+    }
+
+    // REX prefix.
+    if let 0x40..=0x4F = *code.get(i)? {
+        rexw = code[i] & 8 != 0;
+        i += 1;
+    }
+
+    // Opcode.
+    let z = if opsize && !rexw { 2 } else { 4 };
+    let op = *code.get(i)?;
+    let mut kind = InsnKind::Other;
+    let mut rel = false;
+
+    i += 1;
+
+    let (modrm, imm) = match op {
+        0x0F => return decode_0f(code, i, z),
+        0x00..=0x3F => match op & 7 {
+            0..=3 => (true, 0),
+            4 => (false, 1),
+            5 => (false, z),
+            _ => return None, // Prefixes and invalid opcodes in 64-bit mode.
+        },
+        0x50..=0x5F => (false, 0),
+        0x63 => (true, 0),
+        0x68 => (false, z),
+        0x69 => (true, z),
+        0x6A => (false, 1),
+        0x6B => (true, 1),
+        0x6C..=0x6F => (false, 0),
+        0x70..=0x7F => {
+            kind = InsnKind::Jcc;
+            rel = true;
+            (false, 1)
+        }
+        0x80 | 0x83 => (true, 1),
+        0x81 => (true, z),
+        0x84..=0x8F => (true, 0),
+        0x90..=0x99 | 0x9B..=0x9F => (false, 0),
+        0xA0..=0xA3 => (false, if addrsize { 4 } else { 8 }),
+        0xA4..=0xA7 | 0xAA..=0xAF => (false, 0),
+        0xA8 => (false, 1),
+        0xA9 => (false, z),
+        0xB0..=0xB7 => (false, 1),
+        0xB8..=0xBF => (false, if rexw { 8 } else { z }),
+        0xC0 | 0xC1 | 0xC6 => (true, 1),
+        0xC2 | 0xCA => {
+            kind = InsnKind::End;
+            (false, 2)
+        }
+        0xC3 | 0xCB | 0xCC | 0xCF => {
+            kind = InsnKind::End;
+            (false, 0)
+        }
+        0xC7 => (true, z),
+        0xC8 => (false, 3),
+        0xC9 => (false, 0),
+        0xCD => (false, 1),
+        0xD0..=0xD3 | 0xD8..=0xDF => (true, 0),
+        0xD7 => (false, 0),
+        0xE0..=0xE3 => {
+            kind = InsnKind::Loop;
+            rel = true;
+            (false, 1)
+        }
+        0xE4..=0xE7 => (false, 1),
+        0xE8 | 0xE9 => {
+            kind = if op == 0xE8 {
+                InsnKind::Call
+            } else {
+                InsnKind::Jmp
+            };
+            rel = true;
+            (false, 4)
+        }
+        0xEB => {
+            kind = InsnKind::Jmp;
+            rel = true;
+            (false, 1)
+        }
+        0xEC..=0xEF | 0xF1 | 0xF5 | 0xF8..=0xFD => (false, 0),
+        0xF4 => {
+            kind = InsnKind::End;
+            (false, 0)
+        }
+        0xF6 | 0xF7 => {
+            // Only TEST has an immediate.
+            let reg = (code.get(i)? >> 3) & 7;
+            let imm = match (op, reg) {
+                (0xF6, 0 | 1) => 1,
+                (0xF7, 0 | 1) => z,
+                _ => 0,
+            };
+
+            (true, imm)
+        }
+        0xFE => (true, 0),
+        0xFF => {
+            // Indirect jmp (FF /4 and FF /5).
+            if matches!((code.get(i)? >> 3) & 7, 4 | 5) {
+                kind = InsnKind::End;
+            }
+
+            (true, 0)
+        }
+        _ => return None,
+    };
+
+    // ModR/M.
+    let mut disp = None;
+
+    if modrm {
+        let (len, rip) = decode_modrm(code, i)?;
+
+        if rip {
+            disp = Some((i + len - 4, 4));
+        }
+
+        i += len;
+    }
+
+    // Immediate.
+    let len = i + imm;
+
+    if code.len() < len {
+        return None;
+    }
+
+    Some(Insn {
+        len,
+        rel: if rel { Some((i, imm)) } else { disp },
+        kind,
+    })
+}
+
+fn decode_0f(code: &[u8], mut i: usize, z: usize) -> Option<Insn> {
+    let op = *code.get(i)?;
+    let mut kind = InsnKind::Other;
+
+    i += 1;
+
+    let (modrm, imm) = match op {
+        0x0B => {
+            kind = InsnKind::End;
+            (false, 0)
+        }
+        0x05..=0x09 | 0x0E | 0x30..=0x37 | 0x77 | 0xA0..=0xA2 | 0xA8..=0xAA | 0xC8..=0xCF => {
+            (false, 0)
+        }
+        0x38 => {
+            i += 1;
+            (true, 0)
+        }
+        0x3A => {
+            i += 1;
+            (true, 1)
+        }
+        0x70..=0x73 | 0xA4 | 0xAC | 0xBA | 0xC2 | 0xC4..=0xC6 => (true, 1),
+        0x80..=0x8F => {
+            let len = i + z;
+
+            if code.len() < len {
+                return None;
+            }
+
+            return Some(Insn {
+                len,
+                rel: Some((i, z)),
+                kind: InsnKind::Jcc,
+            });
+        }
+        0x04 | 0x0A | 0x0C | 0x24..=0x27 | 0x39 | 0x3B..=0x3F | 0xFF => return None,
+        _ => (true, 0),
+    };
+
+    // ModR/M.
+    let mut rel = None;
+
+    if modrm {
+        let (len, rip) = decode_modrm(code, i)?;
+
+        if rip {
+            rel = Some((i + len - 4, 4));
+        }
+
+        i += len;
+    }
+
+    // Immediate.
+    let len = i + imm;
+
+    if code.len() < len {
+        return None;
+    }
+
+    Some(Insn { len, rel, kind })
+}
+
+/// Returns length of ModR/M, SIB and displacement at `i` and whether it is RIP-relative.
+fn decode_modrm(code: &[u8], i: usize) -> Option<(usize, bool)> {
+    let modrm = *code.get(i)?;
+    let md = modrm >> 6;
+    let rm = modrm & 7;
+
+    if md == 3 {
+        return Some((1, false));
+    }
+
+    // SIB.
+    let mut len = 1;
+
+    if rm == 4 {
+        let sib = *code.get(i + 1)?;
+
+        len += 1;
+
+        if md == 0 && sib & 7 == 5 {
+            return Some((len + 4, false));
+        }
+    } else if md == 0 && rm == 5 {
+        return Some((len + 4, true));
+    }
+
+    // Displacement.
+    match md {
+        1 => len += 1,
+        2 => len += 4,
+        _ => {}
+    }
+
+    Some((len, false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn len(code: &[u8]) -> usize {
+        decode(code).unwrap().len
+    }
+
+    #[test]
+    fn decode_len() {
+        assert_eq!(len(&[0x55]), 1); // push rbp
+        assert_eq!(len(&[0x48, 0x89, 0xE5]), 3); // mov rbp, rsp
+        assert_eq!(len(&[0x41, 0x57]), 2); // push r15
+        assert_eq!(len(&[0x48, 0x83, 0xEC, 0x28]), 4); // sub rsp, 0x28
+        assert_eq!(len(&[0x48, 0x81, 0xEC, 0, 1, 0, 0]), 7); // sub rsp, 0x100
+        assert_eq!(len(&[0x48, 0x89, 0x5C, 0x24, 0x08]), 5); // mov [rsp+8], rbx
+        assert_eq!(len(&[0x48, 0x8B, 0x84, 0x24, 0, 1, 0, 0]), 8); // mov rax, [rsp+0x100]
+        assert_eq!(len(&[0x48, 0xB8, 1, 2, 3, 4, 5, 6, 7, 8]), 10); // mov rax, imm64
+        assert_eq!(len(&[0xB8, 1, 2, 3, 4]), 5); // mov eax, imm32
+        assert_eq!(len(&[0x66, 0xB8, 1, 2]), 4); // mov ax, imm16
+        assert_eq!(len(&[0x66, 0x0F, 0x1F, 0x44, 0, 0]), 6); // nop word [rax+rax]
+        assert_eq!(len(&[0x0F, 0x1F, 0x80, 0, 0, 0, 0]), 7); // nop dword [rax+0]
+        assert_eq!(len(&[0xF6, 0x47, 0x10, 0x01]), 4); // test byte [rdi+0x10], 1
+        assert_eq!(len(&[0xF7, 0xD8]), 2); // neg eax
+        assert_eq!(len(&[0x65, 0x48, 0x8B, 0x04, 0x25, 0, 0, 0, 0]), 9); // mov rax, gs:0
+        assert_eq!(len(&[0x0F, 0x3A, 0x0F, 0xC1, 0x08]), 5); // palignr xmm0, xmm1, 8
+        assert_eq!(len(&[0xC3]), 1);
+    }
+
+    #[test]
+    fn decode_rel() {
+        // mov rax, [rip+0x10]
+        let i = decode(&[0x48, 0x8B, 0x05, 0x10, 0, 0, 0]).unwrap();
+
+        assert_eq!(i.len, 7);
+        assert_eq!(i.rel, Some((3, 4)));
+        assert_eq!(i.kind, InsnKind::Other);
+
+        // cmp byte [rip+0x10], 0
+        let i = decode(&[0x80, 0x3D, 0x10, 0, 0, 0, 0]).unwrap();
+
+        assert_eq!(i.len, 7);
+        assert_eq!(i.rel, Some((2, 4)));
+
+        // Branches.
+        let i = decode(&[0x74, 0x10]).unwrap();
+
+        assert_eq!((i.len, i.rel, i.kind), (2, Some((1, 1)), InsnKind::Jcc));
+
+        let i = decode(&[0x0F, 0x84, 0, 0, 0, 0]).unwrap();
+
+        assert_eq!((i.len, i.rel, i.kind), (6, Some((2, 4)), InsnKind::Jcc));
+
+        let i = decode(&[0xE8, 0, 0, 0, 0]).unwrap();
+
+        assert_eq!((i.len, i.rel, i.kind), (5, Some((1, 4)), InsnKind::Call));
+
+        let i = decode(&[0xEB, 0xFE]).unwrap();
+
+        assert_eq!((i.len, i.rel, i.kind), (2, Some((1, 1)), InsnKind::Jmp));
+
+        // Indirect branches.
+        let i = decode(&[0xFF, 0x20]).unwrap(); // jmp [rax]
+
+        assert_eq!((i.len, i.rel, i.kind), (2, None, InsnKind::End));
+
+        let i = decode(&[0xFF, 0x2D, 0, 0, 0, 0]).unwrap(); // jmp far [rip+0]
+
+        assert_eq!((i.len, i.rel, i.kind), (6, Some((2, 4)), InsnKind::End));
+
+        let i = decode(&[0xFF, 0xD0]).unwrap(); // call rax
+
+        assert_eq!((i.len, i.rel, i.kind), (2, None, InsnKind::Other));
+    }
+
+    #[test]
+    fn decode_invalid() {
+        assert_eq!(decode(&[]), None);
+        assert_eq!(decode(&[0x48]), None);
+        assert_eq!(decode(&[0x48, 0x8B, 0x05, 0x10]), None);
+        assert_eq!(decode(&[0x06]), None);
+        assert_eq!(decode(&[0xC5, 0xF8, 0x77]), None);
+    }
+}
@@ -0,0 +1,292 @@
+use self::trampoline::{build, jump};
+use crate::cpu::WriteProtectGuard;
+use crate::patch::{Claim, PatchError, claim};
+use crate::{Function, Kernel, KernelFn, Offset};
+use core::arch::x86_64::__cpuid;
+use core::ffi::c_void;
+use core::fmt::{Display, Formatter};
+use core::hint::spin_loop;
+use core::marker::PhantomData;
+use core::mem::ManuallyDrop;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+mod decode;
+mod trampoline;
+
+/// Maximum number of bytes that can be overwritten at the target function.
+const MAX_STOLEN: usize = 14 + 15;
+
+/// Recommended size of the trampoline for [`Hook`].
+pub const TRAMPOLINE_SIZE: usize = 128;
+
+/// Detour that was installed on a kernel function.
+///
+/// The beginning of the target function will be replaced with a jump to the detour. The
+/// instructions that was overwritten will be relocated to a trampoline, which can be invoked with
+/// [`Hook::original()`] to call the original function.
+///
+/// Dropping this type will leave the detour installed and the target reserved forever. Use
+/// [`Hook::uninstall()`] to remove it.
+pub struct Hook<T: KernelFn> {
+    target: *mut u8,
+    trampoline: *mut u8,
+    len: usize,
+    saved: [u8; MAX_STOLEN],
+    stolen: usize,
+    claim: ManuallyDrop<Claim>, // Must not released while the detour is installed.
+    phantom: PhantomData<T>,
+}
+
+impl<T: KernelFn> Hook<T> {
+    /// Installs `detour` on `f`.
+    ///
+    /// `trampoline` must be an executable memory that live until this hook is uninstalled and no
+    /// CPUs are running on it. [`TRAMPOLINE_SIZE`] bytes is enough for any function.
+    ///
+    /// The target will be written while all of the other CPUs are waiting in `smp_rendezvous` with
+    /// the interrupts disabled so no CPUs can see a partially written jump.
+    ///
+    /// # Safety
+    /// - `detour` must have the same calling convention and signature as `f`.
+    /// - No threads can be suspended in the middle of the overwritten region (e.g. preempted after
+    ///   the first instruction) since it will resume on the new bytes.
+    /// - Cannot be called while holding a spin lock.
+    pub unsafe fn install<K: Kernel>(
+        k: K,
+        f: Function<T>,
+        detour: T,
+        trampoline: &'static mut [u8],
+    ) -> Result<Self, HookError> {
+        let target = unsafe { k.addr().add(f.get(k.addr())) };
+
+        unsafe { Self::at(k, target.cast_mut(), detour, trampoline) }
+    }
+
+    /// Same as [`Hook::install()`] but install at `target`.
+    ///
+    /// # Safety
+    /// Same as [`Hook::install()`] and `target` must be the first instruction of a function.
+    pub unsafe fn at<K: Kernel>(
+        k: K,
+        target: *mut u8,
+        detour: T,
+        trampoline: &'static mut [u8],
+    ) -> Result<Self, HookError> {
+        unsafe { Self::new(target, detour, trampoline, |dst, code| stop(k, dst, code)) }
+    }
+
+    /// # Safety
+    /// Same as [`Hook::at()`].
+    unsafe fn new(
+        target: *mut u8,
+        detour: T,
+        trampoline: &'static mut [u8],
+        write: impl FnOnce(*mut u8, &[u8]),
+    ) -> Result<Self, HookError> {
+        // Build the trampoline.
+        let (jmp, len) = jump(target as usize, detour.addr() as usize);
+        let code = unsafe { core::slice::from_raw_parts(target, MAX_STOLEN) };
+        let stolen = build(
+            code,
+            target as usize,
+            len,
+            trampoline,
+            trampoline.as_ptr() as usize,
+        )?;
+
+        // Make sure no one else is patching the same range.
+        let claim = claim(target as usize, stolen).map_err(HookError::Patch)?;
+
+        // Overwrite the target. The remaining bytes is filled with int3 so it is easy to spot if
+        // something jump to the middle of it.
+        let mut saved = [0; MAX_STOLEN];
+        let mut patch = [0xCC; MAX_STOLEN];
+
+        saved[..stolen].copy_from_slice(&code[..stolen]);
+        patch[..len].copy_from_slice(&jmp[..len]);
+
+        write(target, &patch[..stolen]);
+
+        Ok(Self {
+            target,
+            trampoline: trampoline.as_mut_ptr(),
+            len: trampoline.len(),
+            saved,
+            stolen,
+            claim: ManuallyDrop::new(claim),
+            phantom: PhantomData,
+        })
+    }
+
+    /// Returns a function to call the original function.
+    pub fn original(&self) -> T {
+        // SAFETY: The trampoline have the same signature as the target.
+        unsafe { T::from_addr(self.trampoline) }
+    }
+
+    /// Restores the original function and returns the trampoline.
+    ///
+    /// The trampoline should not be reused until no CPUs are running on it.
+    ///
+    /// # Safety
+    /// Same as [`Hook::install()`].
+    pub unsafe fn uninstall<K: Kernel>(self, k: K) -> &'static mut [u8] {
+        unsafe { self.restore(|dst, code| stop(k, dst, code)) }
+    }
+
+    /// # Safety
+    /// Same as [`Hook::install()`].
+    unsafe fn restore(self, write: impl FnOnce(*mut u8, &[u8])) -> &'static mut [u8] {
+        write(self.target, &self.saved[..self.stolen]);
+
+        drop(ManuallyDrop::into_inner(self.claim));
+
+        unsafe { core::slice::from_raw_parts_mut(self.trampoline, self.len) }
+    }
+}
+
+unsafe impl<T: KernelFn> Send for Hook<T> {}
+unsafe impl<T: KernelFn> Sync for Hook<T> {}
+
+/// Represents an error when installing [`Hook`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookError {
+    /// The instruction at the offset from the beginning of the target cannot be decoded.
+    Decode(usize),
+    /// The instruction at the offset from the beginning of the target cannot be relocated.
+    Unrelocatable(usize),
+    /// The target function is shorter than the jump.
+    TooShort,
+    /// The trampoline does not have enough space.
+    TrampolineTooSmall,
+    /// The target cannot be patched.
+    Patch(PatchError),
+}
+
+impl Display for HookError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Decode(v) => write!(f, "couldn't decode the instruction at {v:#x}"),
+            Self::Unrelocatable(v) => write!(f, "couldn't relocate the instruction at {v:#x}"),
+            Self::TooShort => f.write_str("the target function is too short"),
+            Self::TrampolineTooSmall => f.write_str("not enough space for the trampoline"),
+            Self::Patch(e) => e.fmt(f),
+        }
+    }
+}
+
+/// Writes `code` to `dst` while all of the other CPUs are waiting with the interrupts disabled.
+///
+/// # Safety
+/// `dst` must be valid for `code.len()` bytes.
+unsafe fn stop<K: Kernel>(k: K, dst: *mut u8, code: &[u8]) {
+    let mut r = Rendezvous {
+        dst,
+        code,
+        writer: AtomicBool::new(false),
+        done: AtomicBool::new(false),
+    };
+
+    unsafe { k.smp_rendezvous(None, Some(rendezvous), None, (&raw mut r).cast()) };
+}
+
+/// Runs on all CPUs by `smp_rendezvous`.
+///
+/// # Safety
+/// `arg` must point to a [`Rendezvous`].
+unsafe extern "C" fn rendezvous(arg: *mut c_void) {
+    let r = unsafe { &*arg.cast::<Rendezvous>() };
+
+    // Only the first CPU writes. The write protection is per-CPU so we need to disable it here.
+    if !r.writer.swap(true, Ordering::Relaxed) {
+        let _wp = unsafe { WriteProtectGuard::new() };
+
+        for (i, &b) in r.code.iter().enumerate() {
+            unsafe { r.dst.add(i).write_volatile(b) };
+        }
+
+        r.done.store(true, Ordering::Release);
+    } else {
+        while !r.done.load(Ordering::Acquire) {
+            spin_loop();
+        }
+    }
+
+    // Discard the instructions that was prefetched before the write.
+    __cpuid(0);
+}
+
+/// Arguments for [`rendezvous()`].
+struct Rendezvous<'a> {
+    dst: *mut u8,
+    code: &'a [u8],
+    writer: AtomicBool,
+    done: AtomicBool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::vec;
+
+    extern crate std;
+
+    extern "C" fn detour(_: usize) -> usize {
+        0
+    }
+
+    #[test]
+    fn install() {
+        // Setup target and trampoline.
+        let code = [
+            0x55, // push rbp
+            0x48, 0x89, 0xE5, // mov rbp, rsp
+            0x41, 0x57, // push r15
+            0x41, 0x56, // push r14
+            0x53, // push rbx
+            0x48, 0x83, 0xEC, 0x18, // sub rsp, 0x18
+            0x48, 0x89, 0xFB, // mov rbx, rdi
+        ];
+        let mut target = vec![0xCC; MAX_STOLEN];
+
+        target[..code.len()].copy_from_slice(&code);
+
+        let target = target.leak().as_mut_ptr();
+        let trampoline = vec![0; TRAMPOLINE_SIZE].leak();
+        let detour: unsafe extern "C" fn(usize) -> usize = detour;
+
+        // Install.
+        let write = |dst: *mut u8, code: &[u8]| unsafe {
+            core::ptr::copy_nonoverlapping(code.as_ptr(), dst, code.len())
+        };
+        let hook = unsafe { Hook::new(target, detour, trampoline, write).unwrap() };
+        let patched = unsafe { core::slice::from_raw_parts(target, MAX_STOLEN) };
+        let (jmp, len) = jump(target as usize, detour as usize);
+
+        assert_eq!(patched[..len], jmp[..len]);
+        assert!(patched[len..hook.stolen].iter().all(|&b| b == 0xCC));
+        assert_eq!(hook.original() as *const u8, hook.trampoline.cast_const());
+
+        // Check trampoline.
+        let t = unsafe { core::slice::from_raw_parts(hook.trampoline, TRAMPOLINE_SIZE) };
+
+        assert_eq!(t[..hook.stolen], code[..hook.stolen]);
+        assert_eq!(t[hook.stolen..(hook.stolen + 6)], [0xFF, 0x25, 0, 0, 0, 0]);
+
+        // Uninstall.
+        let stolen = hook.stolen;
+
+        unsafe { hook.restore(write) };
+
+        assert_eq!(patched[..stolen], code[..stolen]);
+
+        // Dropping must keep the target reserved.
+        let trampoline = vec![0; TRAMPOLINE_SIZE].leak();
+        unsafe { Hook::new(target, detour, trampoline, write).unwrap() };
+
+        assert_eq!(
+            claim(target as usize, 1).err(),
+            Some(PatchError::Overlapped)
+        );
+    }
+}
@@ -0,0 +1,294 @@
+use super::HookError;
+use super::decode::{InsnKind, decode};
+
+/// Returns a jump from `src` to `dst` and its length.
+///
+/// The result will be `jmp rel32` if `dst` is within 2 GB of `src` otherwise `jmp [rip+0]`
+/// followed by the absolute address.
+pub fn jump(src: usize, dst: usize) -> ([u8; 14], usize) {
+    let mut buf = [0; 14];
+
+    match rel32(src + 5, dst) {
+        Some(v) => {
+            buf[0] = 0xE9;
+            buf[1..5].copy_from_slice(&v.to_le_bytes());
+
+            (buf, 5)
+        }
+        None => (absolute(dst), 14),
+    }
+}
+
+/// Returns `jmp [rip+0]` followed by `dst`.
+fn absolute(dst: usize) -> [u8; 14] {
+    let mut buf = [0; 14];
+
+    buf[..6].copy_from_slice(&[0xFF, 0x25, 0, 0, 0, 0]);
+    buf[6..].copy_from_slice(&(dst as u64).to_le_bytes());
+    buf
+}
+
+/// Maximum number of instructions that can be relocated.
+const MAX_INSNS: usize = 16;
+
+/// Relocates the instructions at the beginning of `code` that cover at least `min` bytes to `out`
+/// followed by a jump back to the remaining instructions.
+///
+/// `src` is the address of `code` and `dst` is the address of `out`. Returns the number of bytes
+/// that was relocated from `code`. A relative operand that point inside the relocated bytes will
+/// point to the same instruction in `out` since the original one will be overwritten by the hook.
+pub fn build(
+    code: &[u8],
+    src: usize,
+    min: usize,
+    out: &mut [u8],
+    dst: usize,
+) -> Result<usize, HookError> {
+    let mut i = 0;
+    let mut o = 0;
+    let mut insns = [(0, 0); MAX_INSNS];
+    let mut fixups = [Fixup::default(); MAX_INSNS];
+    let mut n = 0;
+
+    while i < min {
+        let insn = decode(&code[i..]).ok_or(HookError::Decode(i))?;
+        let code = &code[i..(i + insn.len)];
+        let next = src + i + insn.len;
+
+        if n == MAX_INSNS {
+            return Err(HookError::Unrelocatable(i));
+        }
+
+        // Get target of the relative operand.
+        let target = insn.rel.map(|(off, size)| {
+            let v = match size {
+                1 => code[off] as i8 as isize,
+                2 => i16::from_le_bytes([code[off], code[off + 1]]) as isize,
+                _ => i32::from_le_bytes(code[off..(off + 4)].try_into().unwrap()) as isize,
+            };
+
+            next.wrapping_add_signed(v)
+        });
+
+        // Relocate. The displacement will be written after we know all relocated instructions.
+        let unrelocatable = HookError::Unrelocatable(i);
+        let mut buf = [0; 15];
+        let (len, field) = match (insn.kind, insn.rel) {
+            (InsnKind::Loop, _) => return Err(unrelocatable),
+            (InsnKind::Jmp | InsnKind::Jcc, Some((_, 1))) => {
+                // Convert to rel32.
+                let len = if insn.kind == InsnKind::Jmp {
+                    buf[0] = 0xE9;
+                    5
+                } else {
+                    buf[..2].copy_from_slice(&[0x0F, 0x80 | (code[insn.len - 2] & 0xF)]);
+                    6
+                };
+
+                (len, Some(len - 4))
+            }
+            (_, Some((off, 4))) => {
+                buf[..insn.len].copy_from_slice(code);
+                (insn.len, Some(off))
+            }
+            (_, Some(_)) => return Err(unrelocatable),
+            (_, None) => {
+                buf[..insn.len].copy_from_slice(code);
+                (insn.len, None)
+            }
+        };
+
+        emit(out, o, &buf[..len])?;
+
+        insns[n] = (i, o);
+        fixups[n] = match (field, target) {
+            (Some(f), Some(t)) => Fixup {
+                field: Some(o + f),
+                next: o + len,
+                target: t,
+            },
+            _ => Fixup::default(),
+        };
+
+        n += 1;
+        o += len;
+        i += insn.len;
+
+        // Check if the function end before the required length.
+        if i < min && matches!(insn.kind, InsnKind::Jmp | InsnKind::End) {
+            return Err(HookError::TooShort);
+        }
+    }
+
+    // Write the displacements.
+    let range = src..(src + i);
+
+    for (&(off, _), f) in insns[..n].iter().zip(&fixups[..n]) {
+        let field = match f.field {
+            Some(v) => v,
+            None => continue,
+        };
+
+        let v = if range.contains(&f.target) {
+            // Point to the relocated instruction.
+            let target = f.target - src;
+            let (_, o) = insns[..n]
+                .iter()
+                .find(|&&(i, _)| i == target)
+                .ok_or(HookError::Unrelocatable(off))?;
+
+            (*o as isize - f.next as isize) as i32
+        } else {
+            rel32(dst + f.next, f.target).ok_or(HookError::Unrelocatable(off))?
+        };
+
+        out[field..(field + 4)].copy_from_slice(&v.to_le_bytes());
+    }
+
+    // Jump back.
+    emit(out, o, &absolute(src + i))?;
+
+    Ok(i)
+}
+
+/// 32-bit displacement in the relocated instructions.
+#[derive(Default, Clone, Copy)]
+struct Fixup {
+    /// Offset of the displacement in the output.
+    field: Option<usize>,
+    /// Offset of the next instruction in the output.
+    next: usize,
+    target: usize,
+}
+
+fn emit(out: &mut [u8], off: usize, buf: &[u8]) -> Result<(), HookError> {
+    out.get_mut(off..(off + buf.len()))
+        .ok_or(HookError::TrampolineTooSmall)?
+        .copy_from_slice(buf);
+
+    Ok(())
+}
+
+/// Returns displacement from `next` to `target` if it fit in 32-bit.
+fn rel32(next: usize, target: usize) -> Option<i32> {
+    (target.wrapping_sub(next) as isize).try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jump() {
+        let (buf, len) = super::jump(0x1000, 0x2000);
+
+        assert_eq!(&buf[..len], [0xE9, 0xFB, 0x0F, 0, 0]);
+
+        let (buf, len) = super::jump(0xFFFFFFFF82200000, 0x100000001000);
+
+        assert_eq!(
+            &buf[..len],
+            [0xFF, 0x25, 0, 0, 0, 0, 0, 0x10, 0, 0, 0, 0x10, 0, 0]
+        );
+    }
+
+    #[test]
+    fn build() {
+        let src = 0x10000;
+        let dst = 0x20000;
+        let code = [
+            0x55, // push rbp
+            0x48, 0x89, 0xE5, // mov rbp, rsp
+            0x48, 0x8B, 0x05, 0x10, 0x00, 0x00, 0x00, // mov rax, [rip+0x10]
+            0x74, 0x02, // je +2
+            0xEB, 0xFE, // jmp $
+            0xC3, // ret
+        ];
+        let mut out = [0; 64];
+        let len = super::build(&code, src, 14, &mut out, dst).unwrap();
+
+        assert_eq!(len, 15);
+
+        // The first two instructions is the same.
+        assert_eq!(out[..4], code[..4]);
+
+        // RIP-relative should point to the same target (0x1001B).
+        assert_eq!(out[4..7], code[4..7]);
+        assert_eq!(
+            i32::from_le_bytes(out[7..11].try_into().unwrap()),
+            (0x1001B - (dst as isize + 11)) as i32
+        );
+
+        // je rel8 should be converted to je rel32 with the same target (0x1000F).
+        assert_eq!(out[11..13], [0x0F, 0x84]);
+        assert_eq!(
+            i32::from_le_bytes(out[13..17].try_into().unwrap()),
+            (0x1000F - (dst as isize + 17)) as i32
+        );
+
+        // jmp rel8 to itself should be converted to jmp rel32 to the relocated copy since the
+        // original one will be overwritten by the hook.
+        assert_eq!(out[17], 0xE9);
+        assert_eq!(i32::from_le_bytes(out[18..22].try_into().unwrap()), -5);
+
+        // Jump back.
+        assert_eq!(out[22..28], [0xFF, 0x25, 0, 0, 0, 0]);
+        assert_eq!(
+            u64::from_le_bytes(out[28..36].try_into().unwrap()),
+            (src + 15) as u64
+        );
+    }
+
+    #[test]
+    fn build_error() {
+        let mut out = [0; 64];
+
+        // Function is shorter than the jump.
+        let code = [0x31, 0xC0, 0xC3, 0xCC, 0xCC, 0xCC, 0xCC, 0xCC];
+
+        assert_eq!(
+            super::build(&code, 0x1000, 5, &mut out, 0x2000),
+            Err(HookError::TooShort)
+        );
+
+        // loop cannot be relocated.
+        let code = [0xE2, 0xFE, 0x90, 0x90, 0x90];
+
+        assert_eq!(
+            super::build(&code, 0x1000, 5, &mut out, 0x2000),
+            Err(HookError::Unrelocatable(0))
+        );
+
+        // Branch into the middle of the relocated instruction.
+        let code = [0x48, 0x89, 0xE5, 0xEB, 0xFC, 0x90];
+
+        assert_eq!(
+            super::build(&code, 0x1000, 5, &mut out, 0x2000),
+            Err(HookError::Unrelocatable(3))
+        );
+
+        // Indirect jmp end the function.
+        let code = [0xFF, 0x20, 0x90, 0x90, 0x90, 0x90];
+
+        assert_eq!(
+            super::build(&code, 0x1000, 5, &mut out, 0x2000),
+            Err(HookError::TooShort)
+        );
+
+        // RIP-relative target is too far.
+        let code = [0x48, 0x8B, 0x05, 0, 0, 0, 0];
+
+        assert_eq!(
+            super::build(&code, 0x100000000000, 5, &mut out, 0x2000),
+            Err(HookError::Unrelocatable(0))
+        );
+
+        // Trampoline is too small.
+        let code = [0x55, 0x48, 0x89, 0xE5, 0x41, 0x57];
+
+        assert_eq!(
+            super::build(&code, 0x1000, 5, &mut out[..10], 0x2000),
+            Err(HookError::TrampolineTooSmall)
+        );
+    }
+}
@@ -0,0 +1,183 @@
+use crate::Kernel;
+use crate::malloc::{MallocFlags, MallocType};
+use core::ptr::NonNull;
+
+/// Represents `vm_map` structure.
+pub trait VmMap: Sized {}
+
+/// Wired pages that was allocated from `kernel_map` with `kmem_alloc`.
+///
+/// The pages are zero-filled and will be freed with `kmem_free` when dropped. Unlike
+/// [`Contiguous`] the pages may not be physically contiguous.
+pub struct Pages<K: Kernel> {
+    kern: K,
+    addr: NonNull<u8>,
+    len: usize,
+}
+
+impl<K: Kernel> Pages<K> {
+    /// `len` will be rounded up to [`Kernel::PAGE_SIZE`]. Returns [`None`] if `kmem_alloc` failed.
+    ///
+    /// # Panics
+    /// If `len` is zero.
+    pub fn new(kern: K, len: usize) -> Option<Self> {
+        assert_ne!(len, 0);
+
+        let len = len.checked_next_multiple_of(K::PAGE_SIZE)?;
+        let map = unsafe { kern.get(K::KERNEL_MAP).as_mut_ptr().read() };
+        let addr = unsafe { kern.kmem_alloc(map, len) };
+
+        Some(Self {
+            kern,
+            addr: NonNull::new(addr)?,
+            len,
+        })
+    }
+
+    pub fn as_ptr(&self) -> *mut u8 {
+        self.addr.as_ptr()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// Returns physical address of the byte at `off`.
+    ///
+    /// # Panics
+    /// If `off` is out of bound.
+    pub fn phys(&self, off: usize) -> u64 {
+        assert!(off < self.len);
+
+        unsafe {
+            self.kern
+                .pmap_kextract(self.addr.as_ptr().add(off) as usize)
+        }
+    }
+}
+
+impl<K: Kernel> Drop for Pages<K> {
+    fn drop(&mut self) {
+        let map = unsafe { self.kern.get(K::KERNEL_MAP).as_mut_ptr().read() };
+
+        unsafe { self.kern.kmem_free(map, self.addr.as_ptr(), self.len) };
+    }
+}
+
+unsafe impl<K: Kernel + Send> Send for Pages<K> {}
+unsafe impl<K: Kernel + Sync> Sync for Pages<K> {}
+
+/// Physically contiguous memory that was allocated with `contigmalloc`.
+///
+/// The memory will be freed with `contigfree` to the same [`MallocType`] when dropped.
+pub struct Contiguous<K: Kernel> {
+    kern: K,
+    ty: *mut K::Malloc,
+    addr: NonNull<u8>,
+    len: usize,
+}
+
+impl<K: Kernel> Contiguous<K> {
+    /// Returns [`None`] if no physical memory satisfy `range`.
+    ///
+    /// `ty` will be registered if it is not, which may sleep. With [`MallocFlags::NOWAIT`] this
+    /// will return [`None`] instead if `ty` is not registered.
+    ///
+    /// # Panics
+    /// If `len` is zero or `range.alignment` is not a power of two.
+    pub fn new(
+        kern: K,
+        ty: &'static MallocType<K>,
+        len: usize,
+        range: &PhysRange,
+        flags: MallocFlags,
+    ) -> Option<Self> {
+        assert_ne!(len, 0);
+        assert!(range.alignment.is_power_of_two());
+
+        let ty = match flags.contains(MallocFlags::NOWAIT) {
+            true => ty.try_get()?,
+            false => ty.get(),
+        };
+        let addr = unsafe {
+            kern.contigmalloc(
+                len,
+                ty,
+                flags,
+                range.low,
+                range.high,
+                range.alignment,
+                range.boundary,
+            )
+        };
+
+        Some(Self {
+            kern,
+            ty,
+            addr: NonNull::new(addr)?,
+            len,
+        })
+    }
+
+    pub fn as_ptr(&self) -> *mut u8 {
+        self.addr.as_ptr()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// Returns physical address of the first byte.
+    pub fn phys(&self) -> u64 {
+        unsafe { self.kern.pmap_kextract(self.addr.as_ptr() as usize) }
+    }
+}
+
+impl<K: Kernel> Drop for Contiguous<K> {
+    fn drop(&mut self) {
+        unsafe { self.kern.contigfree(self.addr.as_ptr(), self.len, self.ty) };
+    }
+}
+
+unsafe impl<K: Kernel + Send> Send for Contiguous<K> {}
+unsafe impl<K: Kernel + Sync> Sync for Contiguous<K> {}
+
+/// Constraints of the physical address for [`Contiguous`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PhysRange {
+    /// Lowest acceptable physical address.
+    pub low: u64,
+    /// Highest acceptable physical address.
+    pub high: u64,
+    /// Alignment of the physical address. Must be a power of two.
+    pub alignment: usize,
+    /// The memory cannot cross a multiple of this value. Zero means no boundary.
+    pub boundary: u64,
+}
+
+impl Default for PhysRange {
+    fn default() -> Self {
+        Self {
+            low: 0,
+            high: u64::MAX,
+            alignment: 1,
+            boundary: 0,
+        }
+    }
+}
+
+/// Returns physical address of `va` in the kernel address space.
+///
+/// # Safety
+/// `va` must be mapped.
+pub unsafe fn phys<K: Kernel>(kern: K, va: *const u8) -> u64 {
+    unsafe { kern.pmap_kextract(va as usize) }
+}
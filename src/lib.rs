@@ -1,53 +1,81 @@
 #![no_std]
+#![cfg_attr(feature = "allocator-api", feature(allocator_api))]
 
 use self::fd::OpenFlags;
 use self::file::File;
-use self::lock::{LockObject, Mtx};
-use self::malloc::{Malloc, MallocFlags};
+use self::kmem::VmMap;
+use self::lock::{Cv, LockObject, Mtx, RwLock, Sx};
+use self::malloc::{Malloc, MallocFlags, MallocType};
 use self::mount::{Filesystem, FsOps, FsStats, Mount};
 use self::namei::ComponentName;
+use self::pattern::Signature;
 use self::pcpu::Pcpu;
+use self::proc::Proc;
 use self::queue::TailQueue;
 use self::socket::{SockAddr, Socket};
+use self::syscall::Sysent;
 use self::thread::Thread;
 use self::ucred::Ucred;
 use self::uio::{Uio, UioSeg};
-use self::vnode::{Vnode, VnodeOp, VopLookup, VopRead, VopReadDir, VopUnlock, VopVector};
+use self::uma::{UmaCtor, UmaDtor, UmaFini, UmaInit, UmaZone};
+use self::vnode::{
+    Vnode, VnodeOp, VopArgs, VopLock, VopLookup, VopRead, VopReadDir, VopUnlock, VopVector,
+};
+#[cfg(feature = "allocator-api")]
+use core::alloc::AllocError;
 use core::alloc::{GlobalAlloc, Layout};
-use core::ffi::{c_char, c_int};
+use core::ffi::{c_char, c_int, c_void};
 use core::marker::PhantomData;
 use core::mem::transmute;
 use core::num::NonZero;
 use core::ops::Deref;
+#[cfg(feature = "allocator-api")]
+use core::ptr::NonNull;
 use core::ptr::{null_mut, read_unaligned, write_unaligned};
 pub use okf_macros::*;
 
+pub mod cpu;
 pub mod fd;
 pub mod file;
+pub mod firmware;
+pub mod hook;
+pub mod kmem;
 pub mod lock;
 pub mod malloc;
 pub mod mount;
 pub mod namei;
 pub mod notification;
 pub mod panic;
+pub mod patch;
+pub mod pattern;
 pub mod pcpu;
+pub mod proc;
 pub mod queue;
 pub mod socket;
+pub mod syscall;
 pub mod thread;
+pub mod trace;
 pub mod ucred;
 pub mod uio;
+pub mod uma;
 pub mod vnode;
 
 /// Provides methods to access the PS4 kernel for a specific version.
 ///
 /// All methods here are a direct call to the kernel so most of them are unsafe and hard to use.
 /// Some modules may provide high-level wrappers that are easy to use.
+#[kernel_trait]
 pub trait Kernel: MappedKernel {
     const ACCEPT_MTX: StaticMut<Self::Mtx>;
     const EINTR: NonZero<c_int>;
     const EIO: NonZero<c_int>;
+    const ENOSYS: NonZero<c_int>;
+    const EWOULDBLOCK: NonZero<c_int>;
+    const KERNEL_MAP: StaticMut<*mut Self::VmMap>;
     const LK_EXCLUSIVE: c_int;
     const LK_SHARED: c_int;
+    /// `true` if the kernel use the file and line passed to the lock functions (e.g. `WITNESS`).
+    const LOCK_DEBUG: bool;
     const LOOKUP: u64;
     const M_TEMP: StaticMut<Self::Malloc>;
     const MBF_MNTLSTLOCK: c_int;
@@ -55,9 +83,15 @@ pub trait Kernel: MappedKernel {
     const MNT_RDONLY: u64;
     const MOUNTLIST: StaticMut<TailQueue<Self::Mount>>;
     const MOUNTLIST_MTX: StaticMut<Self::Mtx>;
+    const MTX_DEF: c_int;
+    const MTX_SPIN: c_int;
     const NOCPU: u32;
+    const PAGE_SIZE: usize;
     const PANIC: Function<unsafe extern "C" fn(*const c_char, ...) -> !>;
+    const SYSENT: StaticMut<Self::Sysent>;
+    const SYS_MAXSYSCALL: c_int;
     const VDIR: c_int;
+    const VOP_LOCK1: StaticMut<Self::VnodeOp>;
     const VOP_LOOKUP: StaticMut<Self::VnodeOp>;
     const VOP_READ: StaticMut<Self::VnodeOp>;
     const VOP_READDIR: StaticMut<Self::VnodeOp>;
@@ -65,6 +99,7 @@ pub trait Kernel: MappedKernel {
     const VREG: c_int;
 
     type ComponentName: ComponentName<Self>;
+    type Cv: Cv;
     type File: File;
     type Filesystem: Filesystem;
     type FsOps: FsOps<Self>;
@@ -74,24 +109,117 @@ pub trait Kernel: MappedKernel {
     type Mount: Mount<Self>;
     type Mtx: Mtx<Self>;
     type Pcpu: Pcpu<Self>;
+    type Proc: Proc;
+    type RwLock: RwLock<Self>;
     type Socket: Socket;
+    type Sx: Sx<Self>;
+    type Sysent: Sysent<Self>;
     type Thread: Thread<Self>;
     type Ucred: Ucred;
     type Uio: Uio<Self>;
+    type UmaZone: UmaZone;
+    type VmMap: VmMap;
     type Vnode: Vnode<Self>;
     type VnodeOp: VnodeOp;
+    type VopLock: VopLock<Self>;
     type VopLookup: VopLookup<Self>;
     type VopRead: VopRead<Self>;
     type VopReadDir: VopReadDir<Self>;
-    type VopUnlock: VopUnlock;
+    type VopUnlock: VopUnlock<Self>;
     type VopVector: VopVector;
 
     fn get<O: Offset>(self, off: O) -> O::Ops {
-        let addr = unsafe { self.addr().add(off.get()) };
+        let addr = unsafe { self.addr().add(off.get(self.addr())) };
 
         unsafe { <O::Ops as OffsetOps>::new(addr) }
     }
 
+    /// # Safety
+    /// - `addr` must be allocated from [`Kernel::contigmalloc()`] with the same `size` and `ty`.
+    /// - `ty` cannot be null.
+    unsafe fn contigfree(self, addr: *mut u8, size: usize, ty: *mut Self::Malloc);
+
+    /// Returns null if no physical memory satisfy the constraints.
+    ///
+    /// # Safety
+    /// `ty` cannot be null.
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn contigmalloc(
+        self,
+        size: usize,
+        ty: *mut Self::Malloc,
+        flags: MallocFlags,
+        low: u64,
+        high: u64,
+        alignment: usize,
+        boundary: u64,
+    ) -> *mut u8;
+
+    /// Prevents the current thread from being preempted until [`Kernel::critical_exit()`].
+    ///
+    /// # Safety
+    /// Each call must be paired with [`Kernel::critical_exit()`] on the same thread.
+    unsafe fn critical_enter(self);
+
+    /// # Safety
+    /// Must be paired with [`Kernel::critical_enter()`].
+    unsafe fn critical_exit(self);
+
+    /// # Safety
+    /// `cv` must be initialized with [`Kernel::cv_init()`].
+    unsafe fn cv_broadcastpri(self, cv: *mut Self::Cv, pri: c_int);
+
+    /// # Safety
+    /// `cv` must be initialized with [`Kernel::cv_init()`] and has no waiters.
+    unsafe fn cv_destroy(self, cv: *mut Self::Cv);
+
+    /// # Safety
+    /// - `cv` cannot be null and must not be initialized.
+    /// - `desc` cannot be null and must point to a null-terminated string that outlive `cv`.
+    unsafe fn cv_init(self, cv: *mut Self::Cv, desc: *const c_char);
+
+    /// # Safety
+    /// `cv` must be initialized with [`Kernel::cv_init()`].
+    unsafe fn cv_signal(self, cv: *mut Self::Cv);
+
+    /// This is `_cv_timedwait` on the kernel.
+    ///
+    /// # Safety
+    /// - `cv` must be initialized with [`Kernel::cv_init()`].
+    /// - `lock` cannot be null and must be held by the current thread.
+    unsafe fn cv_timedwait(
+        self,
+        cv: *mut Self::Cv,
+        lock: *mut Self::LockObject,
+        timo: c_int,
+    ) -> c_int;
+
+    /// This is `_cv_timedwait_sig` on the kernel.
+    ///
+    /// # Safety
+    /// - `cv` must be initialized with [`Kernel::cv_init()`].
+    /// - `lock` cannot be null and must be held by the current thread.
+    unsafe fn cv_timedwait_sig(
+        self,
+        cv: *mut Self::Cv,
+        lock: *mut Self::LockObject,
+        timo: c_int,
+    ) -> c_int;
+
+    /// This is `_cv_wait` on the kernel.
+    ///
+    /// # Safety
+    /// - `cv` must be initialized with [`Kernel::cv_init()`].
+    /// - `lock` cannot be null and must be held by the current thread.
+    unsafe fn cv_wait(self, cv: *mut Self::Cv, lock: *mut Self::LockObject);
+
+    /// This is `_cv_wait_sig` on the kernel.
+    ///
+    /// # Safety
+    /// - `cv` must be initialized with [`Kernel::cv_init()`].
+    /// - `lock` cannot be null and must be held by the current thread.
+    unsafe fn cv_wait_sig(self, cv: *mut Self::Cv, lock: *mut Self::LockObject) -> c_int;
+
     /// # Safety
     /// `fp` cannot be null.
     unsafe fn fget(
@@ -156,12 +284,48 @@ pub trait Kernel: MappedKernel {
     /// - `auio` cannot be null.
     unsafe fn kern_writev(self, td: *mut Self::Thread, fd: c_int, auio: *mut Self::Uio) -> c_int;
 
+    /// Returns null if failed. The returned memory is zero-filled.
+    ///
+    /// # Safety
+    /// `map` cannot be null.
+    unsafe fn kmem_alloc(self, map: *mut Self::VmMap, size: usize) -> *mut u8;
+
+    /// # Safety
+    /// - `map` cannot be null.
+    /// - `addr` must be allocated from [`Kernel::kmem_alloc()`] with the same `map` and `size`.
+    unsafe fn kmem_free(self, map: *mut Self::VmMap, addr: *mut u8, size: usize);
+
     /// The returned memory guarantee to be 8 byte aligment.
     ///
     /// # Safety
     /// `ty` cannot be null.
     unsafe fn malloc(self, size: usize, ty: *mut Self::Malloc, flags: MallocFlags) -> *mut u8;
 
+    /// # Safety
+    /// `ty` must be initialized with [`Malloc::init()`] and cannot be registered.
+    unsafe fn malloc_init(self, ty: *mut Self::Malloc);
+
+    /// # Safety
+    /// `ty` must be registered with [`Kernel::malloc_init()`] and all memory allocated from it
+    /// must be freed.
+    unsafe fn malloc_uninit(self, ty: *mut Self::Malloc);
+
+    /// # Safety
+    /// `m` must be initialized with [`Kernel::mtx_init()`] and not locked.
+    unsafe fn mtx_destroy(self, m: *mut Self::Mtx);
+
+    /// # Safety
+    /// - `m` cannot be null and must not be initialized.
+    /// - `name` cannot be null and must point to a null-terminated string that outlive `m`.
+    /// - `ty` must be null or point to a null-terminated string that outlive `m`.
+    unsafe fn mtx_init(
+        self,
+        m: *mut Self::Mtx,
+        name: *const c_char,
+        ty: *const c_char,
+        opts: c_int,
+    );
+
     /// # Safety
     /// - `m` cannot be null.
     /// - `file` cannot be null and must point to a null-terminated string.
@@ -173,6 +337,32 @@ pub trait Kernel: MappedKernel {
         line: c_int,
     );
 
+    /// This is `_mtx_lock_spin_flags` on the kernel.
+    ///
+    /// # Safety
+    /// - `m` cannot be null and must be initialized with [`Kernel::MTX_SPIN`].
+    /// - `file` cannot be null and must point to a null-terminated string.
+    unsafe fn mtx_lock_spin_flags(
+        self,
+        m: *mut Self::Mtx,
+        opts: c_int,
+        file: *const c_char,
+        line: c_int,
+    );
+
+    /// This is `_mtx_trylock` on the kernel. Returns non-zero if the mutex was locked.
+    ///
+    /// # Safety
+    /// - `m` cannot be null and must not be initialized with [`Kernel::MTX_SPIN`].
+    /// - `file` cannot be null and must point to a null-terminated string.
+    unsafe fn mtx_trylock_flags(
+        self,
+        m: *mut Self::Mtx,
+        opts: c_int,
+        file: *const c_char,
+        line: c_int,
+    ) -> c_int;
+
     /// # Safety
     /// - `m` cannot be null.
     /// - `file` cannot be null and must point to a null-terminated string.
@@ -184,6 +374,71 @@ pub trait Kernel: MappedKernel {
         line: c_int,
     );
 
+    /// This is `_mtx_unlock_spin_flags` on the kernel.
+    ///
+    /// # Safety
+    /// - `m` cannot be null and must be locked by the current thread.
+    /// - `file` cannot be null and must point to a null-terminated string.
+    unsafe fn mtx_unlock_spin_flags(
+        self,
+        m: *mut Self::Mtx,
+        opts: c_int,
+        file: *const c_char,
+        line: c_int,
+    );
+
+    /// # Safety
+    /// `va` must be mapped.
+    unsafe fn pmap_kextract(self, va: usize) -> u64;
+
+    /// Resizes the memory from [`Kernel::malloc()`] without copying if the current allocation
+    /// has enough room. Returns null without freeing `addr` if failed.
+    ///
+    /// # Safety
+    /// - `addr` must be null or allocated from `ty`.
+    /// - `ty` cannot be null.
+    unsafe fn realloc(
+        self,
+        addr: *mut u8,
+        size: usize,
+        ty: *mut Self::Malloc,
+        flags: MallocFlags,
+    ) -> *mut u8;
+
+    /// # Safety
+    /// - `rw` cannot be null.
+    /// - `file` cannot be null and must point to a null-terminated string.
+    unsafe fn rw_rlock(self, rw: *mut Self::RwLock, file: *const c_char, line: c_int);
+
+    /// # Safety
+    /// - `rw` cannot be null and must be read locked by the current thread.
+    /// - `file` cannot be null and must point to a null-terminated string.
+    unsafe fn rw_runlock(self, rw: *mut Self::RwLock, file: *const c_char, line: c_int);
+
+    /// Returns non-zero if `rw` was read locked.
+    ///
+    /// # Safety
+    /// - `rw` cannot be null.
+    /// - `file` cannot be null and must point to a null-terminated string.
+    unsafe fn rw_try_rlock(self, rw: *mut Self::RwLock, file: *const c_char, line: c_int) -> c_int;
+
+    /// Returns non-zero if `rw` was write locked.
+    ///
+    /// # Safety
+    /// - `rw` cannot be null.
+    /// - `file` cannot be null and must point to a null-terminated string.
+    unsafe fn rw_try_wlock(self, rw: *mut Self::RwLock, file: *const c_char, line: c_int) -> c_int;
+
+    /// # Safety
+    /// - `rw` cannot be null.
+    /// - `file` cannot be null and must point to a null-terminated string.
+    unsafe fn rw_wlock(self, rw: *mut Self::RwLock, file: *const c_char, line: c_int);
+
+    /// # Safety
+    /// - `rw` cannot be null and must be write locked by the current thread.
+    /// - `file` cannot be null and must point to a null-terminated string.
+    unsafe fn rw_wunlock(self, rw: *mut Self::RwLock, file: *const c_char, line: c_int);
+
     /// # Safety
     /// - `ident` cannot be null.
     /// - `wmesg` cannot be null and must point to a null-terminated string.
@@ -196,6 +451,20 @@ pub trait Kernel: MappedKernel {
         timo: c_int,
     ) -> c_int;
 
+    /// Runs `action` on all CPUs at the same time with the interrupts disabled.
+    ///
+    /// All CPUs will wait for each other after `setup` and before `teardown`.
+    ///
+    /// # Safety
+    /// The functions cannot sleep and must be safe to run on all CPUs with `arg`.
+    unsafe fn smp_rendezvous(
+        self,
+        setup: Option<unsafe extern "C" fn(*mut c_void)>,
+        action: Option<unsafe extern "C" fn(*mut c_void)>,
+        teardown: Option<unsafe extern "C" fn(*mut c_void)>,
+        arg: *mut c_void,
+    );
+
     /// # Safety
     /// - `so` cannot be null.
     /// - `nam` cannot be null.
@@ -236,10 +505,116 @@ pub trait Kernel: MappedKernel {
     unsafe fn solisten(self, so: *mut Self::Socket, backlog: c_int, td: *mut Self::Thread)
     -> c_int;
 
+    /// Disables interrupts and preemption on the current CPU until [`Kernel::spinlock_exit()`].
+    ///
+    /// # Safety
+    /// Each call must be paired with [`Kernel::spinlock_exit()`] on the same thread.
+    unsafe fn spinlock_enter(self);
+
+    /// # Safety
+    /// Must be paired with [`Kernel::spinlock_enter()`].
+    unsafe fn spinlock_exit(self);
+
     /// # Safety
     /// `s` cannot be null and must point to a null-terminated string.
     unsafe fn strlen(self, s: *const c_char) -> usize;
 
+    /// # Safety
+    /// `sx` must be initialized with [`Kernel::sx_init()`] and not locked.
+    unsafe fn sx_destroy(self, sx: *mut Self::Sx);
+
+    /// This is `sx_init_flags` on the kernel.
+    ///
+    /// # Safety
+    /// - `sx` cannot be null and must not be initialized.
+    /// - `desc` cannot be null and must point to a null-terminated string that outlive `sx`.
+    unsafe fn sx_init(self, sx: *mut Self::Sx, desc: *const c_char, opts: c_int);
+
+    /// Returns non-zero only when `opts` contains `SX_INTERRUPTIBLE` and the sleep was
+    /// interrupted.
+    ///
+    /// # Safety
+    /// - `sx` cannot be null.
+    /// - `file` cannot be null and must point to a null-terminated string.
+    unsafe fn sx_slock(
+        self,
+        sx: *mut Self::Sx,
+        opts: c_int,
+        file: *const c_char,
+        line: c_int,
+    ) -> c_int;
+
+    /// # Safety
+    /// - `sx` cannot be null and must be shared locked by the current thread.
+    /// - `file` cannot be null and must point to a null-terminated string.
+    unsafe fn sx_sunlock(self, sx: *mut Self::Sx, file: *const c_char, line: c_int);
+
+    /// Returns non-zero if `sx` was shared locked.
+    ///
+    /// # Safety
+    /// - `sx` cannot be null.
+    /// - `file` cannot be null and must point to a null-terminated string.
+    unsafe fn sx_try_slock(self, sx: *mut Self::Sx, file: *const c_char, line: c_int) -> c_int;
+
+    /// Returns non-zero if `sx` was exclusively locked.
+    ///
+    /// # Safety
+    /// - `sx` cannot be null.
+    /// - `file` cannot be null and must point to a null-terminated string.
+    unsafe fn sx_try_xlock(self, sx: *mut Self::Sx, file: *const c_char, line: c_int) -> c_int;
+
+    /// Returns non-zero only when `opts` contains `SX_INTERRUPTIBLE` and the sleep was
+    /// interrupted.
+    ///
+    /// # Safety
+    /// - `sx` cannot be null.
+    /// - `file` cannot be null and must point to a null-terminated string.
+    unsafe fn sx_xlock(
+        self,
+        sx: *mut Self::Sx,
+        opts: c_int,
+        file: *const c_char,
+        line: c_int,
+    ) -> c_int;
+
+    /// # Safety
+    /// - `sx` cannot be null and must be exclusively locked by the current thread.
+    /// - `file` cannot be null and must point to a null-terminated string.
+    unsafe fn sx_xunlock(self, sx: *mut Self::Sx, file: *const c_char, line: c_int);
+
+    /// # Safety
+    /// `name` cannot be null and must point to a null-terminated string that outlive the zone.
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn uma_zcreate(
+        self,
+        name: *const c_char,
+        size: usize,
+        ctor: Option<UmaCtor>,
+        dtor: Option<UmaDtor>,
+        uminit: Option<UmaInit>,
+        fini: Option<UmaFini>,
+        align: c_int,
+        flags: u32,
+    ) -> *mut Self::UmaZone;
+
+    /// # Safety
+    /// `zone` cannot be null and all items must be freed.
+    unsafe fn uma_zdestroy(self, zone: *mut Self::UmaZone);
+
+    /// # Safety
+    /// `zone` cannot be null.
+    unsafe fn uma_zalloc_arg(
+        self,
+        zone: *mut Self::UmaZone,
+        arg: *mut c_void,
+        flags: MallocFlags,
+    ) -> *mut u8;
+
+    /// # Safety
+    /// - `zone` cannot be null.
+    /// - `item` must be allocated from `zone`.
+    unsafe fn uma_zfree_arg(self, zone: *mut Self::UmaZone, item: *mut u8, arg: *mut c_void);
+
     /// # Safety
     /// `mp` cannot be null.
     unsafe fn vfs_busy(self, mp: *mut Self::Mount, flags: c_int) -> c_int;
@@ -248,20 +623,47 @@ pub trait Kernel: MappedKernel {
     /// `mp` cannot be null.
     unsafe fn vfs_unbusy(self, mp: *mut Self::Mount);
 
+    /// This is `_vn_lock` on the kernel.
+    ///
     /// # Safety
-    /// - `vec` cannot be null.
-    /// - `args` cannot be null.
-    unsafe fn vop_lookup(self, vec: *mut Self::VopVector, args: *mut Self::VopLookup) -> c_int;
+    /// - `vp` cannot be null.
+    /// - `file` cannot be null and must point to a null-terminated string.
+    unsafe fn vn_lock(
+        self,
+        vp: *mut Self::Vnode,
+        flags: c_int,
+        file: *const c_char,
+        line: c_int,
+    ) -> c_int;
 
     /// # Safety
     /// - `vec` cannot be null.
     /// - `args` cannot be null.
-    unsafe fn vop_read(self, vec: *mut Self::VopVector, args: *mut Self::VopRead) -> c_int;
+    unsafe fn vop_lock1(self, vec: *mut Self::VopVector, args: *mut Self::VopLock) -> c_int;
 
     /// # Safety
-    /// - `vec` cannot be null.
-    /// - `args` cannot be null.
-    unsafe fn vop_readdir(self, vec: *mut Self::VopVector, args: *mut Self::VopReadDir) -> c_int;
+    /// `vec` cannot be null.
+    unsafe fn vop_lookup(
+        self,
+        vec: *mut Self::VopVector,
+        args: &mut VopArgs<'_, Self, Self::VopLookup>,
+    ) -> c_int;
+
+    /// # Safety
+    /// `vec` cannot be null.
+    unsafe fn vop_read(
+        self,
+        vec: *mut Self::VopVector,
+        args: &mut VopArgs<'_, Self, Self::VopRead>,
+    ) -> c_int;
+
+    /// # Safety
+    /// `vec` cannot be null.
+    unsafe fn vop_readdir(
+        self,
+        vec: *mut Self::VopVector,
+        args: &mut VopArgs<'_, Self, Self::VopReadDir>,
+    ) -> c_int;
 
     /// # Safety
     /// - `vec` cannot be null.
@@ -271,6 +673,18 @@ pub trait Kernel: MappedKernel {
     /// # Safety
     /// `vp` cannot be null and must be locked.
     unsafe fn vput(self, vp: *mut Self::Vnode);
+
+    /// Wakes up all threads sleeping on `ident` with [`Kernel::sleep()`].
+    ///
+    /// # Safety
+    /// `ident` cannot be null.
+    unsafe fn wakeup(self, ident: *mut ());
+
+    /// Wakes up the highest priority thread sleeping on `ident` with [`Kernel::sleep()`].
+    ///
+    /// # Safety
+    /// `ident` cannot be null.
+    unsafe fn wakeup_one(self, ident: *mut ());
 }
 
 /// Mapped PS4 kernel in the memory.
@@ -283,7 +697,11 @@ pub trait MappedKernel: Default + Sized + Copy + Send + Sync + 'static {
 pub trait Offset: Copy {
     type Ops: OffsetOps;
 
-    fn get(self) -> usize;
+    /// Returns the offset from `base`, which is the address of the mapped kernel.
+    ///
+    /// # Panics
+    /// If the offset is a [`Signature`] that does not have a unique match in the kernel.
+    fn get(self, base: *const u8) -> usize;
 }
 
 /// Contains possible operations on an item at the [`Offset`].
@@ -293,12 +711,28 @@ pub trait OffsetOps: Copy {
     unsafe fn new(addr: *const u8) -> Self;
 }
 
+/// Location of an item in the kernel.
+#[derive(Clone, Copy)]
+enum Location {
+    Fixed(usize),
+    Pattern(&'static Signature),
+}
+
+impl Location {
+    fn get(self, base: *const u8) -> usize {
+        match self {
+            Self::Fixed(v) => v,
+            Self::Pattern(s) => unsafe { s.resolve(base) }.expect("no unique match for signature"),
+        }
+    }
+}
+
 /// Offset of an immutable static value in the kernel.
 ///
 /// This immutable is only applied to the value itself at the offset. If the value is a pointer to
 /// mutable data the pointer itself is immutable but the data it point to is mutable.
 pub struct Static<T> {
-    off: usize,
+    loc: Location,
     phantom: PhantomData<T>,
 }
 
@@ -307,7 +741,18 @@ impl<T> Static<T> {
     /// Behavior is undefined if `off` is not valid.
     pub const unsafe fn new(off: usize) -> Self {
         Self {
-            off,
+            loc: Location::Fixed(off),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Create a new [`Static`] that will be located by scanning the kernel text for `sig`.
+    ///
+    /// # Safety
+    /// Behavior is undefined if the item that match with `sig` is not valid.
+    pub const unsafe fn with_signature(sig: &'static Signature) -> Self {
+        Self {
+            loc: Location::Pattern(sig),
             phantom: PhantomData,
         }
     }
@@ -324,8 +769,8 @@ impl<T> Copy for Static<T> {}
 impl<T> Offset for Static<T> {
     type Ops = ImmutableOps<T>;
 
-    fn get(self) -> usize {
-        self.off
+    fn get(self, base: *const u8) -> usize {
+        self.loc.get(base)
     }
 }
 
@@ -359,7 +804,7 @@ impl<T> Deref for ImmutableOps<T> {
 /// This mutable is only applied to the value itself at the offset. If the value is a pointer to
 /// immutable data the pointer itself is mutable but the data it point to is immutable.
 pub struct StaticMut<T> {
-    off: usize,
+    loc: Location,
     phantom: PhantomData<T>,
 }
 
@@ -368,7 +813,18 @@ impl<T> StaticMut<T> {
     /// Behavior is undefined if `off` is not valid.
     pub const unsafe fn new(off: usize) -> Self {
         Self {
-            off,
+            loc: Location::Fixed(off),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Create a new [`StaticMut`] that will be located by scanning the kernel text for `sig`.
+    ///
+    /// # Safety
+    /// Behavior is undefined if the item that match with `sig` is not valid.
+    pub const unsafe fn with_signature(sig: &'static Signature) -> Self {
+        Self {
+            loc: Location::Pattern(sig),
             phantom: PhantomData,
         }
     }
@@ -385,8 +841,8 @@ impl<T> Copy for StaticMut<T> {}
 impl<T> Offset for StaticMut<T> {
     type Ops = MutableOps<T>;
 
-    fn get(self) -> usize {
-        self.off
+    fn get(self, base: *const u8) -> usize {
+        self.loc.get(base)
     }
 }
 
@@ -433,7 +889,7 @@ impl<T> OffsetOps for MutableOps<T> {
 
 /// Offset of a function in the kernel.
 pub struct Function<T: KernelFn> {
-    off: usize,
+    loc: Location,
     phantom: PhantomData<T>,
 }
 
@@ -442,7 +898,18 @@ impl<T: KernelFn> Function<T> {
     /// Behavior is undefined if `off` is not valid.
     pub const unsafe fn new(off: usize) -> Self {
         Self {
-            off,
+            loc: Location::Fixed(off),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Create a new [`Function`] that will be located by scanning the kernel text for `sig`.
+    ///
+    /// # Safety
+    /// Behavior is undefined if the item that match with `sig` is not valid.
+    pub const unsafe fn with_signature(sig: &'static Signature) -> Self {
+        Self {
+            loc: Location::Pattern(sig),
             phantom: PhantomData,
         }
     }
@@ -459,8 +926,8 @@ impl<T: KernelFn> Copy for Function<T> {}
 impl<T: KernelFn> Offset for Function<T> {
     type Ops = FunctionOps<T>;
 
-    fn get(self) -> usize {
-        self.off
+    fn get(self, base: *const u8) -> usize {
+        self.loc.get(base)
     }
 }
 
@@ -495,30 +962,116 @@ impl<T> OffsetOps for FunctionOps<T> {
 }
 
 /// Provides method to cast kernel address into a function pointer.
+///
+/// This was implemented for `unsafe extern "C" fn` with up to 12 arguments, including the variadic
+/// one with at least one fixed argument.
 pub trait KernelFn: Copy {
     /// # Safety
     /// `addr` must be the first instruction of this function.
     unsafe fn from_addr(addr: *const u8) -> Self;
+
+    /// Returns address of this function.
+    fn addr(self) -> *const u8;
 }
 
-impl<R, A1> KernelFn for unsafe extern "C" fn(A1, ...) -> R {
-    unsafe fn from_addr(addr: *const u8) -> Self {
-        unsafe { transmute(addr) }
-    }
+macro_rules! kernel_fn {
+    ($($args:ident),*) => {
+        impl<R, $($args),*> KernelFn for unsafe extern "C" fn($($args),*) -> R {
+            unsafe fn from_addr(addr: *const u8) -> Self {
+                unsafe { transmute(addr) }
+            }
+
+            fn addr(self) -> *const u8 {
+                self as *const u8
+            }
+        }
+
+        kernel_fn!(@variadic $($args),*);
+    };
+    (@variadic) => {};
+    (@variadic $($args:ident),+) => {
+        impl<R, $($args),+> KernelFn for unsafe extern "C" fn($($args),+, ...) -> R {
+            unsafe fn from_addr(addr: *const u8) -> Self {
+                unsafe { transmute(addr) }
+            }
+
+            fn addr(self) -> *const u8 {
+                self as *const u8
+            }
+        }
+    };
 }
 
-/// Implementation of [`GlobalAlloc`] using `malloc` and `free` on `M_TEMP`.
-pub struct Allocator<K: Kernel>(PhantomData<K>);
+kernel_fn!();
+kernel_fn!(A1);
+kernel_fn!(A1, A2);
+kernel_fn!(A1, A2, A3);
+kernel_fn!(A1, A2, A3, A4);
+kernel_fn!(A1, A2, A3, A4, A5);
+kernel_fn!(A1, A2, A3, A4, A5, A6);
+kernel_fn!(A1, A2, A3, A4, A5, A6, A7);
+kernel_fn!(A1, A2, A3, A4, A5, A6, A7, A8);
+kernel_fn!(A1, A2, A3, A4, A5, A6, A7, A8, A9);
+kernel_fn!(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10);
+kernel_fn!(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11);
+kernel_fn!(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12);
+
+/// Implementation of [`GlobalAlloc`] using `malloc` and `free`.
+///
+/// The memory will be allocated from `M_TEMP` unless the type was specified with
+/// [`Allocator::with_type()`]. By default the allocation may sleep, which is not allowed while
+/// holding a spin mutex or in an interrupt context. Use [`Allocator::nowait()`] for those cases.
+pub struct Allocator<K: Kernel + 'static> {
+    ty: Option<&'static MallocType<K>>,
+    flags: MallocFlags,
+}
 
 impl<K: Kernel> Allocator<K> {
     pub const fn new() -> Self {
-        Self(PhantomData)
+        Self {
+            ty: None,
+            flags: MallocFlags::WAITOK,
+        }
+    }
+
+    pub const fn with_type(ty: &'static MallocType<K>) -> Self {
+        Self {
+            ty: Some(ty),
+            flags: MallocFlags::WAITOK,
+        }
+    }
+
+    /// Returns an allocator that never sleep.
+    ///
+    /// The allocation will return null instead of waiting for the memory to be available. The
+    /// memory will be allocated from `M_TEMP` instead if the [`MallocType`] was not registered yet
+    /// since `malloc_init` may sleep.
+    ///
+    /// Enable `allocator-api` feature to use this with a specific container (e.g. `Vec::new_in()`)
+    /// on a nightly compiler. Otherwise this can only be used as a `#[global_allocator]`, which
+    /// makes every allocation non-sleeping.
+    pub const fn nowait(self) -> Self {
+        Self {
+            ty: self.ty,
+            flags: MallocFlags::NOWAIT,
+        }
+    }
+
+    /// Returns `M_TEMP` if the type cannot be registered without sleeping.
+    fn ty(&self, k: K) -> *mut K::Malloc {
+        let ty = match self.ty {
+            Some(v) if self.flags.contains(MallocFlags::NOWAIT) => v.try_get(),
+            Some(v) => Some(v.get()),
+            None => None,
+        };
+
+        ty.unwrap_or_else(|| k.get(K::M_TEMP).as_mut_ptr())
     }
 
     /// # Safety
     /// `layout` must be non-zero.
     #[inline(never)]
-    unsafe fn alloc(layout: Layout, flags: MallocFlags) -> *mut u8 {
+    unsafe fn alloc(&self, layout: Layout, flags: MallocFlags) -> *mut u8 {
         // Calculate allocation size to include a spare room for align adjustment.
         let size = if layout.align() <= 8 {
             layout.size()
@@ -529,16 +1082,16 @@ impl<K: Kernel> Allocator<K> {
             }
         };
 
-        // We will store how many bytes that we have shifted at the end.
-        let size = match size.checked_add(size_of::<usize>()) {
+        // We will store how many bytes that we have shifted and the type at the end.
+        let size = match size.checked_add(size_of::<Trailer<K>>()) {
             Some(v) => v,
             None => return null_mut(),
         };
 
         // Allocate.
         let k = K::default();
-        let t = k.get(K::M_TEMP);
-        let mem = unsafe { k.malloc(size, t.as_mut_ptr(), flags) };
+        let ty = self.ty(k);
+        let mem = unsafe { k.malloc(size, ty, flags) };
 
         if mem.is_null() {
             return null_mut();
@@ -552,13 +1105,31 @@ impl<K: Kernel> Allocator<K> {
             layout.align() - misaligned
         };
 
-        // Store how many bytes have been shifted.
+        // Store how many bytes have been shifted and the type.
         let mem = unsafe { mem.add(adjust) };
 
-        unsafe { write_unaligned(mem.add(layout.size()).cast(), adjust) };
+        unsafe { write_unaligned(mem.add(layout.size()).cast(), Trailer::<K> { adjust, ty }) };
 
         mem
     }
+
+    /// Implementation of [`core::alloc::Allocator`], which allow zero-sized `layout`.
+    #[cfg(feature = "allocator-api")]
+    fn allocate_with(
+        &self,
+        layout: Layout,
+        flags: MallocFlags,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let mem = match layout.size() {
+            0 => core::ptr::without_provenance_mut(layout.align()),
+            _ => unsafe { Allocator::alloc(self, layout, flags) },
+        };
+
+        match NonNull::new(mem) {
+            Some(v) => Ok(NonNull::slice_from_raw_parts(v, layout.size())),
+            None => Err(AllocError),
+        }
+    }
 }
 
 impl<K: Kernel> Default for Allocator<K> {
@@ -567,25 +1138,83 @@ impl<K: Kernel> Default for Allocator<K> {
     }
 }
 
+/// Data at the end of the memory allocated by [`Allocator`].
+#[repr(C)]
+struct Trailer<K: Kernel> {
+    /// Number of bytes that was shifted for the alignment.
+    adjust: usize,
+    /// Type that the memory was allocated from.
+    ty: *mut K::Malloc,
+}
+
 unsafe impl<K: Kernel> GlobalAlloc for Allocator<K> {
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
-        unsafe { Self::alloc(layout, MallocFlags::WAITOK) }
+        unsafe { Allocator::alloc(self, layout, self.flags) }
     }
 
     #[inline(never)]
     unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
         // Get original address before alignment.
-        let adjusted: usize = unsafe { read_unaligned(ptr.add(layout.size()).cast()) };
-        let ptr = unsafe { ptr.sub(adjusted) };
-
-        // Free the memory.
-        let k = K::default();
-        let t = k.get(K::M_TEMP);
+        let t: Trailer<K> = unsafe { read_unaligned(ptr.add(layout.size()).cast()) };
+        let ptr = unsafe { ptr.sub(t.adjust) };
 
-        unsafe { k.free(ptr, t.as_mut_ptr()) };
+        // Free the memory to the same type it was allocated from.
+        unsafe { K::default().free(ptr, t.ty) };
     }
 
     unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
-        unsafe { Self::alloc(layout, MallocFlags::WAITOK | MallocFlags::ZERO) }
+        unsafe { Allocator::alloc(self, layout, self.flags | MallocFlags::ZERO) }
+    }
+
+    #[inline(never)]
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        // The kernel does not know about our align adjustment so we can let it resize the memory
+        // only when there is no adjustment.
+        if layout.align() > 8 {
+            let new = unsafe { Layout::from_size_align_unchecked(new_size, layout.align()) };
+            let mem = unsafe { Allocator::alloc(self, new, self.flags) };
+
+            if !mem.is_null() {
+                unsafe { mem.copy_from_nonoverlapping(ptr, layout.size().min(new_size)) };
+                unsafe { self.dealloc(ptr, layout) };
+            }
+
+            return mem;
+        }
+
+        // Let the kernel decide if the memory need to be moved. The type must be the same as the
+        // original allocation.
+        let size = match new_size.checked_add(size_of::<Trailer<K>>()) {
+            Some(v) => v,
+            None => return null_mut(),
+        };
+
+        let t: Trailer<K> = unsafe { read_unaligned(ptr.add(layout.size()).cast()) };
+        let mem = unsafe { K::default().realloc(ptr, size, t.ty, self.flags) };
+
+        if mem.is_null() {
+            return null_mut();
+        }
+
+        unsafe { write_unaligned(mem.add(new_size).cast(), t) };
+
+        mem
+    }
+}
+
+#[cfg(feature = "allocator-api")]
+unsafe impl<K: Kernel> core::alloc::Allocator for Allocator<K> {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.allocate_with(layout, self.flags)
+    }
+
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.allocate_with(layout, self.flags | MallocFlags::ZERO)
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        if layout.size() != 0 {
+            unsafe { self.dealloc(ptr.as_ptr(), layout) };
+        }
     }
 }
@@ -0,0 +1,185 @@
+use super::{Held, LazyInit, Mtx, MutexGuard, Sx, SxReadGuard, SxWriteGuard};
+use crate::Kernel;
+use core::cell::UnsafeCell;
+use core::ffi::{CStr, c_int};
+use core::mem::MaybeUninit;
+use core::num::NonZero;
+
+/// Represents `cv` structure.
+pub trait Cv: Sized {}
+
+/// Condition variable that owns its `cv`.
+///
+/// The `cv` will be initialized with `cv_init` on the first use and destroyed with `cv_destroy`
+/// when dropped. Keep in mind that a static will never be dropped.
+pub struct Condvar<K: Kernel> {
+    cv: UnsafeCell<MaybeUninit<K::Cv>>,
+    name: &'static CStr,
+    state: LazyInit,
+}
+
+impl<K: Kernel> Condvar<K> {
+    /// `name` will be shown as a wait message of the sleeping threads.
+    pub const fn new(name: &'static CStr) -> Self {
+        Self {
+            cv: UnsafeCell::new(MaybeUninit::uninit()),
+            name,
+            state: LazyInit::new(),
+        }
+    }
+
+    /// Releases the lock of `guard` and sleeps with `cv_wait` until this condition variable is
+    /// signaled. The lock will be reacquired before returning.
+    ///
+    /// Spurious wakeup is possible so the caller must check the condition again.
+    pub fn wait<G: CondvarGuard<K>>(&self, guard: &mut G) {
+        let k = K::default();
+        let lock = guard.lock_object();
+
+        Held::sleep(k, guard.as_raw(), || unsafe {
+            k.cv_wait(self.as_raw(), lock)
+        });
+    }
+
+    /// Same as [`Condvar::wait()`] but the sleep can be interrupted by a signal with
+    /// `cv_wait_sig`.
+    ///
+    /// Returns either [`Kernel::EINTR`] or `ERESTART` if interrupted.
+    pub fn wait_sig<G: CondvarGuard<K>>(&self, guard: &mut G) -> Result<(), NonZero<c_int>> {
+        let k = K::default();
+        let lock = guard.lock_object();
+        let r = Held::sleep(k, guard.as_raw(), || unsafe {
+            k.cv_wait_sig(self.as_raw(), lock)
+        });
+
+        match NonZero::new(r) {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// Same as [`Condvar::wait()`] but give up after `ticks` with `cv_timedwait`.
+    ///
+    /// Returns [`Kernel::EWOULDBLOCK`] if the timeout was elapsed.
+    ///
+    /// # Panics
+    /// If `ticks` is not positive.
+    pub fn wait_timeout<G: CondvarGuard<K>>(
+        &self,
+        guard: &mut G,
+        ticks: c_int,
+    ) -> Result<(), NonZero<c_int>> {
+        assert!(ticks > 0);
+
+        let k = K::default();
+        let lock = guard.lock_object();
+        let r = Held::sleep(k, guard.as_raw(), || unsafe {
+            k.cv_timedwait(self.as_raw(), lock, ticks)
+        });
+
+        match NonZero::new(r) {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// Combination of [`Condvar::wait_sig()`] and [`Condvar::wait_timeout()`] with
+    /// `cv_timedwait_sig`.
+    ///
+    /// # Panics
+    /// If `ticks` is not positive.
+    pub fn wait_timeout_sig<G: CondvarGuard<K>>(
+        &self,
+        guard: &mut G,
+        ticks: c_int,
+    ) -> Result<(), NonZero<c_int>> {
+        assert!(ticks > 0);
+
+        let k = K::default();
+        let lock = guard.lock_object();
+        let r = Held::sleep(k, guard.as_raw(), || unsafe {
+            k.cv_timedwait_sig(self.as_raw(), lock, ticks)
+        });
+
+        match NonZero::new(r) {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// Wakes up one thread with `cv_signal`.
+    pub fn notify_one(&self) {
+        unsafe { K::default().cv_signal(self.as_raw()) };
+    }
+
+    /// Wakes up all threads with `cv_broadcast`.
+    pub fn notify_all(&self) {
+        unsafe { K::default().cv_broadcastpri(self.as_raw(), 0) };
+    }
+
+    /// Returns a pointer to the underlying `cv`, initializing it if it is not.
+    pub fn as_raw(&self) -> *mut K::Cv {
+        let cv = self.cv.get().cast::<K::Cv>();
+
+        self.state
+            .init(|| unsafe { K::default().cv_init(cv, self.name.as_ptr()) });
+
+        cv
+    }
+}
+
+impl<K: Kernel> Drop for Condvar<K> {
+    fn drop(&mut self) {
+        let cv = self.cv.get().cast::<K::Cv>();
+
+        self.state.uninit(|| unsafe { K::default().cv_destroy(cv) });
+    }
+}
+
+unsafe impl<K: Kernel> Send for Condvar<K> {}
+unsafe impl<K: Kernel> Sync for Condvar<K> {}
+
+/// Guard of a lock that can be used with [`Condvar`].
+///
+/// # Safety
+/// [`CondvarGuard::lock_object()`] and [`CondvarGuard::as_raw()`] must return the lock that is
+/// currently held by this guard.
+pub unsafe trait CondvarGuard<K: Kernel> {
+    fn lock_object(&mut self) -> *mut K::LockObject;
+
+    /// Returns a pointer to the lock itself (e.g. [`Mutex::as_raw()`](super::Mutex::as_raw())).
+    ///
+    /// This is used to track the lock that released while waiting when `lock-debug` feature is
+    /// enabled.
+    fn as_raw(&self) -> *const ();
+}
+
+unsafe impl<K: Kernel, T> CondvarGuard<K> for MutexGuard<'_, K, T> {
+    fn lock_object(&mut self) -> *mut K::LockObject {
+        unsafe { (*self.mutex().as_raw()).lock_mut() }
+    }
+
+    fn as_raw(&self) -> *const () {
+        self.mutex().as_raw().cast()
+    }
+}
+
+unsafe impl<K: Kernel, T> CondvarGuard<K> for SxReadGuard<'_, K, T> {
+    fn lock_object(&mut self) -> *mut K::LockObject {
+        unsafe { (*self.lock().as_raw()).lock_mut() }
+    }
+
+    fn as_raw(&self) -> *const () {
+        self.lock().as_raw().cast()
+    }
+}
+
+unsafe impl<K: Kernel, T> CondvarGuard<K> for SxWriteGuard<'_, K, T> {
+    fn lock_object(&mut self) -> *mut K::LockObject {
+        unsafe { (*self.lock().as_raw()).lock_mut() }
+    }
+
+    fn as_raw(&self) -> *const () {
+        self.lock().as_raw().cast()
+    }
+}
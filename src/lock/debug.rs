@@ -0,0 +1,383 @@
+use super::SourceLoc;
+use super::order::{MAX_LOCKS, OrderGraph};
+use crate::Kernel;
+use crate::pcpu::Pcpu;
+use core::arch::x86_64::_rdtsc;
+use core::cell::UnsafeCell;
+use core::fmt::{Display, Formatter};
+use core::hint::spin_loop;
+use core::mem::transmute;
+use core::panic::Location;
+use core::ptr::null_mut;
+use core::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
+
+/// Maximum number of threads that can hold the locks at the same time.
+const MAX_THREADS: usize = 64;
+
+/// Maximum number of locks that a thread can hold at the same time.
+const MAX_HELD: usize = 16;
+
+static STATE: Global = Global::new();
+static HANDLER: AtomicPtr<()> = AtomicPtr::new(null_mut());
+
+/// Returns statistics of `lock` (e.g. value of [`Mutex::as_raw()`](super::Mutex::as_raw())).
+///
+/// Returns [`None`] if `lock` was never acquired through okf or there are too many locks to track.
+pub fn stats<K: Kernel>(k: K, lock: *const ()) -> Option<LockStats> {
+    STATE.with(k, |s| s.graph.find(lock as usize).map(|i| s.stats[i]))
+}
+
+/// Sets a function to be invoked when a lock-order reversal is detected.
+///
+/// The default handler will panic with the [`Reversal`]. The handler is invoked before blocking
+/// on the lock and the thread will continue acquiring the lock after the handler returned.
+///
+/// Locks are identified by their address. Only [`Mutex`](super::Mutex),
+/// [`SpinMutex`](super::SpinMutex) and [`SxLock`](super::SxLock) forget their order when
+/// destroyed so a raw lock that reuse the address of a destroyed one may be reported incorrectly.
+pub fn set_reversal_handler(f: fn(&Reversal)) {
+    HANDLER.store(f as *mut (), Ordering::Release);
+}
+
+/// Statistics of a lock that was acquired through okf.
+///
+/// All durations are in TSC cycles. Reacquiring the lock after waiting on
+/// [`Condvar`](super::Condvar) is counted as a new acquisition without contention and the time
+/// while waiting is not counted as holding the lock.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LockStats {
+    pub acquisitions: u64,
+    /// Number of acquisitions that need to wait for the other owner (e.g. `mtx_trylock` was
+    /// failed). This is always zero for [`SpinMutex`](super::SpinMutex) since the kernel cannot
+    /// try locking a spin mutex.
+    pub contentions: u64,
+    pub total_hold: u64,
+    pub max_hold: u64,
+}
+
+impl LockStats {
+    const fn new() -> Self {
+        Self {
+            acquisitions: 0,
+            contentions: 0,
+            total_hold: 0,
+            max_hold: 0,
+        }
+    }
+}
+
+/// A lock was acquired in the opposite order from the previous acquisitions.
+#[derive(Debug, Clone, Copy)]
+pub struct Reversal {
+    /// The lock that currently held by the thread.
+    pub held: usize,
+    pub held_at: &'static Location<'static>,
+    /// The lock that was previously acquired before [`Reversal::held`].
+    pub acquiring: usize,
+    pub acquiring_at: &'static Location<'static>,
+}
+
+impl Display for Reversal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "lock order reversal: {:#x} acquired at {} while holding {:#x} acquired at {}",
+            self.acquiring, self.acquiring_at, self.held, self.held_at
+        )
+    }
+}
+
+/// Token of a lock that is currently held by the current thread.
+pub(crate) struct Held<K: Kernel> {
+    kern: K,
+    lock: usize,
+    td: usize,
+}
+
+impl<K: Kernel> Held<K> {
+    /// Invokes `try_lock` to acquire `lock` without blocking then `f` if it was failed.
+    ///
+    /// The acquisition will be counted as contended if `try_lock` returns `false`.
+    pub fn acquire(
+        kern: K,
+        lock: *const (),
+        loc: SourceLoc,
+        try_lock: impl FnOnce() -> bool,
+        f: impl FnOnce(),
+    ) -> Self {
+        Self::new(kern, lock, loc, || match try_lock() {
+            true => false,
+            false => {
+                f();
+                true
+            }
+        })
+    }
+
+    /// Invokes `f` to acquire a spin mutex `lock`.
+    ///
+    /// The acquisition will never be counted as contended.
+    pub fn acquire_spin(kern: K, lock: *const (), loc: SourceLoc, f: impl FnOnce()) -> Self {
+        Self::new(kern, lock, loc, || {
+            f();
+            false
+        })
+    }
+
+    /// `f` must returns `true` if the lock was contended.
+    fn new(kern: K, lock: *const (), loc: SourceLoc, f: impl FnOnce() -> bool) -> Self {
+        let td = K::Pcpu::curthread() as usize;
+        let lock = lock as usize;
+
+        // Check the order before blocking so a deadlock still get reported.
+        if let Some(r) = STATE.with(kern, |s| s.check(td, lock, loc.get())) {
+            match HANDLER.load(Ordering::Acquire) {
+                h if h.is_null() => panic!("{r}"),
+                h => unsafe { transmute::<*mut (), fn(&Reversal)>(h)(&r) },
+            }
+        }
+
+        // Acquire.
+        let contended = f();
+        let now = unsafe { _rdtsc() };
+
+        STATE.with(kern, |s| s.acquired(td, lock, loc.get(), contended, now));
+
+        Self { kern, lock, td }
+    }
+
+    /// Invokes `f` that release `lock` and acquire it again before returning (e.g. `cv_wait`).
+    ///
+    /// The time in `f` will not be counted as holding `lock`.
+    pub fn sleep<R>(kern: K, lock: *const (), f: impl FnOnce() -> R) -> R {
+        let td = K::Pcpu::curthread() as usize;
+        let start = unsafe { _rdtsc() };
+        let r = f();
+        let end = unsafe { _rdtsc() };
+
+        STATE.with(kern, |s| s.reacquired(td, lock as usize, start, end));
+
+        r
+    }
+
+    /// Removes all information of `lock` (e.g. when it was destroyed).
+    pub fn forget(kern: K, lock: *const ()) {
+        STATE.with(kern, |s| {
+            if let Some(i) = s.graph.remove(lock as usize) {
+                s.stats[i] = LockStats::new();
+            }
+        });
+    }
+}
+
+impl<K: Kernel> Drop for Held<K> {
+    fn drop(&mut self) {
+        let now = unsafe { _rdtsc() };
+
+        STATE.with(self.kern, |s| s.released(self.td, self.lock, now));
+    }
+}
+
+/// Spin lock to protect [`State`].
+struct Global {
+    locked: AtomicBool,
+    state: UnsafeCell<State>,
+}
+
+impl Global {
+    const fn new() -> Self {
+        Self {
+            locked: AtomicBool::new(false),
+            state: UnsafeCell::new(State {
+                graph: OrderGraph::new(),
+                stats: [LockStats::new(); MAX_LOCKS],
+                threads: [const { Thread::new() }; MAX_THREADS],
+            }),
+        }
+    }
+
+    fn with<K: Kernel, R>(&self, k: K, f: impl FnOnce(&mut State) -> R) -> R {
+        // Interrupts must be disabled since the interrupt handler may acquire a spin mutex.
+        unsafe { k.spinlock_enter() };
+
+        while self
+            .locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            spin_loop();
+        }
+
+        let r = f(unsafe { &mut *self.state.get() });
+
+        self.locked.store(false, Ordering::Release);
+
+        unsafe { k.spinlock_exit() };
+
+        r
+    }
+}
+
+unsafe impl Sync for Global {}
+
+/// Lock-order graph and the locks that currently held by each thread.
+struct State {
+    graph: OrderGraph,
+    stats: [LockStats; MAX_LOCKS],
+    threads: [Thread; MAX_THREADS],
+}
+
+impl State {
+    fn check(
+        &mut self,
+        td: usize,
+        lock: usize,
+        at: &'static Location<'static>,
+    ) -> Option<Reversal> {
+        let to = self.graph.node(lock)?;
+        let t = self.threads.iter().find(|t| t.td == td)?;
+
+        for e in &t.held[..t.len] {
+            let from = match self.graph.find(e.lock) {
+                Some(v) => v,
+                None => continue,
+            };
+
+            if !self.graph.add(from, to) {
+                return Some(Reversal {
+                    held: e.lock,
+                    held_at: e.at.unwrap(),
+                    acquiring: lock,
+                    acquiring_at: at,
+                });
+            }
+        }
+
+        None
+    }
+
+    fn acquired(
+        &mut self,
+        td: usize,
+        lock: usize,
+        at: &'static Location<'static>,
+        contended: bool,
+        now: u64,
+    ) {
+        if let Some(i) = self.graph.node(lock) {
+            let s = &mut self.stats[i];
+
+            s.acquisitions += 1;
+
+            if contended {
+                s.contentions += 1;
+            }
+        }
+
+        // Push to the held list.
+        let t = match self.threads.iter().position(|t| t.td == td) {
+            Some(v) => &mut self.threads[v],
+            None => match self.threads.iter_mut().find(|t| t.td == 0) {
+                Some(v) => v,
+                None => return,
+            },
+        };
+
+        if t.len == MAX_HELD {
+            return;
+        }
+
+        t.td = td;
+        t.held[t.len] = Entry {
+            lock,
+            at: Some(at),
+            since: now,
+        };
+        t.len += 1;
+    }
+
+    fn reacquired(&mut self, td: usize, lock: usize, released: u64, now: u64) {
+        let e = match self
+            .threads
+            .iter_mut()
+            .find(|t| t.td == td)
+            .and_then(|t| t.held[..t.len].iter_mut().rfind(|e| e.lock == lock))
+        {
+            Some(v) => v,
+            None => return,
+        };
+
+        // Keep the entry in the held list since the lock order is still the same.
+        let hold = released.wrapping_sub(e.since);
+
+        e.since = now;
+
+        // Update statistics.
+        if let Some(i) = self.graph.find(lock) {
+            let s = &mut self.stats[i];
+
+            s.acquisitions += 1;
+            s.total_hold += hold;
+            s.max_hold = s.max_hold.max(hold);
+        }
+    }
+
+    fn released(&mut self, td: usize, lock: usize, now: u64) {
+        let t = match self.threads.iter_mut().find(|t| t.td == td) {
+            Some(v) => v,
+            None => return,
+        };
+
+        let i = match t.held[..t.len].iter().rposition(|e| e.lock == lock) {
+            Some(v) => v,
+            None => return,
+        };
+
+        // Remove from the held list.
+        let e = t.held[i];
+
+        t.held.copy_within((i + 1)..t.len, i);
+        t.len -= 1;
+
+        if t.len == 0 {
+            t.td = 0;
+        }
+
+        // Update statistics.
+        if let Some(i) = self.graph.find(lock) {
+            let s = &mut self.stats[i];
+            let hold = now.wrapping_sub(e.since);
+
+            s.total_hold += hold;
+            s.max_hold = s.max_hold.max(hold);
+        }
+    }
+}
+
+/// Locks that currently held by a thread.
+struct Thread {
+    td: usize,
+    len: usize,
+    held: [Entry; MAX_HELD],
+}
+
+impl Thread {
+    const fn new() -> Self {
+        Self {
+            td: 0,
+            len: 0,
+            held: [Entry {
+                lock: 0,
+                at: None,
+                since: 0,
+            }; MAX_HELD],
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Entry {
+    lock: usize,
+    at: Option<&'static Location<'static>>,
+    since: u64,
+}
@@ -0,0 +1,42 @@
+#[cfg(not(feature = "lock-debug"))]
+pub(crate) use self::noop::Held;
+#[cfg(feature = "lock-debug")]
+pub(crate) use super::debug::Held;
+
+#[cfg(not(feature = "lock-debug"))]
+mod noop {
+    use crate::Kernel;
+    use crate::lock::SourceLoc;
+    use core::marker::PhantomData;
+
+    /// No-op version of the lock token when `lock-debug` feature is disabled.
+    pub(crate) struct Held<K: Kernel>(PhantomData<K>);
+
+    impl<K: Kernel> Held<K> {
+        #[inline(always)]
+        pub fn acquire(
+            _: K,
+            _: *const (),
+            _: SourceLoc,
+            _: impl FnOnce() -> bool,
+            f: impl FnOnce(),
+        ) -> Self {
+            f();
+            Self(PhantomData)
+        }
+
+        #[inline(always)]
+        pub fn acquire_spin(_: K, _: *const (), _: SourceLoc, f: impl FnOnce()) -> Self {
+            f();
+            Self(PhantomData)
+        }
+
+        #[inline(always)]
+        pub fn forget(_: K, _: *const ()) {}
+
+        #[inline(always)]
+        pub fn sleep<R>(_: K, _: *const (), f: impl FnOnce() -> R) -> R {
+            f()
+        }
+    }
+}
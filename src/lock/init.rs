@@ -0,0 +1,59 @@
+use core::hint::spin_loop;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+const UNINIT: u8 = 0;
+const BUSY: u8 = 1;
+const READY: u8 = 2;
+
+/// State of a kernel object that is initialized on the first use.
+pub(crate) struct LazyInit(AtomicU8);
+
+impl LazyInit {
+    pub const fn new() -> Self {
+        Self(AtomicU8::new(UNINIT))
+    }
+
+    /// Invokes `f` if the object is not initialized yet.
+    ///
+    /// Other threads will wait until `f` returned.
+    pub fn init(&self, f: impl FnOnce()) {
+        loop {
+            match self
+                .0
+                .compare_exchange_weak(UNINIT, BUSY, Ordering::Acquire, Ordering::Acquire)
+            {
+                Ok(_) => {
+                    f();
+                    self.0.store(READY, Ordering::Release);
+                    break;
+                }
+                Err(READY) => break,
+                Err(_) => spin_loop(),
+            }
+        }
+    }
+
+    /// Returns `true` if the object is initialized.
+    ///
+    /// This never wait for the other thread that currently initializing the object.
+    pub fn is_ready(&self) -> bool {
+        self.0.load(Ordering::Acquire) == READY
+    }
+
+    /// Invokes `f` if the object was initialized.
+    ///
+    /// The object will be initialized again on the next [`LazyInit::init()`].
+    pub fn uninit(&self, f: impl FnOnce()) {
+        if self
+            .0
+            .compare_exchange(READY, BUSY, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            return;
+        }
+
+        f();
+
+        self.0.store(UNINIT, Ordering::Release);
+    }
+}
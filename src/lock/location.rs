@@ -0,0 +1,170 @@
+use crate::Kernel;
+use core::cell::UnsafeCell;
+use core::ffi::{CStr, c_char, c_int};
+use core::hint::spin_loop;
+use core::panic::Location;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+const EMPTY: u8 = 0;
+const BUSY: u8 = 1;
+const READY: u8 = 2;
+
+/// Maximum number of distinct files.
+const FILES: usize = 256;
+
+/// File name to use when [`NAMES`] is full so it is visible on the kernel lock diagnostics.
+const OVERFLOW: &CStr = c"<okf: too many files>";
+
+/// Maximum length of a file name, not including the null terminator.
+const MAX_LEN: usize = 127;
+
+static NAMES: [FileName; FILES] = [const { FileName::new() }; FILES];
+
+/// Source location of a lock operation to forward to the kernel (e.g. `WITNESS`).
+#[derive(Clone, Copy)]
+pub(crate) struct SourceLoc(&'static Location<'static>);
+
+impl SourceLoc {
+    #[track_caller]
+    pub fn caller() -> Self {
+        Self(Location::caller())
+    }
+
+    #[cfg_attr(not(feature = "lock-debug"), allow(dead_code))]
+    pub fn get(self) -> &'static Location<'static> {
+        self.0
+    }
+
+    /// Returns a null-terminated file name that live forever.
+    ///
+    /// Returns an empty string if [`Kernel::LOCK_DEBUG`] is `false` since the kernel never use it.
+    /// See [`intern()`] for the other cases.
+    pub fn file<K: Kernel>(self, k: K) -> *const c_char {
+        if !K::LOCK_DEBUG {
+            return c"".as_ptr();
+        }
+
+        let file = self.0.file();
+
+        if let Some(v) = lookup(file) {
+            return v;
+        }
+
+        // Interrupts must be disabled while filling the slot, otherwise an interrupt handler that
+        // lock something on the same CPU will wait for the slot forever.
+        unsafe { k.spinlock_enter() };
+        let name = intern(file);
+        unsafe { k.spinlock_exit() };
+
+        name
+    }
+
+    pub fn line(self) -> c_int {
+        self.0.line().try_into().unwrap_or(c_int::MAX)
+    }
+}
+
+/// Returns the copy of `file` without blocking if it was already interned.
+fn lookup(file: &'static str) -> Option<*const c_char> {
+    for i in slots(file) {
+        let n = &NAMES[i];
+
+        match n.state.load(Ordering::Acquire) {
+            READY if unsafe { *n.src.get() } == file => return Some(n.buf.get().cast()),
+            READY => continue,
+            _ => return None,
+        }
+    }
+
+    None
+}
+
+/// Returns a null-terminated copy of `file`, copying it to a free slot if it is not interned yet.
+///
+/// The name will be truncated from the beginning if it is too long. Returns [`OVERFLOW`] if there
+/// are too many files.
+fn intern(file: &'static str) -> *const c_char {
+    for i in slots(file) {
+        let n = &NAMES[i];
+
+        loop {
+            match n.state.load(Ordering::Acquire) {
+                EMPTY => {
+                    if n.state
+                        .compare_exchange(EMPTY, BUSY, Ordering::Acquire, Ordering::Relaxed)
+                        .is_err()
+                    {
+                        continue;
+                    }
+
+                    let buf = unsafe { &mut *n.buf.get() };
+                    let src = &file.as_bytes()[file.len().saturating_sub(MAX_LEN)..];
+
+                    unsafe { *n.src.get() = file };
+                    buf[..src.len()].copy_from_slice(src);
+                    buf[src.len()] = 0;
+
+                    n.state.store(READY, Ordering::Release);
+
+                    return buf.as_ptr().cast();
+                }
+                READY if unsafe { *n.src.get() } == file => return n.buf.get().cast(),
+                READY => break,
+                _ => spin_loop(),
+            }
+        }
+    }
+
+    OVERFLOW.as_ptr()
+}
+
+/// Returns the indices of [`NAMES`] to probe for `file`.
+fn slots(file: &str) -> impl Iterator<Item = usize> {
+    // FNV-1a.
+    let hash = file.bytes().fold(0xcbf29ce484222325u64, |h, b| {
+        (h ^ u64::from(b)).wrapping_mul(0x100000001b3)
+    });
+    let start = (hash % FILES as u64) as usize;
+
+    (0..FILES).map(move |i| (start + i) % FILES)
+}
+
+/// Null-terminated copy of a file name from [`Location`].
+struct FileName {
+    state: AtomicU8,
+    src: UnsafeCell<&'static str>,
+    buf: UnsafeCell<[u8; MAX_LEN + 1]>,
+}
+
+impl FileName {
+    const fn new() -> Self {
+        Self {
+            state: AtomicU8::new(EMPTY),
+            src: UnsafeCell::new(""),
+            buf: UnsafeCell::new([0; MAX_LEN + 1]),
+        }
+    }
+}
+
+unsafe impl Sync for FileName {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file() {
+        let a = SourceLoc::caller();
+        let b = SourceLoc::caller();
+        let file = a.get().file();
+
+        assert_eq!(lookup(file), None);
+
+        let name = intern(file);
+
+        assert_eq!(unsafe { CStr::from_ptr(name) }.to_str().unwrap(), file!());
+        assert_eq!(lookup(file), Some(name));
+        assert_eq!(intern(b.get().file()), name);
+        assert_eq!(b.line(), a.line() + 1);
+    }
+}
@@ -1,5 +1,24 @@
+pub use self::condvar::*;
+pub(crate) use self::held::Held;
+pub(crate) use self::init::LazyInit;
+pub(crate) use self::location::SourceLoc;
+pub use self::mutex::*;
+pub use self::rw::*;
+pub use self::sx::*;
 use crate::Kernel;
 
+mod condvar;
+#[cfg(feature = "lock-debug")]
+pub mod debug;
+mod held;
+mod init;
+mod location;
+mod mutex;
+#[cfg(any(test, feature = "lock-debug"))]
+mod order;
+mod rw;
+mod sx;
+
 /// Represents `lock_object` structure.
 pub trait LockObject: Sized {}
 
@@ -9,22 +28,81 @@ pub trait Mtx<K: Kernel>: Sized {
 }
 
 /// RAII struct to unlock a mutex when dropped.
+///
+/// The location of the caller is forwarded to the kernel for both lock and unlock.
 pub struct MtxLock<K: Kernel> {
     kern: K,
     mtx: *mut K::Mtx,
+    loc: SourceLoc,
+    _held: Held<K>, // Must be dropped after the mutex is unlocked.
 }
 
 impl<K: Kernel> MtxLock<K> {
     /// # Safety
     /// `mtx` cannot be null.
+    #[track_caller]
     pub unsafe fn new(kern: K, mtx: *mut K::Mtx) -> Self {
-        unsafe { kern.mtx_lock_flags(mtx, 0, c"".as_ptr(), 0) };
-        Self { kern, mtx }
+        let loc = SourceLoc::caller();
+        let held = Held::acquire(
+            kern,
+            mtx.cast(),
+            loc,
+            || unsafe { kern.mtx_trylock_flags(mtx, 0, loc.file(kern), loc.line()) != 0 },
+            || unsafe { kern.mtx_lock_flags(mtx, 0, loc.file(kern), loc.line()) },
+        );
+
+        Self {
+            kern,
+            mtx,
+            loc,
+            _held: held,
+        }
     }
 }
 
 impl<K: Kernel> Drop for MtxLock<K> {
     fn drop(&mut self) {
-        unsafe { self.kern.mtx_unlock_flags(self.mtx, 0, c"".as_ptr(), 0) };
+        unsafe {
+            self.kern
+                .mtx_unlock_flags(self.mtx, 0, self.loc.file(self.kern), self.loc.line())
+        };
+    }
+}
+
+/// RAII struct to unlock a spin mutex when dropped.
+///
+/// Interrupts are disabled on the current CPU while the mutex is locked.
+pub struct MtxSpinLock<K: Kernel> {
+    kern: K,
+    mtx: *mut K::Mtx,
+    loc: SourceLoc,
+    _held: Held<K>,
+}
+
+impl<K: Kernel> MtxSpinLock<K> {
+    /// # Safety
+    /// `mtx` cannot be null and must be initialized with `MTX_SPIN`.
+    #[track_caller]
+    pub unsafe fn new(kern: K, mtx: *mut K::Mtx) -> Self {
+        let loc = SourceLoc::caller();
+        let held = Held::acquire_spin(kern, mtx.cast(), loc, || unsafe {
+            kern.mtx_lock_spin_flags(mtx, 0, loc.file(kern), loc.line())
+        });
+
+        Self {
+            kern,
+            mtx,
+            loc,
+            _held: held,
+        }
+    }
+}
+
+impl<K: Kernel> Drop for MtxSpinLock<K> {
+    fn drop(&mut self) {
+        unsafe {
+            self.kern
+                .mtx_unlock_spin_flags(self.mtx, 0, self.loc.file(self.kern), self.loc.line())
+        };
     }
 }
@@ -0,0 +1,232 @@
+use super::{Held, LazyInit, MtxLock, MtxSpinLock};
+use crate::Kernel;
+use core::cell::UnsafeCell;
+use core::ffi::CStr;
+use core::mem::{ManuallyDrop, MaybeUninit};
+use core::ops::{Deref, DerefMut};
+use core::ptr::null;
+
+/// Sleep mutex that owns its `mtx` and the data it protects.
+///
+/// The `mtx` will be initialized with `mtx_init` on the first use and destroyed with
+/// `mtx_destroy` when dropped. Keep in mind that a static will never be dropped.
+pub struct Mutex<K: Kernel, T> {
+    mtx: OwnedMtx<K>,
+    data: UnsafeCell<T>,
+}
+
+impl<K: Kernel, T> Mutex<K, T> {
+    /// `name` will be shown on the kernel lock diagnostics (e.g. `WITNESS`).
+    pub const fn new(name: &'static CStr, value: T) -> Self {
+        Self {
+            mtx: OwnedMtx::new(name, false),
+            data: UnsafeCell::new(value),
+        }
+    }
+
+    /// Locks this mutex with `mtx_lock`.
+    ///
+    /// This may sleep so it cannot be called while holding a spin lock.
+    #[track_caller]
+    pub fn lock(&self) -> MutexGuard<'_, K, T> {
+        let k = K::default();
+        let mtx = self.as_raw();
+
+        MutexGuard {
+            mutex: self,
+            _lock: unsafe { MtxLock::new(k, mtx) },
+        }
+    }
+
+    /// Returns a pointer to the underlying `mtx`, initializing it if it is not.
+    pub fn as_raw(&self) -> *mut K::Mtx {
+        self.mtx.get()
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+
+    pub fn into_inner(self) -> T {
+        let mut this = ManuallyDrop::new(self);
+
+        this.mtx.destroy();
+
+        unsafe { this.data.get().read() }
+    }
+}
+
+unsafe impl<K: Kernel, T: Send> Send for Mutex<K, T> {}
+unsafe impl<K: Kernel, T: Send> Sync for Mutex<K, T> {}
+
+/// RAII struct to unlock [`Mutex`] when dropped.
+pub struct MutexGuard<'a, K: Kernel, T> {
+    mutex: &'a Mutex<K, T>,
+    _lock: MtxLock<K>,
+}
+
+impl<'a, K: Kernel, T> MutexGuard<'a, K, T> {
+    /// Returns the [`Mutex`] that was locked by this guard.
+    pub fn mutex(&self) -> &'a Mutex<K, T> {
+        self.mutex
+    }
+}
+
+impl<K: Kernel, T> Deref for MutexGuard<'_, K, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<K: Kernel, T> DerefMut for MutexGuard<'_, K, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.mutex.data.get() }
+    }
+}
+
+unsafe impl<K: Kernel, T: Sync> Sync for MutexGuard<'_, K, T> {}
+
+/// Spin mutex that owns its `mtx` and the data it protects.
+///
+/// Use this instead of [`Mutex`] for the data that shared with interrupt handlers or callouts.
+/// The `mtx` will be initialized with `MTX_SPIN` on the first use with interrupts disabled and
+/// destroyed with `mtx_destroy` when dropped.
+pub struct SpinMutex<K: Kernel, T> {
+    mtx: OwnedMtx<K>,
+    data: UnsafeCell<T>,
+}
+
+impl<K: Kernel, T> SpinMutex<K, T> {
+    /// `name` will be shown on the kernel lock diagnostics (e.g. `WITNESS`).
+    pub const fn new(name: &'static CStr, value: T) -> Self {
+        Self {
+            mtx: OwnedMtx::new(name, true),
+            data: UnsafeCell::new(value),
+        }
+    }
+
+    /// Locks this mutex with `mtx_lock_spin`.
+    ///
+    /// Interrupts are disabled on the current CPU until the returned guard is dropped so the guard
+    /// must be dropped as soon as possible and nothing can sleep while holding it.
+    #[track_caller]
+    pub fn lock(&self) -> SpinMutexGuard<'_, K, T> {
+        let k = K::default();
+        let mtx = self.as_raw();
+
+        SpinMutexGuard {
+            mutex: self,
+            _lock: unsafe { MtxSpinLock::new(k, mtx) },
+        }
+    }
+
+    /// Returns a pointer to the underlying `mtx`, initializing it if it is not.
+    pub fn as_raw(&self) -> *mut K::Mtx {
+        self.mtx.get()
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+
+    pub fn into_inner(self) -> T {
+        let mut this = ManuallyDrop::new(self);
+
+        this.mtx.destroy();
+
+        unsafe { this.data.get().read() }
+    }
+}
+
+unsafe impl<K: Kernel, T: Send> Send for SpinMutex<K, T> {}
+unsafe impl<K: Kernel, T: Send> Sync for SpinMutex<K, T> {}
+
+/// RAII struct to unlock [`SpinMutex`] when dropped.
+pub struct SpinMutexGuard<'a, K: Kernel, T> {
+    mutex: &'a SpinMutex<K, T>,
+    _lock: MtxSpinLock<K>,
+}
+
+impl<K: Kernel, T> Deref for SpinMutexGuard<'_, K, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<K: Kernel, T> DerefMut for SpinMutexGuard<'_, K, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.mutex.data.get() }
+    }
+}
+
+unsafe impl<K: Kernel, T: Sync> Sync for SpinMutexGuard<'_, K, T> {}
+
+/// `mtx` that is initialized on the first use and destroyed when dropped.
+struct OwnedMtx<K: Kernel> {
+    mtx: UnsafeCell<MaybeUninit<K::Mtx>>,
+    name: &'static CStr,
+    spin: bool,
+    state: LazyInit,
+}
+
+impl<K: Kernel> OwnedMtx<K> {
+    const fn new(name: &'static CStr, spin: bool) -> Self {
+        Self {
+            mtx: UnsafeCell::new(MaybeUninit::uninit()),
+            name,
+            spin,
+            state: LazyInit::new(),
+        }
+    }
+
+    fn get(&self) -> *mut K::Mtx {
+        let mtx = self.mtx.get().cast::<K::Mtx>();
+        let opts = match self.spin {
+            true => K::MTX_SPIN,
+            false => K::MTX_DEF,
+        };
+
+        if self.state.is_ready() {
+            return mtx;
+        }
+
+        // Interrupts must be disabled while initializing the spin mutex, otherwise an interrupt
+        // handler that use the same mutex will wait for the initialization on the same CPU forever.
+        let k = K::default();
+
+        if self.spin {
+            unsafe { k.spinlock_enter() };
+        }
+
+        self.state
+            .init(|| unsafe { k.mtx_init(mtx, self.name.as_ptr(), null(), opts) });
+
+        if self.spin {
+            unsafe { k.spinlock_exit() };
+        }
+
+        mtx
+    }
+
+    fn destroy(&mut self) {
+        let mtx = self.mtx.get().cast::<K::Mtx>();
+
+        self.state.uninit(|| {
+            let k = K::default();
+
+            Held::forget(k, mtx.cast());
+
+            unsafe { k.mtx_destroy(mtx) };
+        });
+    }
+}
+
+impl<K: Kernel> Drop for OwnedMtx<K> {
+    fn drop(&mut self) {
+        self.destroy();
+    }
+}
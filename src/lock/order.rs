@@ -0,0 +1,123 @@
+/// Maximum number of locks that can be tracked by [`OrderGraph`].
+pub(crate) const MAX_LOCKS: usize = 128;
+
+/// Directed graph of the order that locks was acquired.
+///
+/// An edge from A to B means B was acquired while holding A. Each lock is identified by its
+/// address.
+pub(crate) struct OrderGraph {
+    locks: [usize; MAX_LOCKS],
+    edges: [u128; MAX_LOCKS],
+}
+
+impl OrderGraph {
+    pub const fn new() -> Self {
+        Self {
+            locks: [0; MAX_LOCKS],
+            edges: [0; MAX_LOCKS],
+        }
+    }
+
+    /// Returns index of `lock`, adding it if it does not exists. Returns [`None`] if the graph is
+    /// full.
+    pub fn node(&mut self, lock: usize) -> Option<usize> {
+        assert_ne!(lock, 0);
+
+        if let Some(i) = self.find(lock) {
+            return Some(i);
+        }
+
+        let i = self.locks.iter().position(|&v| v == 0)?;
+
+        self.locks[i] = lock;
+
+        Some(i)
+    }
+
+    pub fn find(&self, lock: usize) -> Option<usize> {
+        self.locks.iter().position(|&v| v == lock)
+    }
+
+    /// Removes `lock` and all of its edges (e.g. when the lock was destroyed).
+    pub fn remove(&mut self, lock: usize) -> Option<usize> {
+        let i = self.find(lock)?;
+
+        self.locks[i] = 0;
+        self.edges[i] = 0;
+
+        for e in &mut self.edges {
+            *e &= !(1 << i);
+        }
+
+        Some(i)
+    }
+
+    /// Records `to` was acquired while holding `from`.
+    ///
+    /// Returns [`false`] without adding the edge if `from` was previously acquired while holding
+    /// `to`, either directly or through the other locks.
+    pub fn add(&mut self, from: usize, to: usize) -> bool {
+        if from == to {
+            return true;
+        }
+
+        if self.reachable(to, from) {
+            return false;
+        }
+
+        self.edges[from] |= 1 << to;
+
+        true
+    }
+
+    fn reachable(&self, from: usize, to: usize) -> bool {
+        let mut visited = 1u128 << from;
+        let mut pending = self.edges[from];
+
+        while pending != 0 {
+            let i = pending.trailing_zeros() as usize;
+
+            if i == to {
+                return true;
+            }
+
+            pending &= !(1 << i);
+
+            if visited & (1 << i) == 0 {
+                visited |= 1 << i;
+                pending |= self.edges[i] & !visited;
+            }
+        }
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reversal() {
+        let mut g = OrderGraph::new();
+        let a = g.node(0x1000).unwrap();
+        let b = g.node(0x2000).unwrap();
+        let c = g.node(0x3000).unwrap();
+
+        // A -> B -> C.
+        assert!(g.add(a, b));
+        assert!(g.add(b, c));
+        assert!(g.add(a, a));
+        assert_eq!(g.node(0x2000), Some(b));
+
+        // Direct and indirect reversal.
+        assert!(!g.add(b, a));
+        assert!(!g.add(c, a));
+
+        // Destroying B break the indirect order.
+        assert_eq!(g.remove(0x2000), Some(b));
+        assert_eq!(g.find(0x2000), None);
+        assert!(g.add(c, a));
+        assert!(!g.add(a, c));
+    }
+}
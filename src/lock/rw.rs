@@ -0,0 +1,87 @@
+use super::{Held, SourceLoc};
+use crate::Kernel;
+
+/// Represents `rwlock` structure.
+pub trait RwLock<K: Kernel>: Sized {
+    fn lock_mut(&mut self) -> &mut K::LockObject;
+}
+
+/// RAII struct to release a read lock on `rwlock` when dropped.
+pub struct RwReadLock<K: Kernel> {
+    kern: K,
+    rw: *mut K::RwLock,
+    loc: SourceLoc,
+    _held: Held<K>,
+}
+
+impl<K: Kernel> RwReadLock<K> {
+    /// # Safety
+    /// `rw` cannot be null.
+    #[track_caller]
+    pub unsafe fn new(kern: K, rw: *mut K::RwLock) -> Self {
+        let loc = SourceLoc::caller();
+        let held = Held::acquire(
+            kern,
+            rw.cast(),
+            loc,
+            || unsafe { kern.rw_try_rlock(rw, loc.file(kern), loc.line()) != 0 },
+            || unsafe { kern.rw_rlock(rw, loc.file(kern), loc.line()) },
+        );
+
+        Self {
+            kern,
+            rw,
+            loc,
+            _held: held,
+        }
+    }
+}
+
+impl<K: Kernel> Drop for RwReadLock<K> {
+    fn drop(&mut self) {
+        unsafe {
+            self.kern
+                .rw_runlock(self.rw, self.loc.file(self.kern), self.loc.line())
+        };
+    }
+}
+
+/// RAII struct to release a write lock on `rwlock` when dropped.
+pub struct RwWriteLock<K: Kernel> {
+    kern: K,
+    rw: *mut K::RwLock,
+    loc: SourceLoc,
+    _held: Held<K>,
+}
+
+impl<K: Kernel> RwWriteLock<K> {
+    /// # Safety
+    /// `rw` cannot be null.
+    #[track_caller]
+    pub unsafe fn new(kern: K, rw: *mut K::RwLock) -> Self {
+        let loc = SourceLoc::caller();
+        let held = Held::acquire(
+            kern,
+            rw.cast(),
+            loc,
+            || unsafe { kern.rw_try_wlock(rw, loc.file(kern), loc.line()) != 0 },
+            || unsafe { kern.rw_wlock(rw, loc.file(kern), loc.line()) },
+        );
+
+        Self {
+            kern,
+            rw,
+            loc,
+            _held: held,
+        }
+    }
+}
+
+impl<K: Kernel> Drop for RwWriteLock<K> {
+    fn drop(&mut self) {
+        unsafe {
+            self.kern
+                .rw_wunlock(self.rw, self.loc.file(self.kern), self.loc.line())
+        };
+    }
+}
@@ -0,0 +1,238 @@
+use super::{Held, LazyInit, SourceLoc};
+use crate::Kernel;
+use core::cell::UnsafeCell;
+use core::ffi::CStr;
+use core::mem::{ManuallyDrop, MaybeUninit};
+use core::ops::{Deref, DerefMut};
+
+/// Represents `sx` structure.
+pub trait Sx<K: Kernel>: Sized {
+    fn lock_mut(&mut self) -> &mut K::LockObject;
+}
+
+/// RAII struct to release a shared lock on `sx` when dropped.
+pub struct SxReadLock<K: Kernel> {
+    kern: K,
+    sx: *mut K::Sx,
+    loc: SourceLoc,
+    _held: Held<K>,
+}
+
+impl<K: Kernel> SxReadLock<K> {
+    /// # Safety
+    /// `sx` cannot be null.
+    #[track_caller]
+    pub unsafe fn new(kern: K, sx: *mut K::Sx) -> Self {
+        let loc = SourceLoc::caller();
+        let held = Held::acquire(
+            kern,
+            sx.cast(),
+            loc,
+            || unsafe { kern.sx_try_slock(sx, loc.file(kern), loc.line()) != 0 },
+            || unsafe {
+                kern.sx_slock(sx, 0, loc.file(kern), loc.line());
+            },
+        );
+
+        Self {
+            kern,
+            sx,
+            loc,
+            _held: held,
+        }
+    }
+}
+
+impl<K: Kernel> Drop for SxReadLock<K> {
+    fn drop(&mut self) {
+        unsafe {
+            self.kern
+                .sx_sunlock(self.sx, self.loc.file(self.kern), self.loc.line())
+        };
+    }
+}
+
+/// RAII struct to release an exclusive lock on `sx` when dropped.
+pub struct SxWriteLock<K: Kernel> {
+    kern: K,
+    sx: *mut K::Sx,
+    loc: SourceLoc,
+    _held: Held<K>,
+}
+
+impl<K: Kernel> SxWriteLock<K> {
+    /// # Safety
+    /// `sx` cannot be null.
+    #[track_caller]
+    pub unsafe fn new(kern: K, sx: *mut K::Sx) -> Self {
+        let loc = SourceLoc::caller();
+        let held = Held::acquire(
+            kern,
+            sx.cast(),
+            loc,
+            || unsafe { kern.sx_try_xlock(sx, loc.file(kern), loc.line()) != 0 },
+            || unsafe {
+                kern.sx_xlock(sx, 0, loc.file(kern), loc.line());
+            },
+        );
+
+        Self {
+            kern,
+            sx,
+            loc,
+            _held: held,
+        }
+    }
+}
+
+impl<K: Kernel> Drop for SxWriteLock<K> {
+    fn drop(&mut self) {
+        unsafe {
+            self.kern
+                .sx_xunlock(self.sx, self.loc.file(self.kern), self.loc.line())
+        };
+    }
+}
+
+/// Shared/exclusive lock that owns its `sx` and the data it protects.
+///
+/// Unlike [`Mutex`](super::Mutex) the owner can sleep while holding the lock so this is suitable
+/// for the data that need to be locked for a long time. The `sx` will be initialized with
+/// `sx_init` on the first use and destroyed with `sx_destroy` when dropped.
+pub struct SxLock<K: Kernel, T> {
+    sx: UnsafeCell<MaybeUninit<K::Sx>>,
+    name: &'static CStr,
+    state: LazyInit,
+    data: UnsafeCell<T>,
+}
+
+impl<K: Kernel, T> SxLock<K, T> {
+    /// `name` will be shown on the kernel lock diagnostics (e.g. `WITNESS`).
+    pub const fn new(name: &'static CStr, value: T) -> Self {
+        Self {
+            sx: UnsafeCell::new(MaybeUninit::uninit()),
+            name,
+            state: LazyInit::new(),
+            data: UnsafeCell::new(value),
+        }
+    }
+
+    /// Acquires a shared lock with `sx_slock`.
+    #[track_caller]
+    pub fn read(&self) -> SxReadGuard<'_, K, T> {
+        let k = K::default();
+        let sx = self.as_raw();
+
+        SxReadGuard {
+            lock: self,
+            _sx: unsafe { SxReadLock::new(k, sx) },
+        }
+    }
+
+    /// Acquires an exclusive lock with `sx_xlock`.
+    #[track_caller]
+    pub fn write(&self) -> SxWriteGuard<'_, K, T> {
+        let k = K::default();
+        let sx = self.as_raw();
+
+        SxWriteGuard {
+            lock: self,
+            _sx: unsafe { SxWriteLock::new(k, sx) },
+        }
+    }
+
+    /// Returns a pointer to the underlying `sx`, initializing it if it is not.
+    pub fn as_raw(&self) -> *mut K::Sx {
+        let sx = self.sx.get().cast::<K::Sx>();
+
+        self.state
+            .init(|| unsafe { K::default().sx_init(sx, self.name.as_ptr(), 0) });
+
+        sx
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+
+    pub fn into_inner(self) -> T {
+        let mut this = ManuallyDrop::new(self);
+
+        this.destroy();
+
+        unsafe { this.data.get().read() }
+    }
+
+    fn destroy(&mut self) {
+        let sx = self.sx.get().cast::<K::Sx>();
+
+        self.state.uninit(|| {
+            let k = K::default();
+
+            Held::forget(k, sx.cast());
+
+            unsafe { k.sx_destroy(sx) };
+        });
+    }
+}
+
+impl<K: Kernel, T> Drop for SxLock<K, T> {
+    fn drop(&mut self) {
+        self.destroy();
+    }
+}
+
+unsafe impl<K: Kernel, T: Send> Send for SxLock<K, T> {}
+unsafe impl<K: Kernel, T: Send + Sync> Sync for SxLock<K, T> {}
+
+/// RAII struct to release a shared lock on [`SxLock`] when dropped.
+pub struct SxReadGuard<'a, K: Kernel, T> {
+    lock: &'a SxLock<K, T>,
+    _sx: SxReadLock<K>,
+}
+
+impl<'a, K: Kernel, T> SxReadGuard<'a, K, T> {
+    /// Returns the [`SxLock`] that was locked by this guard.
+    pub fn lock(&self) -> &'a SxLock<K, T> {
+        self.lock
+    }
+}
+
+impl<K: Kernel, T> Deref for SxReadGuard<'_, K, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+unsafe impl<K: Kernel, T: Sync> Sync for SxReadGuard<'_, K, T> {}
+
+/// RAII struct to release an exclusive lock on [`SxLock`] when dropped.
+pub struct SxWriteGuard<'a, K: Kernel, T> {
+    lock: &'a SxLock<K, T>,
+    _sx: SxWriteLock<K>,
+}
+
+impl<'a, K: Kernel, T> SxWriteGuard<'a, K, T> {
+    /// Returns the [`SxLock`] that was locked by this guard.
+    pub fn lock(&self) -> &'a SxLock<K, T> {
+        self.lock
+    }
+}
+
+impl<K: Kernel, T> Deref for SxWriteGuard<'_, K, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<K: Kernel, T> DerefMut for SxWriteGuard<'_, K, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+unsafe impl<K: Kernel, T: Sync> Sync for SxWriteGuard<'_, K, T> {}
@@ -1,14 +1,92 @@
+use crate::Kernel;
+use crate::lock::LazyInit;
 use bitflags::bitflags;
-use core::ffi::c_int;
+use core::cell::UnsafeCell;
+use core::ffi::{CStr, c_char, c_int, c_ulong};
+use core::mem::MaybeUninit;
+
+/// Value of `ks_magic` for a valid `malloc_type`.
+pub const M_MAGIC: c_ulong = 877983977;
 
 /// Represents `malloc_type` structure.
-pub trait Malloc: Sized {}
+pub trait Malloc: Sized {
+    /// Initializes a new `malloc_type` with `desc` as its name.
+    ///
+    /// This is the same as the static initializer of `MALLOC_DEFINE`. The type need to be
+    /// registered with [`Kernel::malloc_init()`] before use.
+    ///
+    /// # Safety
+    /// `this` must be valid for writes and `desc` must be a null-terminated string that outlive the
+    /// type.
+    unsafe fn init(this: *mut Self, desc: *const c_char);
+}
+
+/// Our own `malloc_type`, which is the same as `MALLOC_DEFINE`.
+///
+/// The type will be registered with `malloc_init` on the first use, which may sleep. Use
+/// [`MallocType::get()`] to register it before using it with a non-sleeping
+/// [`Allocator`](crate::Allocator). Use [`MallocType::uninit()`] to unregister it before unloading.
+pub struct MallocType<K: Kernel> {
+    ty: UnsafeCell<MaybeUninit<K::Malloc>>,
+    desc: &'static CStr,
+    state: LazyInit,
+}
+
+impl<K: Kernel> MallocType<K> {
+    /// `desc` will be shown on the kernel memory statistics (e.g. `vmstat -m`).
+    pub const fn new(desc: &'static CStr) -> Self {
+        Self {
+            ty: UnsafeCell::new(MaybeUninit::uninit()),
+            desc,
+            state: LazyInit::new(),
+        }
+    }
+
+    /// Returns a pointer to the `malloc_type` after registered it with `malloc_init` if needed.
+    pub fn get(&self) -> *mut K::Malloc {
+        let ty = self.ty.get().cast::<K::Malloc>();
+
+        self.state.init(|| {
+            let k = K::default();
+
+            unsafe { K::Malloc::init(ty, self.desc.as_ptr()) };
+            unsafe { k.malloc_init(ty) };
+        });
+
+        ty
+    }
+
+    /// Returns a pointer to the `malloc_type` only if it was registered.
+    ///
+    /// Unlike [`MallocType::get()`] this never sleep.
+    pub fn try_get(&self) -> Option<*mut K::Malloc> {
+        match self.state.is_ready() {
+            true => Some(self.ty.get().cast()),
+            false => None,
+        }
+    }
+
+    /// Unregisters the type with `malloc_uninit`.
+    ///
+    /// The type will be registered again on the next [`MallocType::get()`].
+    ///
+    /// # Safety
+    /// All memory allocated from this type must be freed.
+    pub unsafe fn uninit(&self) {
+        self.state
+            .uninit(|| unsafe { K::default().malloc_uninit(self.ty.get().cast()) });
+    }
+}
+
+unsafe impl<K: Kernel> Send for MallocType<K> {}
+unsafe impl<K: Kernel> Sync for MallocType<K> {}
 
 bitflags! {
     /// Flags to `malloc`.
     #[repr(transparent)]
     #[derive(Clone, Copy)]
     pub struct MallocFlags: c_int {
+        const NOWAIT = 0x1;
         const WAITOK = 0x2;
         const ZERO = 0x100;
     }
@@ -1,11 +1,16 @@
 use self::msg::Message;
 use crate::Kernel;
+use crate::cpu::enable_write_protect;
 use core::fmt::Write;
 use core::panic::PanicInfo;
 
 mod msg;
 
 pub fn panic<K: Kernel>(k: K, i: &PanicInfo) -> ! {
+    // The guard will not be dropped so we need to restore CR0.WP here otherwise the kernel will
+    // continue running with a writable text.
+    unsafe { enable_write_protect() };
+
     // Write panic message.
     let mut m = Message::default();
     let _ = write!(m, "{i}");
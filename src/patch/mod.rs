@@ -0,0 +1,99 @@
+use self::registry::REGISTRY;
+use crate::cpu::WriteProtectGuard;
+use crate::{Kernel, StaticMut};
+use core::fmt::{Display, Formatter};
+
+pub(crate) use self::registry::Claim;
+
+mod registry;
+
+/// Bytes that was written to the kernel memory.
+///
+/// The original bytes will be restored when dropped.
+pub struct Patch<const N: usize> {
+    addr: *mut [u8; N],
+    original: [u8; N],
+    _claim: Claim,
+}
+
+impl<const N: usize> Patch<N> {
+    /// Writes `new` to `off` if the current bytes is the same as `expected`.
+    ///
+    /// The write will be done with [`WriteProtectGuard`] so `off` can be on a read-only memory.
+    ///
+    /// # Safety
+    /// Writing `new` to `off` must not cause undefined behavior (e.g. other CPUs is running on it).
+    pub unsafe fn apply<K: Kernel>(
+        k: K,
+        off: StaticMut<[u8; N]>,
+        expected: &[u8; N],
+        new: &[u8; N],
+    ) -> Result<Self, PatchError> {
+        let addr = k.get(off).as_mut_ptr();
+        let claim = REGISTRY.claim(addr as usize, N)?;
+        let original = unsafe { addr.read_volatile() };
+
+        if original != *expected {
+            return Err(PatchError::Mismatch);
+        }
+
+        unsafe { write(addr, new) };
+
+        Ok(Self {
+            addr,
+            original,
+            _claim: claim,
+        })
+    }
+
+    /// Returns the bytes before the patch.
+    pub fn original(&self) -> &[u8; N] {
+        &self.original
+    }
+
+    /// Restores the original bytes.
+    ///
+    /// This is the same as dropping the patch.
+    pub fn revert(self) {}
+}
+
+impl<const N: usize> Drop for Patch<N> {
+    fn drop(&mut self) {
+        unsafe { write(self.addr, &self.original) };
+    }
+}
+
+unsafe impl<const N: usize> Send for Patch<N> {}
+unsafe impl<const N: usize> Sync for Patch<N> {}
+
+/// Represents an error when applying [`Patch`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchError {
+    /// The current bytes is not the same as expected.
+    Mismatch,
+    /// The range is overlapped with the other patch.
+    Overlapped,
+    /// Too many active patches.
+    TooManyPatches,
+}
+
+impl Display for PatchError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Mismatch => f.write_str("unexpected bytes at the target"),
+            Self::Overlapped => f.write_str("the target is already patched"),
+            Self::TooManyPatches => f.write_str("too many active patches"),
+        }
+    }
+}
+
+/// Reserves `len` bytes at `addr` for patching until the returned [`Claim`] is dropped.
+pub(crate) fn claim(addr: usize, len: usize) -> Result<Claim, PatchError> {
+    REGISTRY.claim(addr, len)
+}
+
+unsafe fn write<const N: usize>(addr: *mut [u8; N], v: &[u8; N]) {
+    let _wp = unsafe { WriteProtectGuard::new() };
+
+    unsafe { addr.write_volatile(*v) };
+}
@@ -0,0 +1,123 @@
+use super::PatchError;
+use core::cell::UnsafeCell;
+use core::hint::spin_loop;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+pub static REGISTRY: Registry = Registry::new();
+
+/// List of memory ranges that currently patched.
+///
+/// This cannot be used from the interrupt handler since it does not disable the interrupts.
+pub struct Registry {
+    lock: AtomicBool,
+    ranges: UnsafeCell<[(usize, usize); 64]>,
+}
+
+impl Registry {
+    const fn new() -> Self {
+        Self {
+            lock: AtomicBool::new(false),
+            ranges: UnsafeCell::new([(0, 0); 64]),
+        }
+    }
+
+    pub fn claim(&'static self, addr: usize, len: usize) -> Result<Claim, PatchError> {
+        let end = addr.checked_add(len).ok_or(PatchError::Overlapped)?;
+
+        self.with(|ranges| {
+            // Check if overlapped.
+            let mut free = None;
+
+            for (i, &(s, e)) in ranges.iter().enumerate() {
+                if s == e {
+                    free.get_or_insert(i);
+                } else if addr < e && s < end {
+                    return Err(PatchError::Overlapped);
+                }
+            }
+
+            // Reserve.
+            let i = free.ok_or(PatchError::TooManyPatches)?;
+
+            ranges[i] = (addr, end);
+
+            Ok(Claim {
+                registry: self,
+                index: i,
+            })
+        })
+    }
+
+    fn with<R>(&self, f: impl FnOnce(&mut [(usize, usize); 64]) -> R) -> R {
+        while self
+            .lock
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            spin_loop();
+        }
+
+        let r = f(unsafe { &mut *self.ranges.get() });
+
+        self.lock.store(false, Ordering::Release);
+
+        r
+    }
+}
+
+unsafe impl Sync for Registry {}
+
+/// RAII struct to release the range from [`Registry`].
+pub struct Claim {
+    registry: &'static Registry,
+    index: usize,
+}
+
+impl Drop for Claim {
+    fn drop(&mut self) {
+        self.registry.with(|ranges| ranges[self.index] = (0, 0));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn claim() {
+        static REGISTRY: Registry = Registry::new();
+
+        let a = REGISTRY.claim(0x1000, 0x10).unwrap();
+        let b = REGISTRY.claim(0x1010, 0x10).unwrap();
+
+        assert_eq!(
+            REGISTRY.claim(0x100F, 1).err(),
+            Some(PatchError::Overlapped)
+        );
+
+        assert_eq!(
+            REGISTRY.claim(0x0FF0, 0x100).err(),
+            Some(PatchError::Overlapped)
+        );
+
+        // Fill the registry.
+        let mut claims = [const { None }; 62];
+
+        for (i, c) in claims.iter_mut().enumerate() {
+            *c = Some(REGISTRY.claim(0x2000 + i, 1).unwrap());
+        }
+
+        assert_eq!(
+            REGISTRY.claim(0x3000, 1).err(),
+            Some(PatchError::TooManyPatches)
+        );
+
+        // Release.
+        drop(a);
+
+        let _c = REGISTRY.claim(0x1008, 8).unwrap();
+
+        drop(b);
+        drop(claims);
+    }
+}
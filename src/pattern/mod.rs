@@ -0,0 +1,273 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+const UNRESOLVED: usize = usize::MAX;
+const NOT_FOUND: usize = usize::MAX - 1;
+
+/// Byte signature of something in the kernel text with its resolved offset.
+///
+/// Use [`signature`](crate::signature) macro to construct this type from a string (e.g.
+/// `48 89 5C 24 ?? 55`) or `#[pattern]` attribute to declare an item on [`Kernel`](crate::Kernel)
+/// with this type.
+pub struct Signature {
+    pattern: &'static [Option<u8>],
+    rel: Option<usize>,
+    cache: AtomicUsize,
+}
+
+impl Signature {
+    /// If `rel` is [`Some`] the result will be a target of 32-bit relative displacement at `rel`
+    /// from the start of the match instead (e.g. `3` for `lea rax, [rip + X]`).
+    pub const fn new(pattern: &'static [Option<u8>], rel: Option<usize>) -> Self {
+        Self {
+            pattern,
+            rel,
+            cache: AtomicUsize::new(UNRESOLVED),
+        }
+    }
+
+    /// Returns offset of the item that match with this signature in the kernel mapped at `base`.
+    ///
+    /// The scan will be done only once and the result will be cached. Returns [`None`] if the
+    /// signature has no match or have multiple matches.
+    ///
+    /// # Safety
+    /// `base` must be a mapped kernel.
+    pub unsafe fn resolve(&self, base: *const u8) -> Option<usize> {
+        // Check cache.
+        match self.cache.load(Ordering::Relaxed) {
+            UNRESOLVED => {}
+            NOT_FOUND => return None,
+            v => return Some(v),
+        }
+
+        // Scan. Multiple threads may do this at the same time but all of them will get the same
+        // result so it is fine.
+        let off = match unsafe { mapped_text(base) } {
+            Some(t) => self.find(t.data).map(|v| t.offset + v),
+            None => None,
+        };
+
+        self.cache
+            .store(off.unwrap_or(NOT_FOUND), Ordering::Relaxed);
+
+        off
+    }
+
+    /// Returns offset of the item that match with this signature in `text`.
+    ///
+    /// Returns [`None`] if the signature has no match or have multiple matches.
+    pub fn find(&self, text: &[u8]) -> Option<usize> {
+        let mut matches = self.matches(text);
+
+        // Check if the result is unique.
+        let off = matches.next()?;
+
+        if matches.next().is_some() {
+            return None;
+        }
+
+        self.target(text, off)
+    }
+
+    /// Returns an iterator over the offsets in `text` that match with this signature.
+    ///
+    /// Unlike [`Self::find()`] the relative displacement is not followed. Use [`Self::target()`]
+    /// to get the final result from each match.
+    pub fn matches<'a>(&self, text: &'a [u8]) -> Matches<'a> {
+        Matches {
+            pattern: self.pattern,
+            text,
+            next: 0,
+        }
+    }
+
+    /// Returns offset of the item in `text` for a match at `off`.
+    pub fn target(&self, text: &[u8], off: usize) -> Option<usize> {
+        let rel = match self.rel {
+            Some(v) => v,
+            None => return Some(off),
+        };
+
+        let disp = text.get((off + rel)..(off + rel + 4))?;
+        let disp = i32::from_le_bytes(disp.try_into().unwrap());
+
+        (off + rel + 4).checked_add_signed(disp as isize)
+    }
+}
+
+/// Iterator over the offsets of the matches of a [`Signature`].
+pub struct Matches<'a> {
+    pattern: &'static [Option<u8>],
+    text: &'a [u8],
+    next: usize,
+}
+
+impl Iterator for Matches<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let last = self.text.len().checked_sub(self.pattern.len())?;
+
+        while self.next <= last {
+            let off = self.next;
+            let found = self
+                .pattern
+                .iter()
+                .zip(&self.text[off..])
+                .all(|(p, &b)| p.is_none_or(|p| p == b));
+
+            self.next += 1;
+
+            if found {
+                return Some(off);
+            }
+        }
+
+        None
+    }
+}
+
+/// Executable segment of a kernel image.
+pub struct Text<'a> {
+    /// Virtual address of the kernel base when linked.
+    pub base: u64,
+    /// Offset of this segment from the kernel base.
+    pub offset: usize,
+    pub data: &'a [u8],
+}
+
+/// Returns the executable segment of a kernel image (e.g. a kernel dump).
+///
+/// `image` must be the same layout as the kernel in memory, not the kernel ELF file.
+pub fn text(image: &[u8]) -> Option<Text<'_>> {
+    parse_text(|off, len| image.get(off..off.checked_add(len)?))
+}
+
+/// Returns the executable segment of the kernel mapped at `base`.
+///
+/// # Safety
+/// `base` must point to a mapped kernel ELF.
+unsafe fn mapped_text(base: *const u8) -> Option<Text<'static>> {
+    parse_text(|off, len| Some(unsafe { core::slice::from_raw_parts(base.add(off), len) }))
+}
+
+fn parse_text<'a>(read: impl Fn(usize, usize) -> Option<&'a [u8]>) -> Option<Text<'a>> {
+    // Check ELF header.
+    let hdr = read(0, 0x40)?;
+
+    if !hdr.starts_with(b"\x7FELF\x02\x01") {
+        return None;
+    }
+
+    let phoff = u64::from_le_bytes(hdr[0x20..0x28].try_into().unwrap());
+    let phentsize = u16::from_le_bytes(hdr[0x36..0x38].try_into().unwrap());
+    let phnum = u16::from_le_bytes(hdr[0x38..0x3A].try_into().unwrap());
+    let phoff: usize = phoff.try_into().ok()?;
+
+    if phentsize < 0x38 {
+        return None;
+    }
+
+    // Find the executable segment. The first loadable segment is where the base is.
+    let mut base = None;
+
+    for i in 0..usize::from(phnum) {
+        let phdr = read(phoff + i * usize::from(phentsize), 0x38)?;
+        let ty = u32::from_le_bytes(phdr[0x00..0x04].try_into().unwrap());
+        let flags = u32::from_le_bytes(phdr[0x04..0x08].try_into().unwrap());
+        let addr = u64::from_le_bytes(phdr[0x10..0x18].try_into().unwrap());
+        let len = u64::from_le_bytes(phdr[0x28..0x30].try_into().unwrap());
+
+        if ty != 1 {
+            continue;
+        }
+
+        let base = *base.get_or_insert(addr);
+
+        if flags & 1 != 0 {
+            let offset: usize = addr.checked_sub(base)?.try_into().ok()?;
+            let data = read(offset, len.try_into().ok()?)?;
+
+            return Some(Text { base, offset, data });
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find() {
+        let text = [
+            0x90, 0x55, 0x48, 0x89, 0xE5, 0x48, 0x8D, 0x05, 0x10, 0x00, 0x00, 0x00,
+        ];
+
+        // Exact and wildcard.
+        let s = Signature::new(&[Some(0x55), Some(0x48), Some(0x89)], None);
+
+        assert_eq!(s.find(&text), Some(1));
+
+        let s = Signature::new(&[Some(0x48), None, Some(0xE5)], None);
+
+        assert_eq!(s.find(&text), Some(2));
+
+        // Multiple matches.
+        let s = Signature::new(&[Some(0x48)], None);
+
+        assert_eq!(s.find(&text), None);
+
+        // Relative displacement.
+        let s = Signature::new(&[Some(0x48), Some(0x8D), Some(0x05)], Some(3));
+
+        assert_eq!(s.find(&text), Some(0x1C));
+
+        // Displacement is out of bound.
+        let s = Signature::new(&[Some(0x05), Some(0x10)], Some(2));
+
+        assert_eq!(s.find(&text), None);
+
+        // Pattern is longer than the text.
+        let s = Signature::new(&[None; 16], None);
+
+        assert_eq!(s.find(&text), None);
+    }
+
+    #[test]
+    fn resolve() {
+        // Build an ELF with a data segment and a text segment.
+        let mut elf = [0u8; 0x200];
+
+        elf[..6].copy_from_slice(b"\x7FELF\x02\x01");
+        elf[0x20..0x28].copy_from_slice(&0x40u64.to_le_bytes());
+        elf[0x36..0x38].copy_from_slice(&0x38u16.to_le_bytes());
+        elf[0x38..0x3A].copy_from_slice(&2u16.to_le_bytes());
+
+        for (i, (flags, addr, len)) in [(4u32, 0x1000u64, 0x100u64), (5, 0x1100, 0x100)]
+            .into_iter()
+            .enumerate()
+        {
+            let phdr = &mut elf[(0x40 + i * 0x38)..];
+
+            phdr[0x00..0x04].copy_from_slice(&1u32.to_le_bytes());
+            phdr[0x04..0x08].copy_from_slice(&flags.to_le_bytes());
+            phdr[0x10..0x18].copy_from_slice(&addr.to_le_bytes());
+            phdr[0x28..0x30].copy_from_slice(&len.to_le_bytes());
+        }
+
+        // The same bytes in the data segment should be ignored.
+        elf[0xF0..0xF4].copy_from_slice(&[0x55, 0x48, 0x89, 0xE5]);
+        elf[0x180..0x184].copy_from_slice(&[0x55, 0x48, 0x89, 0xE5]);
+
+        let s = Signature::new(&[Some(0x55), Some(0x48), Some(0x89), Some(0xE5)], None);
+
+        assert_eq!(unsafe { s.resolve(elf.as_ptr()) }, Some(0x180));
+
+        // Result should be cached.
+        elf[0x180] = 0;
+
+        assert_eq!(unsafe { s.resolve(elf.as_ptr()) }, Some(0x180));
+    }
+}
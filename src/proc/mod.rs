@@ -0,0 +1,14 @@
+use core::ffi::c_int;
+
+/// Represents `proc` structure.
+///
+/// The methods return [`None`] if the firmware crate does not know where the field is.
+pub trait Proc: Sized {
+    /// Returns value of `p_pid`.
+    fn pid(&self) -> Option<c_int>;
+
+    /// Returns title ID of the application (e.g. `CUSA00001`) that this process belongs to.
+    ///
+    /// The remaining bytes will be zeroes if the title ID is shorter than the buffer.
+    fn title_id(&self) -> Option<&[u8; 10]>;
+}
@@ -0,0 +1,240 @@
+use crate::Kernel;
+use crate::patch::{Claim, PatchError, claim};
+use crate::thread::Thread;
+use crate::trace::syscalls::original;
+use core::ffi::c_int;
+use core::fmt::{Display, Formatter};
+use core::mem::{align_of, size_of};
+use core::num::NonZero;
+use core::ptr::{NonNull, copy_nonoverlapping};
+
+/// Type of `sy_call`.
+pub type SyscallFn<K> = unsafe extern "C" fn(*mut <K as Kernel>::Thread, *mut u8) -> c_int;
+
+/// Represents `sysent` structure.
+///
+/// The kernel may read the entry from the other CPUs at any time so the setters must be atomic.
+pub trait Sysent<K: Kernel>: Sized {
+    /// Returns value of `sy_narg`.
+    fn narg(&self) -> c_int;
+
+    /// Set value of `sy_narg`.
+    ///
+    /// # Safety
+    /// `v` must not be greater than the number of arguments the handler was expected.
+    unsafe fn set_narg(&self, v: c_int);
+
+    /// Returns value of `sy_call`.
+    fn call(&self) -> SyscallFn<K>;
+
+    /// Set value of `sy_call`.
+    ///
+    /// # Safety
+    /// `v` must be able to handle `sy_narg` arguments.
+    unsafe fn set_call(&self, v: SyscallFn<K>);
+}
+
+/// Invokes system call `number` with `args` on behalf of `td` by dispatching through `sysent`
+/// table.
+///
+/// Returns `td_retval[0]` on success. Use [`Thread::ret()`] to get `td_retval[1]` for the system
+/// calls that return two values. Returns [`Kernel::ENOSYS`] without invoking anything if `number`
+/// is `nosys`.
+///
+/// # Safety
+/// - `td` must be the current thread.
+/// - `args` must have the same layout as the arguments of the system call (e.g. `getpid_args`).
+/// - Any pointer in `args` must point to the user memory of the process owning `td` since the
+///   system call will use `copyin` and `copyout` on it.
+pub unsafe fn call<K: Kernel, A>(
+    k: K,
+    td: *mut K::Thread,
+    number: c_int,
+    args: &A,
+) -> Result<usize, NonZero<c_int>> {
+    const {
+        assert!(size_of::<A>() <= 8 * 8, "too many arguments");
+    }
+
+    if number < 0 || number >= K::SYS_MAXSYSCALL {
+        return Err(K::ENOSYS);
+    }
+
+    // The first entry is always nosys. Compare with the original entries since the table may be
+    // wrapped by the trace.
+    let table = k.get(K::SYSENT).as_mut_ptr().cast_const();
+    let entry = unsafe { &*table.add(number as usize) };
+    let nosys = original::<K>(unsafe { &*table }, 0);
+
+    if original::<K>(entry, number as usize) == nosys {
+        return Err(K::ENOSYS);
+    }
+
+    // Copy the arguments to a buffer with the same size as the kernel so the system call can read
+    // all of its arguments.
+    let mut buf = [0usize; 8];
+
+    unsafe {
+        copy_nonoverlapping(
+            (args as *const A).cast::<u8>(),
+            buf.as_mut_ptr().cast(),
+            size_of::<A>(),
+        )
+    };
+
+    // Invoke. We need to set the number the same as the kernel since some wrappers like
+    // trace::syscalls rely on it.
+    let td = unsafe { &mut *td };
+    let code = td.syscall();
+
+    td.set_syscall(number);
+    td.set_ret(0, 0);
+    td.set_ret(1, 0);
+
+    let r = unsafe { entry.call()(td, buf.as_mut_ptr().cast()) };
+
+    if let Some(v) = code {
+        td.set_syscall(v);
+    }
+
+    match r {
+        0 => Ok(td.ret(0)),
+        v => Err(NonZero::new(v).unwrap()),
+    }
+}
+
+/// Custom system call that was installed on `sysent` table.
+///
+/// The original entry will be restored when dropped.
+pub struct Syscall<K: Kernel> {
+    entry: *const K::Sysent,
+    number: c_int,
+    narg: c_int,
+    call: SyscallFn<K>,
+    _claim: Claim,
+}
+
+impl<K: Kernel> Syscall<K> {
+    /// Installs `handler` as a system call `number`.
+    ///
+    /// If `number` is [`None`] the first entry that is `nosys` will be used. The handler receives
+    /// the calling thread and the arguments from the user. Use [`Thread::set_ret()`] to set the
+    /// return values and return [`Err`] with an errno to fail the call.
+    ///
+    /// `handler` must not capture anything. This will be checked at compile time.
+    ///
+    /// # Safety
+    /// - Any bit patterns must be valid for `A` since its value come from the user.
+    /// - The handler must not be running when the returned [`Syscall`] is dropped.
+    ///
+    /// [`Thread::set_ret()`]: crate::thread::Thread::set_ret()
+    pub unsafe fn register<A, F>(
+        k: K,
+        number: Option<c_int>,
+        handler: F,
+    ) -> Result<Self, SyscallError>
+    where
+        F: Fn(&mut K::Thread, &A) -> Result<(), NonZero<c_int>> + Copy,
+    {
+        const {
+            assert!(size_of::<F>() == 0, "the handler cannot capture anything");
+            assert!(size_of::<A>() <= 8 * 8, "too many arguments");
+            assert!(
+                align_of::<A>() <= 8,
+                "alignment of the arguments is too large"
+            );
+        }
+
+        let _ = handler;
+
+        // Find the entry.
+        let table = k.get(K::SYSENT).as_mut_ptr().cast_const();
+        let number = match number {
+            Some(n) if n <= 0 || n >= K::SYS_MAXSYSCALL => return Err(SyscallError::InvalidNumber),
+            Some(n) => n,
+            None => {
+                let nosys = unsafe { (*table).call() as usize };
+
+                (1..K::SYS_MAXSYSCALL)
+                    .find(|&n| unsafe { (*table.add(n as usize)).call() as usize } == nosys)
+                    .ok_or(SyscallError::NoFreeSlot)?
+            }
+        };
+
+        let entry = unsafe { table.add(number as usize) };
+        let claim = claim(entry as usize, size_of::<K::Sysent>()).map_err(SyscallError::Patch)?;
+
+        // Set the number of arguments first so the handler always see all of its arguments.
+        let e = unsafe { &*entry };
+        let narg = e.narg();
+        let call = e.call();
+
+        unsafe { e.set_narg(size_of::<A>().div_ceil(8) as c_int) };
+        unsafe { e.set_call(dispatch::<K, A, F>) };
+
+        Ok(Self {
+            entry,
+            number,
+            narg,
+            call,
+            _claim: claim,
+        })
+    }
+
+    /// Returns the system call number.
+    pub fn number(&self) -> c_int {
+        self.number
+    }
+
+    /// Restores the original entry.
+    ///
+    /// This is the same as dropping the system call.
+    pub fn unregister(self) {}
+}
+
+impl<K: Kernel> Drop for Syscall<K> {
+    fn drop(&mut self) {
+        let e = unsafe { &*self.entry };
+
+        unsafe { e.set_call(self.call) };
+        unsafe { e.set_narg(self.narg) };
+    }
+}
+
+unsafe impl<K: Kernel> Send for Syscall<K> {}
+unsafe impl<K: Kernel> Sync for Syscall<K> {}
+
+/// Represents an error when registering [`Syscall`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyscallError {
+    /// The number is out of range of `sysent` table.
+    InvalidNumber,
+    /// No `nosys` entry available.
+    NoFreeSlot,
+    /// Failed to reserve the entry (e.g. it is already registered).
+    Patch(PatchError),
+}
+
+impl Display for SyscallError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidNumber => f.write_str("invalid system call number"),
+            Self::NoFreeSlot => f.write_str("no free system call number"),
+            Self::Patch(e) => e.fmt(f),
+        }
+    }
+}
+
+unsafe extern "C" fn dispatch<K, A, F>(td: *mut K::Thread, uap: *mut u8) -> c_int
+where
+    K: Kernel,
+    F: Fn(&mut K::Thread, &A) -> Result<(), NonZero<c_int>> + Copy,
+{
+    // SAFETY: F is a zero-sized Copy type so any instance is the same as the one on register.
+    let handler = unsafe { NonNull::<F>::dangling().read() };
+
+    match handler(unsafe { &mut *td }, unsafe { &*uap.cast::<A>() }) {
+        Ok(_) => 0,
+        Err(e) => e.get(),
+    }
+}
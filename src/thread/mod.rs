@@ -1,7 +1,11 @@
 use crate::Kernel;
+use core::ffi::c_int;
 
 /// Represents `thread` structure.
 pub trait Thread<K: Kernel>: Sized {
+    /// Returns value of `td_proc`.
+    fn proc(&self) -> *mut K::Proc;
+
     /// Returns value of `td_ucred`.
     fn cred(&self) -> *mut K::Ucred;
 
@@ -10,4 +14,20 @@ pub trait Thread<K: Kernel>: Sized {
     /// # Panics
     /// If `i` is not `0` or `1`.
     fn ret(&self, i: usize) -> usize;
+
+    /// Set value of `td_retval[i]`.
+    ///
+    /// # Panics
+    /// If `i` is not `0` or `1`.
+    fn set_ret(&mut self, i: usize, v: usize);
+
+    /// Returns value of `td_sa.code`, which is the number of the current system call.
+    ///
+    /// Returns [`None`] if the firmware crate does not know where the field is.
+    fn syscall(&self) -> Option<c_int>;
+
+    /// Set value of `td_sa.code`.
+    ///
+    /// This does nothing if [`Thread::syscall()`] returns [`None`].
+    fn set_syscall(&mut self, v: c_int);
 }
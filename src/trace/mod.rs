@@ -0,0 +1,4 @@
+pub use self::ring::*;
+
+mod ring;
+pub mod syscalls;
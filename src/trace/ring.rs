@@ -0,0 +1,148 @@
+use core::cell::UnsafeCell;
+use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// Lock-free bounded queue that can be written from multiple CPUs at the same time.
+///
+/// [`Ring::push()`] never block. The value will be discarded when the buffer is full.
+pub struct Ring<T, const N: usize> {
+    slots: [Slot<T>; N],
+    head: AtomicUsize,
+    tail: AtomicUsize,
+    dropped: AtomicUsize,
+}
+
+impl<T, const N: usize> Ring<T, N> {
+    pub const fn new() -> Self {
+        const { assert!(N > 0) };
+
+        let mut slots = [const {
+            Slot {
+                seq: AtomicUsize::new(0),
+                value: UnsafeCell::new(MaybeUninit::uninit()),
+            }
+        }; N];
+        let mut i = 0;
+
+        while i < N {
+            slots[i].seq = AtomicUsize::new(i);
+            i += 1;
+        }
+
+        Self {
+            slots,
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            dropped: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns [`false`] if the buffer is full.
+    pub fn push(&self, v: T) -> bool {
+        let mut pos = self.head.load(Ordering::Relaxed);
+
+        loop {
+            // The slot is free when its sequence is the same as the position.
+            let slot = &self.slots[pos % N];
+            let seq = slot.seq.load(Ordering::Acquire);
+
+            match seq.wrapping_sub(pos) as isize {
+                0 => match self.head.compare_exchange_weak(
+                    pos,
+                    pos.wrapping_add(1),
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => {
+                        unsafe { (*slot.value.get()).write(v) };
+                        slot.seq.store(pos.wrapping_add(1), Ordering::Release);
+                        return true;
+                    }
+                    Err(v) => pos = v,
+                },
+                ..0 => {
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                    return false;
+                }
+                _ => pos = self.head.load(Ordering::Relaxed),
+            }
+        }
+    }
+
+    /// Removes the oldest value.
+    pub fn pop(&self) -> Option<T> {
+        let mut pos = self.tail.load(Ordering::Relaxed);
+
+        loop {
+            // The slot is ready when its sequence is one after the position.
+            let slot = &self.slots[pos % N];
+            let seq = slot.seq.load(Ordering::Acquire);
+
+            match seq.wrapping_sub(pos.wrapping_add(1)) as isize {
+                0 => match self.tail.compare_exchange_weak(
+                    pos,
+                    pos.wrapping_add(1),
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => {
+                        let v = unsafe { (*slot.value.get()).assume_init_read() };
+                        slot.seq.store(pos.wrapping_add(N), Ordering::Release);
+                        return Some(v);
+                    }
+                    Err(v) => pos = v,
+                },
+                ..0 => return None,
+                _ => pos = self.tail.load(Ordering::Relaxed),
+            }
+        }
+    }
+
+    /// Returns number of values that was discarded due to the buffer is full.
+    pub fn dropped(&self) -> usize {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl<T, const N: usize> Default for Ring<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> Drop for Ring<T, N> {
+    fn drop(&mut self) {
+        while self.pop().is_some() {}
+    }
+}
+
+unsafe impl<T: Send, const N: usize> Send for Ring<T, N> {}
+unsafe impl<T: Send, const N: usize> Sync for Ring<T, N> {}
+
+struct Slot<T> {
+    seq: AtomicUsize,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push_pop() {
+        let r = Ring::<u32, 2>::new();
+
+        // Fill.
+        assert!(r.push(1));
+        assert!(r.push(2));
+        assert!(!r.push(3));
+        assert_eq!(r.dropped(), 1);
+
+        // Drain.
+        assert_eq!(r.pop(), Some(1));
+        assert!(r.push(4));
+        assert_eq!(r.pop(), Some(2));
+        assert_eq!(r.pop(), Some(4));
+        assert_eq!(r.pop(), None);
+    }
+}
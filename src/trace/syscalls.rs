@@ -0,0 +1,367 @@
+use super::Ring;
+use crate::Kernel;
+use crate::patch::{Claim, PatchError, claim};
+use crate::pcpu::Pcpu;
+use crate::proc::Proc;
+use crate::syscall::{SyscallFn, Sysent};
+use crate::thread::Thread;
+use core::cell::UnsafeCell;
+use core::ffi::c_int;
+use core::fmt::{Display, Formatter};
+use core::hint::spin_loop;
+use core::mem::{size_of, transmute};
+use core::ptr::null_mut;
+use core::sync::atomic::{AtomicI32, AtomicPtr, AtomicUsize, Ordering};
+
+/// Maximum value of [`Kernel::SYS_MAXSYSCALL`] that can be traced.
+const MAX_SYSCALL: usize = 1024;
+
+/// Maximum number of arguments to record.
+const MAX_ARGS: usize = 8;
+
+static ACTIVE: AtomicPtr<Header> = AtomicPtr::new(null_mut());
+static EPOCH: AtomicUsize = AtomicUsize::new(0);
+static RECORDING: AtomicUsize = AtomicUsize::new(0);
+static ORIGINALS: [Original; MAX_SYSCALL] = [const { Original::new() }; MAX_SYSCALL];
+
+/// Buffer of the system calls that was made by the processes that match with [`Filter`].
+///
+/// Only one trace can be active at a time.
+#[repr(C)]
+pub struct SyscallTrace<const N: usize> {
+    header: Header,
+    records: Ring<Record, N>,
+}
+
+impl<const N: usize> SyscallTrace<N> {
+    pub const fn new() -> Self {
+        Self {
+            header: Header {
+                filter: UnsafeCell::new(Filter::Pid(0)),
+                push: Self::push,
+            },
+            records: Ring::new(),
+        }
+    }
+
+    /// Wraps all entries in `sysent` table to record the calls that match with `filter`.
+    ///
+    /// The table cannot be modified by [`Syscall`](crate::syscall::Syscall) while tracing.
+    ///
+    /// # Safety
+    /// The code must never be unloaded once this method succeeded, even after the returned
+    /// [`Tracing`] was dropped. See [`Tracing`] for the reason.
+    pub unsafe fn start<K: Kernel>(
+        &'static self,
+        k: K,
+        filter: Filter,
+    ) -> Result<Tracing<K>, TraceError> {
+        const { assert!(K::SYS_MAXSYSCALL as usize <= MAX_SYSCALL) };
+
+        // Check if the firmware provide all fields we need.
+        let td = unsafe { &*K::Pcpu::curthread() };
+        let p = unsafe { &*td.proc() };
+
+        if td.syscall().is_none() || p.pid().is_none() || p.title_id().is_none() {
+            return Err(TraceError::Unsupported);
+        }
+
+        if !ACTIVE.load(Ordering::SeqCst).is_null() {
+            return Err(TraceError::Busy);
+        }
+
+        // Reserve the whole table. The active trace also hold this claim so the other trace
+        // cannot be started until we release it.
+        let count = K::SYS_MAXSYSCALL as usize;
+        let table = k.get(K::SYSENT).as_mut_ptr().cast_const();
+        let claim = claim(table as usize, size_of::<K::Sysent>() * count)?;
+        let me = (&self.header as *const Header).cast_mut();
+
+        // No wrappers are using this trace at this point since the previous trace was fully
+        // stopped before releasing the claim so we can update the filter before publishing it.
+        unsafe { *self.header.filter.get() = filter };
+
+        // Invalidate the wrappers that was blocked since the previous trace then activate the
+        // trace. The store also release the filter to the wrappers.
+        EPOCH.fetch_add(1, Ordering::SeqCst);
+        ACTIVE.store(me, Ordering::SeqCst);
+
+        // Install the wrappers.
+        for (i, o) in ORIGINALS[..count].iter().enumerate() {
+            let e = unsafe { &*table.add(i) };
+
+            o.call.store(e.call() as usize, Ordering::Relaxed);
+            o.narg.store(e.narg(), Ordering::Relaxed);
+
+            unsafe { e.set_call(traced::<K>) };
+        }
+
+        Ok(Tracing {
+            table,
+            count,
+            _claim: claim,
+        })
+    }
+
+    /// Removes the oldest record.
+    pub fn pop(&self) -> Option<Record> {
+        self.records.pop()
+    }
+
+    /// Returns number of records that was discarded due to the buffer is full.
+    pub fn dropped(&self) -> usize {
+        self.records.dropped()
+    }
+
+    unsafe fn push(h: *const Header, r: Record) {
+        unsafe { (*h.cast::<Self>()).records.push(r) };
+    }
+}
+
+impl<const N: usize> Default for SyscallTrace<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+unsafe impl<const N: usize> Sync for SyscallTrace<N> {}
+
+/// RAII struct to remove the wrappers from `sysent` table when dropped.
+///
+/// Dropping this type will wait only for the wrappers that currently touching the trace, which
+/// never block. The system calls that are blocked inside the original entry (e.g. `nanosleep`)
+/// will return to the wrapper after this type was dropped so the wrapper code must stay resident.
+/// Those wrappers will not record anything into the stopped trace.
+pub struct Tracing<K: Kernel> {
+    table: *const K::Sysent,
+    count: usize,
+    _claim: Claim,
+}
+
+impl<K: Kernel> Drop for Tracing<K> {
+    fn drop(&mut self) {
+        for (i, o) in ORIGINALS[..self.count].iter().enumerate() {
+            let e = unsafe { &*self.table.add(i) };
+            let f: SyscallFn<K> = unsafe { transmute(o.call.load(Ordering::Relaxed)) };
+
+            unsafe { e.set_call(f) };
+        }
+
+        // Stop the trace then wait for the wrappers that may still use it. Sequential consistency
+        // is required here to make sure either the wrapper see the trace was stopped or we see
+        // the wrapper.
+        EPOCH.fetch_add(1, Ordering::SeqCst);
+        ACTIVE.store(null_mut(), Ordering::SeqCst);
+
+        while RECORDING.load(Ordering::SeqCst) != 0 {
+            spin_loop();
+        }
+    }
+}
+
+unsafe impl<K: Kernel> Send for Tracing<K> {}
+unsafe impl<K: Kernel> Sync for Tracing<K> {}
+
+/// Which process to trace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+    Pid(c_int),
+    TitleId(&'static [u8]),
+}
+
+impl Filter {
+    fn matches(&self, p: &impl Proc) -> bool {
+        match *self {
+            Self::Pid(v) => p.pid() == Some(v),
+            Self::TitleId(v) => p.title_id().is_some_and(|t| trim(t) == v),
+        }
+    }
+}
+
+/// A system call that was made by the traced process.
+#[derive(Debug, Clone, Copy)]
+pub struct Record {
+    pub pid: c_int,
+    pub title_id: [u8; 10],
+    pub code: c_int,
+    pub narg: usize,
+    pub args: [usize; MAX_ARGS],
+    pub ret: [usize; 2],
+    /// Zero if the system call was success.
+    pub error: c_int,
+}
+
+impl Display for Record {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let title = trim(&self.title_id);
+
+        write!(f, "[{}", self.pid)?;
+
+        if !title.is_empty() {
+            f.write_str(" ")?;
+
+            for &b in title {
+                write!(f, "{}", b as char)?;
+            }
+        }
+
+        write!(f, "] {}(", self.code)?;
+
+        for (i, v) in self.args[..self.narg].iter().enumerate() {
+            if i != 0 {
+                f.write_str(", ")?;
+            }
+
+            write!(f, "{v:#x}")?;
+        }
+
+        match self.error {
+            0 => write!(f, ") = {:#x}", self.ret[0]),
+            e => write!(f, ") = -1 (errno {e})"),
+        }
+    }
+}
+
+/// Represents an error when starting [`SyscallTrace`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceError {
+    /// The other trace is already active.
+    Busy,
+    /// The firmware crate does not provide the fields required for tracing (e.g. `td_sa.code`).
+    Unsupported,
+    /// Failed to reserve `sysent` table (e.g. a custom system call is registered).
+    Patch(PatchError),
+}
+
+impl From<PatchError> for TraceError {
+    fn from(value: PatchError) -> Self {
+        Self::Patch(value)
+    }
+}
+
+impl Display for TraceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Busy => f.write_str("the other trace is already active"),
+            Self::Unsupported => f.write_str("tracing is not supported on this firmware"),
+            Self::Patch(e) => e.fmt(f),
+        }
+    }
+}
+
+/// Part of [`SyscallTrace`] that does not depend on the size of the buffer.
+struct Header {
+    filter: UnsafeCell<Filter>,
+    push: unsafe fn(*const Self, Record),
+}
+
+/// Original entry of `sysent` table.
+struct Original {
+    call: AtomicUsize,
+    narg: AtomicI32,
+}
+
+impl Original {
+    const fn new() -> Self {
+        Self {
+            call: AtomicUsize::new(0),
+            narg: AtomicI32::new(0),
+        }
+    }
+}
+
+/// RAII struct to mark the current wrapper as using the active trace.
+struct Recording;
+
+impl Recording {
+    fn new() -> Self {
+        RECORDING.fetch_add(1, Ordering::SeqCst);
+        Self
+    }
+}
+
+impl Drop for Recording {
+    fn drop(&mut self) {
+        RECORDING.fetch_sub(1, Ordering::Release);
+    }
+}
+
+/// Returns address of `sy_call` on `e` before it was wrapped by the trace.
+///
+/// `i` is the index of `e` in `sysent` table.
+pub(crate) fn original<K: Kernel>(e: &K::Sysent, i: usize) -> usize {
+    let call = e.call() as usize;
+
+    match call == traced::<K> as SyscallFn<K> as usize {
+        true => ORIGINALS[i].call.load(Ordering::Relaxed),
+        false => call,
+    }
+}
+
+unsafe extern "C" fn traced<K: Kernel>(td: *mut K::Thread, uap: *mut u8) -> c_int {
+    // Get the original entry. The kernel invoke the first entry without changing the number if it
+    // is not valid. The number always available here since it was checked when starting.
+    let code = unsafe { (*td).syscall().unwrap_or(-1) };
+    let o = match code >= 0 && code < K::SYS_MAXSYSCALL {
+        true => &ORIGINALS[code as usize],
+        false => &ORIGINALS[0],
+    };
+    let call: SyscallFn<K> = unsafe { transmute(o.call.load(Ordering::Relaxed)) };
+
+    // Check if we need to record this call. The trace may already stopped if the kernel invoke
+    // this wrapper just before it was removed.
+    let p = unsafe { &*(*td).proc() };
+    let recording = Recording::new();
+    let epoch = EPOCH.load(Ordering::SeqCst);
+    let trace = match unsafe { ACTIVE.load(Ordering::SeqCst).as_ref() } {
+        Some(v) if unsafe { (*v.filter.get()).matches(p) } => v,
+        _ => {
+            drop(recording);
+            return unsafe { call(td, uap) };
+        }
+    };
+
+    // Copy the arguments before the system call modify it.
+    let narg = (o.narg.load(Ordering::Relaxed) as usize).min(MAX_ARGS);
+    let mut args = [0; MAX_ARGS];
+
+    for (i, a) in args[..narg].iter_mut().enumerate() {
+        *a = unsafe { uap.cast::<usize>().add(i).read_unaligned() };
+    }
+
+    // The system call may block for a long time so we need to release the trace while invoking.
+    drop(recording);
+
+    let error = unsafe { call(td, uap) };
+
+    // Make sure the trace still active.
+    let _recording = Recording::new();
+
+    if !core::ptr::eq(ACTIVE.load(Ordering::SeqCst), trace) || EPOCH.load(Ordering::SeqCst) != epoch
+    {
+        return error;
+    }
+
+    unsafe {
+        (trace.push)(
+            trace,
+            Record {
+                pid: p.pid().unwrap_or(-1),
+                title_id: p.title_id().copied().unwrap_or_default(),
+                code,
+                narg,
+                args,
+                ret: [(*td).ret(0), (*td).ret(1)],
+                error,
+            },
+        )
+    };
+
+    error
+}
+
+fn trim(v: &[u8]) -> &[u8] {
+    let len = v.iter().position(|&b| b == 0).unwrap_or(v.len());
+
+    &v[..len]
+}
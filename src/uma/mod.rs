@@ -0,0 +1,217 @@
+use crate::Kernel;
+use crate::malloc::MallocFlags;
+use core::ffi::{CStr, c_int, c_void};
+use core::marker::PhantomData;
+use core::mem::{MaybeUninit, align_of, size_of};
+use core::num::NonZero;
+use core::ops::{Deref, DerefMut};
+use core::ptr::{NonNull, drop_in_place, null_mut};
+
+/// Type of `uma_ctor`.
+pub type UmaCtor = unsafe extern "C" fn(*mut c_void, c_int, *mut c_void, c_int) -> c_int;
+
+/// Type of `uma_dtor`.
+pub type UmaDtor = unsafe extern "C" fn(*mut c_void, c_int, *mut c_void);
+
+/// Type of `uma_init`.
+pub type UmaInit = unsafe extern "C" fn(*mut c_void, c_int, c_int) -> c_int;
+
+/// Type of `uma_fini`.
+pub type UmaFini = unsafe extern "C" fn(*mut c_void, c_int);
+
+/// Represents `uma_zone` structure.
+pub trait UmaZone: Sized {}
+
+/// UMA zone for `T`.
+///
+/// The zone will be destroyed with `uma_zdestroy` when dropped.
+pub struct Zone<K: Kernel, T> {
+    kern: K,
+    zone: *mut K::UmaZone,
+    callbacks: bool,
+    phantom: PhantomData<T>,
+}
+
+impl<K: Kernel, T> Zone<K, T> {
+    /// Creates a zone without constructor and destructor.
+    ///
+    /// Use [`Zone::alloc()`] to allocate an item. The item will be dropped when [`ZoneBox`] is
+    /// dropped. Returns [`None`] if `uma_zcreate` failed.
+    pub fn new(kern: K, name: &'static CStr) -> Option<Self> {
+        unsafe { Self::create(kern, name, None, None) }
+    }
+
+    /// Creates a zone with `ctor` and `dtor` as `uma_ctor` and `uma_dtor`.
+    ///
+    /// `ctor` must initialize the item if it return [`Ok`]. Use [`Zone::construct()`] to allocate
+    /// an item that was initialized by `ctor`. `dtor` will be invoked before the item is dropped.
+    ///
+    /// Both `ctor` and `dtor` must not capture anything. This will be checked at compile time.
+    pub fn with_callbacks<C, D>(kern: K, name: &'static CStr, ctor: C, dtor: D) -> Option<Self>
+    where
+        C: Fn(&mut MaybeUninit<T>) -> Result<(), NonZero<c_int>> + Copy,
+        D: Fn(&mut T) + Copy,
+    {
+        const {
+            assert!(
+                size_of::<C>() == 0,
+                "the constructor cannot capture anything"
+            );
+            assert!(
+                size_of::<D>() == 0,
+                "the destructor cannot capture anything"
+            );
+        }
+
+        let _ = (ctor, dtor);
+        let ctor: UmaCtor = construct::<T, C>;
+        let dtor: UmaDtor = destruct::<T, D>;
+
+        unsafe { Self::create(kern, name, Some(ctor), Some(dtor)) }
+    }
+
+    /// Allocates an item with `value`.
+    ///
+    /// If the zone has a constructor the item that was initialized by it will be replaced with
+    /// `value`. Returns [`None`] if `uma_zalloc` failed.
+    pub fn alloc(&self, value: T, flags: MallocFlags) -> Option<ZoneBox<'_, K, T>> {
+        let item = self.zalloc(flags)?;
+
+        if self.callbacks {
+            unsafe { *item.as_ptr() = value };
+        } else {
+            unsafe { item.write(value) };
+        }
+
+        Some(ZoneBox { zone: self, item })
+    }
+
+    /// Allocates an item that was initialized by the constructor.
+    ///
+    /// Returns [`None`] if `uma_zalloc` or the constructor failed.
+    ///
+    /// # Panics
+    /// If the zone was not created with [`Zone::with_callbacks()`].
+    pub fn construct(&self, flags: MallocFlags) -> Option<ZoneBox<'_, K, T>> {
+        assert!(self.callbacks, "the zone has no constructor");
+
+        let item = self.zalloc(flags)?;
+
+        Some(ZoneBox { zone: self, item })
+    }
+
+    unsafe fn create(
+        kern: K,
+        name: &'static CStr,
+        ctor: Option<UmaCtor>,
+        dtor: Option<UmaDtor>,
+    ) -> Option<Self> {
+        const { assert!(size_of::<T>() != 0, "zero-sized type is not supported") };
+
+        let zone = unsafe {
+            kern.uma_zcreate(
+                name.as_ptr(),
+                size_of::<T>(),
+                ctor,
+                dtor,
+                None,
+                None,
+                (align_of::<T>() - 1) as c_int,
+                0,
+            )
+        };
+
+        if zone.is_null() {
+            return None;
+        }
+
+        Some(Self {
+            kern,
+            zone,
+            callbacks: ctor.is_some(),
+            phantom: PhantomData,
+        })
+    }
+
+    fn zalloc(&self, flags: MallocFlags) -> Option<NonNull<T>> {
+        let item = unsafe { self.kern.uma_zalloc_arg(self.zone, null_mut(), flags) };
+
+        NonNull::new(item.cast())
+    }
+}
+
+impl<K: Kernel, T> Drop for Zone<K, T> {
+    fn drop(&mut self) {
+        unsafe { self.kern.uma_zdestroy(self.zone) };
+    }
+}
+
+unsafe impl<K: Kernel + Send, T: Send> Send for Zone<K, T> {}
+unsafe impl<K: Kernel + Sync, T: Send> Sync for Zone<K, T> {}
+
+/// Item that was allocated from [`Zone`].
+///
+/// The item will be returned to the zone when dropped.
+pub struct ZoneBox<'a, K: Kernel, T> {
+    zone: &'a Zone<K, T>,
+    item: NonNull<T>,
+}
+
+impl<K: Kernel, T> Deref for ZoneBox<'_, K, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { self.item.as_ref() }
+    }
+}
+
+impl<K: Kernel, T> DerefMut for ZoneBox<'_, K, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { self.item.as_mut() }
+    }
+}
+
+impl<K: Kernel, T> Drop for ZoneBox<'_, K, T> {
+    fn drop(&mut self) {
+        let z = self.zone;
+
+        // The destructor will drop the item for us.
+        if !z.callbacks {
+            unsafe { drop_in_place(self.item.as_ptr()) };
+        }
+
+        unsafe {
+            z.kern
+                .uma_zfree_arg(z.zone, self.item.as_ptr().cast(), null_mut())
+        };
+    }
+}
+
+unsafe impl<K: Kernel, T: Send> Send for ZoneBox<'_, K, T> {}
+unsafe impl<K: Kernel, T: Sync> Sync for ZoneBox<'_, K, T> {}
+
+unsafe extern "C" fn construct<T, C>(mem: *mut c_void, _: c_int, _: *mut c_void, _: c_int) -> c_int
+where
+    C: Fn(&mut MaybeUninit<T>) -> Result<(), NonZero<c_int>> + Copy,
+{
+    // SAFETY: C is a zero-sized Copy type so any instance is the same as the one on creation.
+    let ctor = unsafe { NonNull::<C>::dangling().read() };
+
+    match ctor(unsafe { &mut *mem.cast() }) {
+        Ok(_) => 0,
+        Err(e) => e.get(),
+    }
+}
+
+unsafe extern "C" fn destruct<T, D>(mem: *mut c_void, _: c_int, _: *mut c_void)
+where
+    D: Fn(&mut T) + Copy,
+{
+    // SAFETY: D is a zero-sized Copy type so any instance is the same as the one on creation.
+    let dtor = unsafe { NonNull::<D>::dangling().read() };
+    let item = mem.cast::<T>();
+
+    dtor(unsafe { &mut *item });
+
+    unsafe { drop_in_place(item) };
+}
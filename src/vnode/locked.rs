@@ -0,0 +1,71 @@
+use super::{Vnode, VopUnlock};
+use crate::Kernel;
+use crate::lock::SourceLoc;
+use core::ffi::c_int;
+use core::num::NonZero;
+
+/// RAII struct to release a vnode lock when dropped.
+///
+/// This type is required by the operations that need a locked vnode (e.g.
+/// [`VopRead::new()`](super::VopRead::new())).
+pub struct LockedVnode<K: Kernel> {
+    kern: K,
+    vp: *mut K::Vnode,
+    put: bool,
+}
+
+impl<K: Kernel> LockedVnode<K> {
+    /// Locks `vp` with `vn_lock`.
+    ///
+    /// `flags` must contain either [`Kernel::LK_SHARED`] or [`Kernel::LK_EXCLUSIVE`]. The lock
+    /// will be released with `VOP_UNLOCK` when dropped without touching the reference.
+    ///
+    /// # Safety
+    /// `vp` cannot be null and must be referenced until the returned [`LockedVnode`] is dropped.
+    #[track_caller]
+    pub unsafe fn new(kern: K, vp: *mut K::Vnode, flags: c_int) -> Result<Self, NonZero<c_int>> {
+        let loc = SourceLoc::caller();
+
+        match NonZero::new(unsafe { kern.vn_lock(vp, flags, loc.file(kern), loc.line()) }) {
+            Some(e) => Err(e),
+            None => Ok(Self {
+                kern,
+                vp,
+                put: false,
+            }),
+        }
+    }
+
+    /// Takes ownership of a locked and referenced `vp` (e.g. from `VFS_ROOT` or `VOP_LOOKUP`).
+    ///
+    /// Both the lock and the reference will be released with `vput` when dropped.
+    ///
+    /// # Safety
+    /// `vp` cannot be null and must be locked by the current thread with a reference owned by the
+    /// caller.
+    pub unsafe fn from_raw(kern: K, vp: *mut K::Vnode) -> Self {
+        Self {
+            kern,
+            vp,
+            put: true,
+        }
+    }
+
+    pub fn as_ptr(&self) -> *mut K::Vnode {
+        self.vp
+    }
+}
+
+impl<K: Kernel> Drop for LockedVnode<K> {
+    fn drop(&mut self) {
+        if self.put {
+            unsafe { self.kern.vput(self.vp) };
+            return;
+        }
+
+        let mut args = unsafe { K::VopUnlock::new(self.kern, self.vp, 0) };
+        let ops = unsafe { (*self.vp).ops() };
+
+        unsafe { self.kern.vop_unlock(ops, &mut args) };
+    }
+}
@@ -1,9 +1,11 @@
 pub use self::dirent::*;
+pub use self::locked::*;
 pub use self::op::*;
 use crate::Kernel;
 use core::ffi::c_int;
 
 mod dirent;
+mod locked;
 mod op;
 
 /// Represents `vnode` structure.
@@ -1,54 +1,96 @@
+use super::LockedVnode;
 use crate::Kernel;
-use core::ffi::c_int;
+use core::ffi::{c_char, c_int};
+use core::marker::PhantomData;
+use core::ops::Deref;
 
 /// Represents `vnodeop_desc` structure.
 pub trait VnodeOp: Sized {}
 
+/// Represents `vop_lock1_args` structure.
+pub trait VopLock<K: Kernel>: Sized {
+    /// # Safety
+    /// - `vp` cannot be null.
+    /// - `file` cannot be null and must point to a null-terminated string.
+    unsafe fn new(k: K, vp: *mut K::Vnode, flags: c_int, file: *const c_char, line: c_int) -> Self;
+}
+
 /// Represents `vop_unlock_args` structure.
-pub trait VopUnlock: Sized {}
+pub trait VopUnlock<K: Kernel>: Sized {
+    /// # Safety
+    /// `vp` cannot be null and must be locked by the current thread.
+    unsafe fn new(k: K, vp: *mut K::Vnode, flags: c_int) -> Self;
+}
 
 /// Represents `vop_read_args` structure.
 pub trait VopRead<K: Kernel>: Sized {
     /// # Safety
-    /// - `vp` cannot be null and must be locked.
     /// - `uio` cannot be null.
     /// - `cred` cannot be null.
-    unsafe fn new(
+    unsafe fn new<'a>(
         k: K,
-        vp: *mut K::Vnode,
+        vp: &'a LockedVnode<K>,
         uio: *mut K::Uio,
         flags: c_int,
         cred: *mut K::Ucred,
-    ) -> Self;
+    ) -> VopArgs<'a, K, Self>;
 }
 
 /// Represents `vop_readdir_args` structure.
 pub trait VopReadDir<K: Kernel>: Sized {
     /// # Safety
-    /// - `vp` cannot be null and must be locked.
     /// - `uio` cannot be null.
     /// - `cred` cannot be null.
-    unsafe fn new(
+    unsafe fn new<'a>(
         k: K,
-        vp: *mut K::Vnode,
+        vp: &'a LockedVnode<K>,
         uio: *mut K::Uio,
         cred: *mut K::Ucred,
         eof: *mut c_int,
         ncookies: *mut c_int,
         cookies: *mut *mut u64,
-    ) -> Self;
+    ) -> VopArgs<'a, K, Self>;
 }
 
 /// Represents `vop_lookup_args` structure.
 pub trait VopLookup<K: Kernel>: Sized {
     /// # Safety
-    /// - `vp` cannot be null and must be locked.
     /// - `out` cannot be null.
     /// - `cn` cannot be null.
-    unsafe fn new(
+    unsafe fn new<'a>(
         k: K,
-        vp: *mut K::Vnode,
+        vp: &'a LockedVnode<K>,
         out: *mut *mut K::Vnode,
         cn: *mut K::ComponentName,
-    ) -> Self;
+    ) -> VopArgs<'a, K, Self>;
+}
+
+/// Arguments of a vnode operation that borrow the [`LockedVnode`] it operates on.
+///
+/// The vnode cannot be unlocked while this struct is alive.
+#[repr(transparent)]
+pub struct VopArgs<'a, K: Kernel, A> {
+    args: A,
+    vp: PhantomData<&'a LockedVnode<K>>,
+}
+
+impl<'a, K: Kernel, A> VopArgs<'a, K, A> {
+    /// # Safety
+    /// `args` must operate on `vp`.
+    pub unsafe fn new(vp: &'a LockedVnode<K>, args: A) -> Self {
+        let _ = vp;
+
+        Self {
+            args,
+            vp: PhantomData,
+        }
+    }
+}
+
+impl<K: Kernel, A> Deref for VopArgs<'_, K, A> {
+    type Target = A;
+
+    fn deref(&self) -> &Self::Target {
+        &self.args
+    }
 }